@@ -49,12 +49,43 @@ pub type MdsMatrixBabyBear = MdsMatrixMontyField31<MDSBabyBearData>;
 
 #[cfg(test)]
 mod tests {
-    use p3_field::AbstractField;
+    use p3_field::{AbstractField, PrimeField32};
+    use p3_mds::util::{apply_circulant, first_row_to_first_col};
+    use p3_monty_31::apply_circulant_monty_karat;
     use p3_symmetric::Permutation;
+    use rand::{thread_rng, Rng};
 
     use super::MdsMatrixBabyBear;
     use crate::BabyBear;
 
+    fn check_apply_circulant_monty_karat<const N: usize>() {
+        let mut rng = thread_rng();
+        let input: [BabyBear; N] = rng.gen();
+        let first_row: [u64; N] =
+            core::array::from_fn(|_| rng.gen_range(0..BabyBear::ORDER_U32 as u64));
+
+        // The canonical path works on the field elements themselves.
+        let expected = apply_circulant(&first_row, input);
+
+        let first_col = first_row_to_first_col(&first_row).map(|c| c as i64);
+        assert_eq!(apply_circulant_monty_karat(input, first_col), expected);
+
+        // Negative entries are reduced like their field counterparts.
+        let negated = apply_circulant_monty_karat(input, first_col.map(|c| -c));
+        assert_eq!(negated, expected.map(|x| -x));
+    }
+
+    #[test]
+    fn apply_circulant_monty_karat_matches_canonical() {
+        check_apply_circulant_monty_karat::<3>();
+        check_apply_circulant_monty_karat::<8>();
+        check_apply_circulant_monty_karat::<12>();
+        check_apply_circulant_monty_karat::<16>();
+        check_apply_circulant_monty_karat::<20>();
+        check_apply_circulant_monty_karat::<32>();
+        check_apply_circulant_monty_karat::<64>();
+    }
+
     #[test]
     fn babybear8() {
         let input: [BabyBear; 8] = [
@@ -163,6 +163,7 @@ mod tests {
     use p3_symmetric::Permutation;
 
     use super::*;
+    use crate::GrindingChallenger;
 
     const WIDTH: usize = 24;
     const RATE: usize = 16;
@@ -181,6 +182,18 @@ mod tests {
 
     impl CryptographicPermutation<TestArray> for TestPermutation {}
 
+    #[test]
+    fn test_grind_with_stats() {
+        let mut challenger = DuplexChallenger::<F, _, WIDTH, RATE>::new(TestPermutation {});
+        challenger.observe(F::from_canonical_u8(7));
+
+        let (witness, attempts) = challenger.clone().grind_with_stats(6);
+        assert!(challenger.clone().check_witness(6, witness));
+        assert!(attempts >= 1);
+        let witness = challenger.clone().grind(6);
+        assert!(challenger.check_witness(6, witness));
+    }
+
     #[test]
     fn test_duplex_challenger_randomized() {
         let permutation = TestPermutation {};
@@ -1,3 +1,5 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
 use p3_field::{Field, PrimeField, PrimeField32, PrimeField64};
 use p3_maybe_rayon::prelude::*;
 use p3_symmetric::CryptographicPermutation;
@@ -12,6 +14,14 @@ pub trait GrindingChallenger:
 
     fn grind(&mut self, bits: usize) -> Self::Witness;
 
+    /// Same as `grind`, but also returns the number of candidate witnesses checked, which should
+    /// be about `2^bits` on average. This helps calibrate `bits` against the time grinding takes.
+    ///
+    /// The default doesn't count them, and returns 0, which no actual search can have checked.
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        (self.grind(bits), 0)
+    }
+
     #[must_use]
     fn check_witness(&mut self, bits: usize, witness: Self::Witness) -> bool {
         self.observe(witness);
@@ -27,15 +37,12 @@ where
 {
     type Witness = F;
 
-    #[instrument(name = "grind for proof-of-work witness", skip_all)]
     fn grind(&mut self, bits: usize) -> Self::Witness {
-        let witness = (0..F::ORDER_U64)
-            .into_par_iter()
-            .map(|i| F::from_canonical_u64(i))
-            .find_any(|witness| self.clone().check_witness(bits, *witness))
-            .expect("failed to find witness");
-        assert!(self.check_witness(bits, witness));
-        witness
+        grind_prime_field(self, bits)
+    }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        grind_prime_field_with_stats(self, bits)
     }
 }
 
@@ -47,14 +54,62 @@ where
 {
     type Witness = F;
 
-    #[instrument(name = "grind for proof-of-work witness", skip_all)]
     fn grind(&mut self, bits: usize) -> Self::Witness {
-        let witness = (0..F::ORDER_U64)
-            .into_par_iter()
-            .map(F::from_canonical_u64)
-            .find_any(|witness| self.clone().check_witness(bits, *witness))
-            .expect("failed to find witness");
-        assert!(self.check_witness(bits, witness));
-        witness
+        grind_prime_field(self, bits)
     }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        grind_prime_field_with_stats(self, bits)
+    }
+}
+
+/// Search a prime field, in parallel, for a witness to a `bits`-bit proof of work. The challenger
+/// is left having observed the witness.
+#[instrument(name = "grind for proof-of-work witness", skip_all)]
+pub(crate) fn grind_prime_field<C, F>(challenger: &mut C, bits: usize) -> F
+where
+    C: GrindingChallenger<Witness = F>,
+    F: PrimeField64,
+{
+    let witness = (0..F::ORDER_U64)
+        .into_par_iter()
+        .map(F::from_canonical_u64)
+        .find_any(|witness| challenger.clone().check_witness(bits, *witness))
+        .expect("failed to find witness");
+    assert!(challenger.check_witness(bits, witness));
+    witness
+}
+
+/// The number of candidates each task of `grind_prime_field_with_stats` checks in turn, counting
+/// them locally, before adding the count to the shared total.
+const GRIND_BLOCK_LEN: u64 = 1 << 10;
+
+/// Same as `grind_prime_field`, but also returns the number of candidates checked. The candidates
+/// are searched in blocks, each counted locally and added to the total once, so that the threads
+/// don't contend for the counter on every candidate.
+#[instrument(name = "grind for proof-of-work witness", skip_all)]
+pub(crate) fn grind_prime_field_with_stats<C, F>(challenger: &mut C, bits: usize) -> (F, u64)
+where
+    C: GrindingChallenger<Witness = F>,
+    F: PrimeField64,
+{
+    let attempts = AtomicU64::new(0);
+    let witness = (0..F::ORDER_U64.div_ceil(GRIND_BLOCK_LEN))
+        .into_par_iter()
+        .map(|block| {
+            let start = block * GRIND_BLOCK_LEN;
+            let end = (start + GRIND_BLOCK_LEN).min(F::ORDER_U64);
+            let mut checked = 0;
+            let witness = (start..end).map(F::from_canonical_u64).find(|witness| {
+                checked += 1;
+                challenger.clone().check_witness(bits, *witness)
+            });
+            attempts.fetch_add(checked, Ordering::Relaxed);
+            witness
+        })
+        .find_any(Option::is_some)
+        .flatten()
+        .expect("failed to find witness");
+    assert!(challenger.check_witness(bits, witness));
+    (witness, attempts.into_inner())
 }
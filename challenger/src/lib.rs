@@ -9,6 +9,8 @@ mod grinding_challenger;
 mod hash_challenger;
 mod multi_field_challenger;
 mod serializing_challenger;
+#[cfg(feature = "transcript-recorder")]
+mod transcript_recorder;
 
 use alloc::vec::Vec;
 use core::array;
@@ -19,6 +21,8 @@ pub use hash_challenger::*;
 pub use multi_field_challenger::*;
 use p3_field::{AbstractExtensionField, Field};
 pub use serializing_challenger::*;
+#[cfg(feature = "transcript-recorder")]
+pub use transcript_recorder::*;
 
 pub trait CanObserve<T> {
     fn observe(&mut self, value: T);
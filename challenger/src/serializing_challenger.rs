@@ -2,11 +2,10 @@ use alloc::vec::Vec;
 use core::marker::PhantomData;
 
 use p3_field::{ExtensionField, PrimeField32, PrimeField64};
-use p3_maybe_rayon::prelude::*;
 use p3_symmetric::{CryptographicHasher, Hash};
 use p3_util::log2_ceil_u64;
-use tracing::instrument;
 
+use crate::grinding_challenger::{grind_prime_field, grind_prime_field_with_stats};
 use crate::{
     CanObserve, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger, HashChallenger,
 };
@@ -120,15 +119,12 @@ where
 {
     type Witness = F;
 
-    #[instrument(name = "grind for proof-of-work witness", skip_all)]
     fn grind(&mut self, bits: usize) -> Self::Witness {
-        let witness = (0..F::ORDER_U64)
-            .into_par_iter()
-            .map(|i| F::from_canonical_u64(i))
-            .find_any(|witness| self.clone().check_witness(bits, *witness))
-            .expect("failed to find witness");
-        assert!(self.check_witness(bits, witness));
-        witness
+        grind_prime_field(self, bits)
+    }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        grind_prime_field_with_stats(self, bits)
     }
 }
 
@@ -218,15 +214,12 @@ where
 {
     type Witness = F;
 
-    #[instrument(name = "grind for proof-of-work witness", skip_all)]
     fn grind(&mut self, bits: usize) -> Self::Witness {
-        let witness = (0..F::ORDER_U64)
-            .into_par_iter()
-            .map(|i| F::from_canonical_u64(i))
-            .find_any(|witness| self.clone().check_witness(bits, *witness))
-            .expect("failed to find witness");
-        assert!(self.check_witness(bits, witness));
-        witness
+        grind_prime_field(self, bits)
+    }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        grind_prime_field_with_stats(self, bits)
     }
 }
 
@@ -0,0 +1,176 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use p3_field::Field;
+
+use crate::{CanObserve, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger};
+
+/// A single interaction with a challenger, with values in their `Debug` representation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    Observe(String),
+    Sample(String),
+    SampleBits { bits: usize, value: String },
+}
+
+/// A challenger which forwards everything to an inner challenger, while recording each value
+/// observed or sampled. Passing one to both the prover and the verifier makes it possible to diff
+/// their transcripts and find exactly where they diverge.
+///
+/// Grinding is recorded as the observation of the witness followed by sampling `bits` zero bits,
+/// which is what the verifier's `check_witness` does, so that honest prover and verifier
+/// transcripts are identical.
+#[derive(Clone, Debug)]
+pub struct TranscriptRecorder<C> {
+    pub inner: C,
+    events: Vec<TranscriptEvent>,
+}
+
+impl<C> TranscriptRecorder<C> {
+    pub const fn new(inner: C) -> Self {
+        Self {
+            inner,
+            events: Vec::new(),
+        }
+    }
+
+    /// The events recorded so far, oldest first.
+    pub fn events(&self) -> &[TranscriptEvent] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<TranscriptEvent> {
+        self.events
+    }
+}
+
+impl<C, T> CanObserve<T> for TranscriptRecorder<C>
+where
+    C: CanObserve<T>,
+    T: Debug,
+{
+    fn observe(&mut self, value: T) {
+        self.events
+            .push(TranscriptEvent::Observe(format!("{:?}", value)));
+        self.inner.observe(value);
+    }
+}
+
+impl<C, T> CanSample<T> for TranscriptRecorder<C>
+where
+    C: CanSample<T>,
+    T: Debug,
+{
+    fn sample(&mut self) -> T {
+        let value = self.inner.sample();
+        self.events
+            .push(TranscriptEvent::Sample(format!("{:?}", value)));
+        value
+    }
+}
+
+impl<C, T> CanSampleBits<T> for TranscriptRecorder<C>
+where
+    C: CanSampleBits<T>,
+    T: Debug,
+{
+    fn sample_bits(&mut self, bits: usize) -> T {
+        let value = self.inner.sample_bits(bits);
+        self.events.push(TranscriptEvent::SampleBits {
+            bits,
+            value: format!("{:?}", value),
+        });
+        value
+    }
+}
+
+impl<C, F> FieldChallenger<F> for TranscriptRecorder<C>
+where
+    C: FieldChallenger<F>,
+    F: Field,
+{
+}
+
+impl<C> GrindingChallenger for TranscriptRecorder<C>
+where
+    C: GrindingChallenger,
+{
+    type Witness = C::Witness;
+
+    fn grind(&mut self, bits: usize) -> Self::Witness {
+        let witness = self.inner.grind(bits);
+        self.record_grind(bits, witness);
+        witness
+    }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (Self::Witness, u64) {
+        let (witness, attempts) = self.inner.grind_with_stats(bits);
+        self.record_grind(bits, witness);
+        (witness, attempts)
+    }
+}
+
+impl<C> TranscriptRecorder<C>
+where
+    C: GrindingChallenger,
+{
+    /// Record the events of the inner challenger's grinding, i.e. of checking `witness`.
+    fn record_grind(&mut self, bits: usize, witness: C::Witness) {
+        self.events
+            .push(TranscriptEvent::Observe(format!("{:?}", witness)));
+        self.events.push(TranscriptEvent::SampleBits {
+            bits,
+            value: format!("{:?}", 0usize),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_field::AbstractField;
+    use p3_goldilocks::Goldilocks;
+    use p3_symmetric::{CryptographicPermutation, Permutation};
+
+    use super::*;
+    use crate::DuplexChallenger;
+
+    type F = Goldilocks;
+
+    #[derive(Clone)]
+    struct TestPermutation {}
+
+    impl Permutation<[F; 8]> for TestPermutation {
+        fn permute_mut(&self, input: &mut [F; 8]) {
+            input.reverse();
+            input[0] += F::one();
+        }
+    }
+
+    impl CryptographicPermutation<[F; 8]> for TestPermutation {}
+
+    #[test]
+    fn prover_and_verifier_transcripts_match() {
+        let new_recorder =
+            || TranscriptRecorder::new(DuplexChallenger::<F, _, 8, 4>::new(TestPermutation {}));
+
+        let mut prover = new_recorder();
+        prover.observe(F::from_canonical_u8(3));
+        let beta: F = prover.sample();
+        let witness = prover.grind(2);
+        let index: usize = prover.sample_bits(5);
+
+        let mut verifier = new_recorder();
+        verifier.observe(F::from_canonical_u8(3));
+        let v_beta: F = verifier.sample();
+        assert_eq!(v_beta, beta);
+        assert!(verifier.check_witness(2, witness));
+        let v_index: usize = verifier.sample_bits(5);
+        assert_eq!(v_index, index);
+
+        assert_eq!(prover.events().len(), 5);
+        assert_eq!(prover.events()[0], TranscriptEvent::Observe("3".into()));
+        assert_eq!(prover.events(), verifier.events());
+    }
+}
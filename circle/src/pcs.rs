@@ -295,7 +295,8 @@ where
                     first_layer_siblings,
                     first_layer_proof,
                 }
-            });
+            })
+            .expect("FRI parameters are unsuitable for the committed matrices");
 
         (
             values,
@@ -333,17 +334,22 @@ where
         challenger.observe(proof.first_layer_commitment.clone());
         let bivariate_beta: Challenge = challenger.sample_ext_element();
 
-        // +1 to account for first layer
-        let log_global_max_height =
-            proof.fri_proof.commit_phase_commits.len() + self.fri_config.log_blowup + 1;
+        let log_global_max_height = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .map(|(domain, _)| domain.log_n + self.fri_config.log_blowup)
+            .max()
+            .expect("nothing to verify");
 
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
 
-        p3_fri::verifier::verify(
+        // The first layer is folded before FRI, so its inputs are half the height.
+        p3_fri::verifier::verify_with_log_max_height(
             &g,
             &self.fri_config,
             &proof.fri_proof,
+            log_global_max_height - 1,
             challenger,
             |index, input_proof| {
                 // log_height -> (alpha_offset, ro)
@@ -470,6 +476,8 @@ mod tests {
     use p3_challenger::{HashChallenger, SerializingChallenger32};
     use p3_commit::{ExtensionMmcs, Pcs};
     use p3_field::extension::BinomialExtensionField;
+    use p3_field::AbstractField;
+    use p3_fri::prover::FriProverError;
     use p3_keccak::Keccak256Hash;
     use p3_merkle_tree::FieldMerkleTreeMmcs;
     use p3_mersenne_31::Mersenne31;
@@ -504,12 +512,7 @@ mod tests {
 
         type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
 
-        let fri_config = FriConfig {
-            log_blowup: 1,
-            num_queries: 2,
-            proof_of_work_bits: 1,
-            mmcs: challenge_mmcs,
-        };
+        let fri_config = FriConfig::new(1, 0, 2, 1, challenge_mmcs);
 
         type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
         let pcs = Pcs {
@@ -542,5 +545,58 @@ mod tests {
             &mut chal,
         )
         .expect("verify err");
+
+        // The height FRI expects follows from the claimed domains, not from the proof.
+        let larger_d = <Pcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+            &pcs,
+            1 << (log_n + 1),
+        );
+        let mut chal = Challenger::from_hasher(vec![], byte_hash);
+        let result = pcs.verify(
+            vec![(
+                comm,
+                vec![(larger_d, vec![(zeta, values[0][0][0].clone())])],
+            )],
+            &proof,
+            &mut chal,
+        );
+        assert!(matches!(
+            result,
+            Err(FriError::LogMaxHeightMismatch {
+                expected: 11,
+                actual: 10,
+            })
+        ));
+    }
+
+    #[test]
+    fn circle_fri_rejects_non_constant_final_poly() {
+        type Val = Mersenne31;
+        type Challenge = BinomialExtensionField<Mersenne31, 3>;
+
+        type ByteHash = Keccak256Hash;
+        type FieldHash = SerializingHasher32<ByteHash>;
+        let byte_hash = ByteHash {};
+        type MyCompress = CompressionFunctionFromHasher<u8, ByteHash, 2, 32>;
+        type ValMmcs = FieldMerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+        let val_mmcs = ValMmcs::new(FieldHash::new(byte_hash), MyCompress::new(byte_hash));
+        type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+        type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+
+        // The circle folding only supports constant final polynomials, so this config is refused
+        // up front rather than failing an assertion once the commit phase is done.
+        let fri_config = FriConfig::new(1, 1, 2, 1, ChallengeMmcs::new(val_mmcs));
+        let g: CircleFriGenericConfig<Val, (), ()> = CircleFriGenericConfig(PhantomData);
+        let result = p3_fri::prover::prove(
+            &g,
+            &fri_config,
+            vec![vec![Challenge::zero(); 1 << 6]],
+            &mut Challenger::from_hasher(vec![], byte_hash),
+            |_| unreachable!("no queries should be answered"),
+        );
+        assert_eq!(
+            result.err(),
+            Some(FriProverError::UnsupportedFinalPolyLen { len: 2 })
+        );
     }
 }
@@ -32,6 +32,8 @@ where
     type Commitment = InnerMmcs::Commitment;
     type Proof = InnerMmcs::Proof;
     type Error = InnerMmcs::Error;
+    /// The base field coordinates of the opened rows, and the inner MMCS's own scratch.
+    type OpenScratch = (Vec<F>, InnerMmcs::OpenScratch);
 
     fn commit<M: Matrix<EF>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>) {
         self.inner
@@ -44,11 +46,38 @@ where
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<EF>>, Self::Proof) {
         let (opened_base_values, proof) = self.inner.open_batch(index, prover_data);
+        let opened_ext_values = opened_base_values.into_iter().map(ext_row).collect();
+        (opened_ext_values, proof)
+    }
+
+    fn open_batch_into<M: Matrix<EF>>(
+        &self,
+        index: usize,
+        prover_data: &Self::ProverData<M>,
+        opened_values: &mut Vec<EF>,
+        scratch: &mut Self::OpenScratch,
+    ) -> Self::Proof {
+        let (opened_base_values, inner_scratch) = scratch;
+        let proof =
+            self.inner
+                .open_batch_into(index, prover_data, opened_base_values, inner_scratch);
+        opened_values.clear();
+        opened_values.extend(opened_base_values.chunks(EF::D).map(EF::from_base_slice));
+        proof
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn open_multi_batch<M: Matrix<EF>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> Option<(Vec<Vec<Vec<EF>>>, Self::Proof)> {
+        let (opened_base_values, proof) = self.inner.open_multi_batch(indices, prover_data)?;
         let opened_ext_values = opened_base_values
             .into_iter()
-            .map(|row| row.chunks(EF::D).map(EF::from_base_slice).collect())
+            .map(|rows| rows.into_iter().map(ext_row).collect())
             .collect();
-        (opened_ext_values, proof)
+        Some((opened_ext_values, proof))
     }
 
     fn get_matrices<'a, M: Matrix<EF>>(&self, prover_data: &'a Self::ProverData<M>) -> Vec<&'a M> {
@@ -67,23 +96,59 @@ where
         opened_values: &[Vec<EF>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
-        let opened_base_values: Vec<Vec<F>> = opened_values
+        let opened_base_values: Vec<Vec<F>> =
+            opened_values.iter().map(|row| base_row(row)).collect();
+        self.inner.verify_batch(
+            commit,
+            &base_dimensions::<EF, F>(dimensions),
+            index,
+            &opened_base_values,
+            proof,
+        )
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<EF>>],
+        proof: &Self::Proof,
+    ) -> Option<Result<(), Self::Error>> {
+        let opened_base_values: Vec<Vec<Vec<F>>> = opened_values
             .iter()
-            .map(|row| {
-                row.iter()
-                    .flat_map(|el| el.as_base_slice())
-                    .copied()
-                    .collect()
-            })
+            .map(|rows| rows.iter().map(|row| base_row(row)).collect())
             .collect();
-        let base_dimensions = dimensions
-            .iter()
-            .map(|dim| Dimensions {
-                width: dim.width * EF::D,
-                height: dim.height,
-            })
-            .collect::<Vec<_>>();
-        self.inner
-            .verify_batch(commit, &base_dimensions, index, &opened_base_values, proof)
+        self.inner.verify_multi_batch(
+            commit,
+            &base_dimensions::<EF, F>(dimensions),
+            indices,
+            &opened_base_values,
+            proof,
+        )
     }
 }
+
+/// Reinterpret a row of base field values as extension field values.
+fn ext_row<F: Field, EF: ExtensionField<F>>(row: Vec<F>) -> Vec<EF> {
+    row.chunks(EF::D).map(EF::from_base_slice).collect()
+}
+
+/// Flatten a row of extension field values into their base field coordinates.
+fn base_row<F: Field, EF: ExtensionField<F>>(row: &[EF]) -> Vec<F> {
+    row.iter()
+        .flat_map(|el| el.as_base_slice())
+        .copied()
+        .collect()
+}
+
+/// The dimensions of the flattened base field matrices underlying extension field matrices.
+fn base_dimensions<EF: ExtensionField<F>, F: Field>(dimensions: &[Dimensions]) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dim| Dimensions {
+            width: dim.width * EF::D,
+            height: dim.height,
+        })
+        .collect()
+}
@@ -20,6 +20,9 @@ pub trait Mmcs<T: Send + Sync>: Clone {
     type Commitment: Clone + Serialize + DeserializeOwned;
     type Proof: Clone + Serialize + DeserializeOwned;
     type Error: Debug;
+    /// Buffers `open_batch_into` may reuse between calls, e.g. for rows opened in another
+    /// representation before being converted. Callers keep one alongside `opened_values`.
+    type OpenScratch: Default;
 
     fn commit<M: Matrix<T>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>);
 
@@ -44,6 +47,38 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<T>>, Self::Proof);
 
+    /// Like `open_batch`, but writes the opened rows, concatenated in the same order, into
+    /// `opened_values`, which is cleared first. This lets callers opening many batches reuse a
+    /// single buffer (and `scratch`) rather than allocating a `Vec` per opened row.
+    fn open_batch_into<M: Matrix<T>>(
+        &self,
+        index: usize,
+        prover_data: &Self::ProverData<M>,
+        opened_values: &mut Vec<T>,
+        _scratch: &mut Self::OpenScratch,
+    ) -> Self::Proof {
+        let (opened_rows, proof) = self.open_batch(index, prover_data);
+        opened_values.clear();
+        opened_values.extend(opened_rows.into_iter().flatten());
+        proof
+    }
+
+    /// Opens the rows at each of `indices`, following the semantics of `open_batch`, with a single
+    /// proof covering all of them. Returns `(openings, proof)`, where `openings[k]` holds the rows
+    /// opened at `indices[k]`. Indices may repeat.
+    ///
+    /// Schemes such as vector commitments can prove many openings at once far more cheaply than
+    /// one at a time, and can override this, along with `verify_multi_batch`. The default returns
+    /// `None`, meaning the scheme can only open one index at a time, with `open_batch`.
+    #[allow(clippy::type_complexity)]
+    fn open_multi_batch<M: Matrix<T>>(
+        &self,
+        _indices: &[usize],
+        _prover_data: &Self::ProverData<M>,
+    ) -> Option<(Vec<Vec<Vec<T>>>, Self::Proof)> {
+        None
+    }
+
     /// Get the matrices that were committed to.
     fn get_matrices<'a, M: Matrix<T>>(&self, prover_data: &'a Self::ProverData<M>) -> Vec<&'a M>;
 
@@ -75,4 +110,45 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         opened_values: &[Vec<T>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error>;
+
+    /// Verify an opening made by `open_multi_batch`, where `opened_values[k]` holds the rows
+    /// opened at `indices[k]`. Returns `None` if the scheme doesn't support opening several
+    /// indices at once, i.e. if `open_multi_batch` returns `None`.
+    fn verify_multi_batch(
+        &self,
+        _commit: &Self::Commitment,
+        _dimensions: &[Dimensions],
+        _indices: &[usize],
+        _opened_values: &[Vec<Vec<T>>],
+        _proof: &Self::Proof,
+    ) -> Option<Result<(), Self::Error>> {
+        None
+    }
+
+    /// Verify several batch openings, possibly against different commitments, in one call.
+    /// Each element of `openings` holds the arguments of a single `verify_batch` call, and this
+    /// succeeds only if every one of them would.
+    ///
+    /// The default implementation simply verifies each opening in turn, but schemes which can
+    /// share work between openings may override it.
+    fn verify_batches(&self, openings: &[BatchOpeningRef<'_, T, Self>]) -> Result<(), Self::Error> {
+        openings.iter().try_for_each(|opening| {
+            self.verify_batch(
+                opening.commit,
+                opening.dimensions,
+                opening.index,
+                opening.opened_values,
+                opening.proof,
+            )
+        })
+    }
+}
+
+/// The arguments of a single `Mmcs::verify_batch` call, as passed to `Mmcs::verify_batches`.
+pub struct BatchOpeningRef<'a, T: Send + Sync, M: Mmcs<T>> {
+    pub commit: &'a M::Commitment,
+    pub dimensions: &'a [Dimensions],
+    pub index: usize,
+    pub opened_values: &'a [Vec<T>],
+    pub proof: &'a M::Proof,
 }
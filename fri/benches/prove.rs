@@ -0,0 +1,64 @@
+use core::marker::PhantomData;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractField, Field};
+use p3_fri::{prover, FriConfig, TwoAdicFriGenericConfig};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::util::reverse_matrix_index_bits;
+use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::thread_rng;
+
+type Val = BabyBear;
+type Challenge = BinomialExtensionField<Val, 4>;
+
+type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixBabyBear, 16, 7>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    FieldMerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+/// Proves a single random codeword of `2^log_size` evaluations, so that the query phase opens paths
+/// in trees of up to `2^(log_size - 1)` leaves.
+fn bench_prove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fri_prove");
+    group.sample_size(10);
+
+    let mut rng = thread_rng();
+    let perm = Perm::new_from_rng_128(
+        Poseidon2ExternalMatrixGeneral,
+        DiffusionMatrixBabyBear::default(),
+        &mut rng,
+    );
+    let mmcs = ChallengeMmcs::new(ValMmcs::new(
+        MyHash::new(perm.clone()),
+        MyCompress::new(perm.clone()),
+    ));
+    let config = FriConfig::new(1, 0, 100, 0, mmcs);
+    let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+    for log_size in [18, 20, 22] {
+        let evals = RowMajorMatrix::<Challenge>::rand(&mut rng, 1 << (log_size - 1), 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Challenge::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input = lde.values;
+
+        group.bench_function(BenchmarkId::from_parameter(log_size), |b| {
+            b.iter(|| {
+                let mut challenger = Challenger::new(perm.clone());
+                prover::prove(&g, &config, vec![input.clone()], &mut challenger, |_| ())
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_prove);
+criterion_main!(benches);
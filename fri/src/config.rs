@@ -1,22 +1,421 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
+use core::time::Duration;
 
-use p3_field::Field;
+use p3_challenger::{FieldChallenger, GrindingChallenger};
+use p3_field::{ExtensionField, Field};
+use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 
 #[derive(Debug)]
-pub struct FriConfig<M> {
+pub struct FriConfig<M, Gr = ChallengerGrinding> {
+    /// The log2 of the blowup factor of the committed codewords, so `blowup() == 1 << log_blowup`.
     pub log_blowup: usize,
+    /// The log2 of the number of coefficients of the final polynomial, so
+    /// `final_poly_len() == 1 << log_final_poly_len`. Folding stops once the codeword has
+    /// `blowup() * final_poly_len()` evaluations, at which point the prover sends the remaining
+    /// polynomial in the clear. Zero means folding all the way down to a constant.
+    pub log_final_poly_len: usize,
     pub num_queries: usize,
+    /// The difficulty of the proof of work, in bits. With `grind_time_budget` set this is only a
+    /// floor, and the prover may grind to a higher difficulty. Zero means no proof of work.
     pub proof_of_work_bits: usize,
+    /// When the proof of work is done, relative to observing the final polynomial.
+    pub pow_stage: PowStage,
+    /// The difficulty, in bits, of a second proof of work done once the query indices have been
+    /// sampled. Zero, the usual choice, means there is none.
+    ///
+    /// Otherwise, after sampling every query index, the prover observes the sibling values of each
+    /// query's commit phase openings, query by query and round by round, then grinds for this
+    /// many bits. The verifier observes the same values from the proof, checks the witness, and
+    /// only then checks the queries. This binds the query responses into the transcript, so that a
+    /// cheating prover who wants to try different responses to the same queries has to grind
+    /// afresh for each. It doesn't change the query indices, so the soundness gained is modest.
+    /// The input openings are generic, so they aren't observed.
+    pub post_query_pow_bits: usize,
+    /// If set, the commit phase folds exactly this many times, rather than until
+    /// `blowup() * final_poly_len()` evaluations remain, so that proofs for inputs of different
+    /// sizes have the same shape. The codeword left after the last fold must then have between
+    /// `blowup()` and `blowup() * final_poly_len()` evaluations, so the final polynomial may have
+    /// fewer than `final_poly_len()` coefficients; see `pad_final_poly`.
+    pub fixed_rounds: Option<usize>,
+    /// How the proof of work witness is found and checked.
+    pub grinding: Gr,
+    /// If set, the prover may keep grinding to higher difficulties than `proof_of_work_bits` for
+    /// up to this long, as decided by `GrindingStrategy::grind_with_budget`. The difficulty
+    /// reached is recorded in the proof, and the verifier checks the witness against it, only
+    /// requiring it to be at least `proof_of_work_bits`.
+    pub grind_time_budget: Option<Duration>,
+    /// If set, the most evaluations the commit phase may commit to across all of its rounds. The
+    /// prover checks this before each commitment, and fails with
+    /// `FriProverError::CommitBudgetExceeded` rather than exceed it, so that one oversized proof
+    /// can't monopolize a shared prover's memory.
+    pub max_committed_elements: Option<usize>,
+    /// If set, the final polynomial is also committed with `mmcs`, as a single row of its
+    /// coefficients, and the transcript observes that commitment rather than
+    /// the coefficients, so that the final polynomial is handled like every other round, e.g. by
+    /// a recursive verifier. This costs one extra commitment and an opening proof per FRI proof
+    /// (the opening of a single-row matrix has an empty Merkle path with the default MMCS), on
+    /// top of the coefficients, which the proof still carries.
+    pub commit_final_poly: bool,
+    /// If set, each commit phase round also commits to the inputs mixed into the codeword after
+    /// its fold, in rows as wide as the codeword's, alongside the codeword itself. Each query then
+    /// opens their rows too, and the verifier checks the opened values against the inputs' reduced
+    /// openings, so that every input is bound by a commit phase commitment before the challenge it
+    /// is mixed in with is sampled, however the PCS opens it. This costs one sibling value per
+    /// committed input per round in each query proof.
+    pub commit_mixed_inputs: bool,
+    /// If set, the final polynomial in the proof is padded with zeros to exactly
+    /// `final_poly_len()` coefficients, whatever the size of the input, e.g. so that a recursive
+    /// verifier can take it as a fixed-size array. Otherwise it has one coefficient per
+    /// `blowup()` evaluations of the final codeword, which is fewer than `final_poly_len()` when
+    /// `fixed_rounds` leaves a shorter codeword. The padding is cosmetic, since trailing zero
+    /// coefficients don't change the polynomial, and the verifier still checks the true degree
+    /// bound, rejecting nonzero padding.
+    pub pad_final_poly: bool,
+    /// If set, the first commit phase layer is committed, opened and verified with this rather
+    /// than `mmcs`, e.g. to use a hash suited to the large first layer and a cheaper one for the
+    /// small layers after it. It has the same type as `mmcs`, so the commitments and opening
+    /// proofs of every round have the same types.
+    pub first_layer_mmcs: Option<M>,
     pub mmcs: M,
 }
 
+/// The point in the FRI transcript at which the prover grinds, and the verifier checks, the proof
+/// of work witness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowStage {
+    /// Grind as soon as the last commit phase commitment has been observed, before the final
+    /// polynomial. The witness then doesn't depend on the final polynomial, so a cheating prover
+    /// can try many final polynomials (each giving different query indices) for the cost of a
+    /// single grind; the proof of work only protects the commit phase challenges.
+    AfterCommitPhase,
+    /// Grind after observing the final polynomial, just before sampling the query indices. The
+    /// witness binds the whole commit phase, so every attempt at finding favourable query indices
+    /// costs a fresh grind. This is the usual choice.
+    BeforeQueries,
+}
+
+/// The proof of work done by the FRI prover, and checked by the verifier, which need not use the
+/// same hash as the Fiat-Shamir challenger.
+///
+/// The proof of work only helps soundness if the witness depends on the whole transcript so far,
+/// so that grinding can't start before the values it protects are fixed. `grind` must therefore
+/// derive its puzzle from the challenger's state, e.g. by sampling from it, and both `grind` and
+/// `check_witness` must then observe the witness, so that the query indices depend on it. The two
+/// must leave the challenger in the same state.
+pub trait GrindingStrategy<Challenger> {
+    type Witness: Clone;
+
+    /// Find a witness for a `bits`-bit proof of work bound to the state of `challenger`.
+    fn grind(&self, challenger: &mut Challenger, bits: usize) -> Self::Witness;
+
+    /// Check a witness produced by `grind` from a challenger in the same state.
+    fn check_witness(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+        witness: Self::Witness,
+    ) -> bool;
+
+    /// Same as `grind`, but also returns the number of candidate witnesses checked, if the
+    /// strategy keeps track of it. The default doesn't.
+    fn grind_with_stats(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+    ) -> (Self::Witness, Option<u64>) {
+        (self.grind(challenger, bits), None)
+    }
+
+    /// Same as `grind_with_stats`, but also returns the difficulty the witness actually meets, if
+    /// the strategy can tell. A witness found for `bits` bits often happens to meet a higher
+    /// difficulty too, but one which never does suggests the proof of work is easier than it
+    /// should be. The default can't tell.
+    fn grind_with_achieved_bits(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+    ) -> (Self::Witness, Option<u64>, Option<usize>) {
+        let (witness, attempts) = self.grind_with_stats(challenger, bits);
+        (witness, attempts, None)
+    }
+
+    /// Find a witness for a proof of work of at least `min_bits` bits, grinding to higher
+    /// difficulties while `budget` allows, and return the difficulty reached with the witness and,
+    /// if the strategy keeps track of it, the number of candidate witnesses checked. The
+    /// challenger must be left as `grind` would leave it for that difficulty, so that
+    /// `check_witness` accepts the witness at the returned difficulty.
+    ///
+    /// Returns `None`, leaving the challenger untouched, if the strategy can't honour the budget,
+    /// and the prover then fails with `FriProverError::GrindTimeBudgetUnsupported`. There is no
+    /// clock in `no_std`, so the default does this; strategies which can measure time should
+    /// override it.
+    fn grind_with_budget(
+        &self,
+        _challenger: &mut Challenger,
+        _min_bits: usize,
+        _budget: Duration,
+    ) -> Option<(usize, Self::Witness, Option<u64>)> {
+        None
+    }
+}
+
+/// A source of the time elapsed since some fixed instant, used to grind within a
+/// `grind_time_budget`. There is no clock in `no_std`, so one has to be supplied, e.g. measuring
+/// from a `std::time::Instant`.
+pub trait GrindClock {
+    /// The time elapsed since the clock's fixed instant, or `None` if it can't tell.
+    fn now(&self) -> Option<Duration>;
+}
+
+/// The clock of a strategy which can't measure time, so can't grind within a time budget.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoClock;
+
+impl GrindClock for NoClock {
+    fn now(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Grind with the challenger's own hash, as provided by `GrindingChallenger`.
+///
+/// Given a `grind_time_budget`, it grinds to ever higher difficulties, as measured by `clock`,
+/// and keeps the witness of the highest. Each difficulty takes about twice as long as the one
+/// before, so it only starts on the next one if that is expected to finish within the budget.
+/// The default `NoClock` can't do this, and the prover then fails with
+/// `FriProverError::GrindTimeBudgetUnsupported`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChallengerGrinding<Clock = NoClock> {
+    pub clock: Clock,
+}
+
+/// The most bits `sample_bits` can be asked for when checking a witness: one less than the size
+/// of the field the witness is drawn from.
+fn max_pow_bits<Challenger: GrindingChallenger>() -> usize {
+    (<Challenger::Witness as Field>::bits() - 1).min(usize::BITS as usize - 1)
+}
+
+/// The number of attempts reported by `GrindingChallenger::grind_with_stats`, or `None` if the
+/// challenger doesn't count them, in which case it reports 0.
+fn counted(attempts: u64) -> Option<u64> {
+    (attempts > 0).then_some(attempts)
+}
+
+/// The highest difficulty, of at least `bits`, which `witness` meets for a challenger in the state
+/// of `start`.
+fn achieved_pow_bits<Challenger: GrindingChallenger>(
+    start: &Challenger,
+    bits: usize,
+    witness: Challenger::Witness,
+) -> usize {
+    (bits + 1..=max_pow_bits::<Challenger>())
+        .take_while(|&b| start.clone().check_witness(b, witness))
+        .last()
+        .unwrap_or(bits)
+}
+
+impl<Challenger, Clock> GrindingStrategy<Challenger> for ChallengerGrinding<Clock>
+where
+    Challenger: GrindingChallenger,
+    Clock: GrindClock,
+{
+    type Witness = Challenger::Witness;
+
+    fn grind(&self, challenger: &mut Challenger, bits: usize) -> Self::Witness {
+        challenger.grind(bits)
+    }
+
+    fn grind_with_stats(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+    ) -> (Self::Witness, Option<u64>) {
+        let (witness, attempts) = challenger.grind_with_stats(bits);
+        (witness, counted(attempts))
+    }
+
+    fn grind_with_achieved_bits(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+    ) -> (Self::Witness, Option<u64>, Option<usize>) {
+        let start = challenger.clone();
+        let (witness, attempts) = challenger.grind_with_stats(bits);
+        let achieved_bits = achieved_pow_bits(&start, bits, witness);
+        (witness, counted(attempts), Some(achieved_bits))
+    }
+
+    fn grind_with_budget(
+        &self,
+        challenger: &mut Challenger,
+        min_bits: usize,
+        budget: Duration,
+    ) -> Option<(usize, Self::Witness, Option<u64>)> {
+        let start_time = self.clock.now()?;
+        let deadline = start_time + budget;
+
+        let (mut witness, mut attempts) = challenger.clone().grind_with_stats(min_bits);
+        let mut bits = achieved_pow_bits(challenger, min_bits, witness);
+        let mut level_start = start_time;
+        loop {
+            let now = self.clock.now()?;
+            let last_level_time = now.saturating_sub(level_start);
+            if bits >= max_pow_bits::<Challenger>() || now + 2 * last_level_time > deadline {
+                break;
+            }
+            level_start = now;
+            let (next_witness, next_attempts) = challenger.clone().grind_with_stats(bits + 1);
+            witness = next_witness;
+            attempts += next_attempts;
+            bits = achieved_pow_bits(challenger, bits + 1, witness);
+        }
+
+        // Leave the challenger as grinding to `bits` directly would have.
+        let valid = challenger.check_witness(bits, witness);
+        debug_assert!(valid);
+        Some((bits, witness, counted(attempts)))
+    }
+
+    fn check_witness(
+        &self,
+        challenger: &mut Challenger,
+        bits: usize,
+        witness: Self::Witness,
+    ) -> bool {
+        challenger.check_witness(bits, witness)
+    }
+}
+
 impl<M> FriConfig<M> {
+    /// A config with the given parameters, and the usual choices for everything else: a single
+    /// proof of work, done with the challenger just before the queries and without a time budget,
+    /// folding until `blowup() * final_poly_len()` evaluations remain, no limit on the number of
+    /// committed evaluations, a final polynomial sent in the clear and padded to
+    /// `final_poly_len()` coefficients, only the codeword committed in each commit phase round,
+    /// and `mmcs` for every round. The fields are
+    /// public, so any of these can be changed afterwards.
+    ///
+    /// Panics if `blowup() * final_poly_len()` doesn't fit in a `usize`, as such a config could
+    /// never fold anything.
+    pub fn new(
+        log_blowup: usize,
+        log_final_poly_len: usize,
+        num_queries: usize,
+        proof_of_work_bits: usize,
+        mmcs: M,
+    ) -> Self {
+        assert!(
+            log_blowup + log_final_poly_len < usize::BITS as usize,
+            "log_blowup ({}) + log_final_poly_len ({}) is too large",
+            log_blowup,
+            log_final_poly_len
+        );
+        Self {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+            pow_stage: PowStage::BeforeQueries,
+            post_query_pow_bits: 0,
+            fixed_rounds: None,
+            grinding: ChallengerGrinding::default(),
+            grind_time_budget: None,
+            max_committed_elements: None,
+            commit_final_poly: false,
+            commit_mixed_inputs: false,
+            pad_final_poly: true,
+            first_layer_mmcs: None,
+            mmcs,
+        }
+    }
+}
+
+impl<M, Gr> FriConfig<M, Gr> {
+    /// The same config, except that the proof of work is done with `grinding`.
+    pub fn with_grinding<NewGr>(self, grinding: NewGr) -> FriConfig<M, NewGr> {
+        // Changing the type parameter rules out `..self`, so the fields are moved across by
+        // name; destructuring without `..` makes a new field a compile error here.
+        let FriConfig {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+            pow_stage,
+            post_query_pow_bits,
+            fixed_rounds,
+            grinding: _,
+            grind_time_budget,
+            max_committed_elements,
+            commit_final_poly,
+            commit_mixed_inputs,
+            pad_final_poly,
+            first_layer_mmcs,
+            mmcs,
+        } = self;
+        FriConfig {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+            pow_stage,
+            post_query_pow_bits,
+            fixed_rounds,
+            grinding,
+            grind_time_budget,
+            max_committed_elements,
+            commit_final_poly,
+            commit_mixed_inputs,
+            pad_final_poly,
+            first_layer_mmcs,
+            mmcs,
+        }
+    }
+
     pub const fn blowup(&self) -> usize {
         1 << self.log_blowup
     }
 
+    /// The parameters which the prover and verifier must agree on, for use with the given PCS.
+    pub fn params<F: Field, G: FriGenericConfig<F>>(&self, g: &G) -> FriParams {
+        FriParams {
+            log_blowup: self.log_blowup,
+            log_final_poly_len: self.log_final_poly_len,
+            num_queries: self.num_queries,
+            pow_bits: self.proof_of_work_bits,
+            extra_index_bits: g.extra_query_index_bits(),
+            log_arity: g.log_arity(),
+        }
+    }
+
+    pub const fn final_poly_len(&self) -> usize {
+        1 << self.log_final_poly_len
+    }
+
+    /// The MMCS used for the given commit phase round: `first_layer_mmcs` for round 0, if set,
+    /// and `mmcs` otherwise.
+    pub fn commit_phase_mmcs(&self, round: usize) -> &M {
+        match (round, &self.first_layer_mmcs) {
+            (0, Some(first_layer_mmcs)) => first_layer_mmcs,
+            _ => &self.mmcs,
+        }
+    }
+
+    /// The log2 of the number of evaluations left once the commit phase stops folding an input
+    /// with `2^log_max_height` evaluations, each round folding by `2^log_arity`, or `None` if
+    /// `fixed_rounds` doesn't suit such an input.
+    pub fn log_final_height(&self, log_max_height: usize, log_arity: usize) -> Option<usize> {
+        let log_max_final_height = self.log_blowup + self.log_final_poly_len;
+        match self.fixed_rounds {
+            None => Some(log_max_final_height),
+            Some(rounds) => log_max_height
+                .checked_sub(rounds * log_arity)
+                .filter(|h| (self.log_blowup..=log_max_final_height).contains(h)),
+        }
+    }
+
     /// Returns the soundness bits of this FRI instance based on the
     /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
     ///
@@ -27,6 +426,54 @@ impl<M> FriConfig<M> {
     }
 }
 
+/// The parameters of a FRI instance which the prover and the verifier must agree on, derived once
+/// from a `FriConfig` and the PCS's `FriGenericConfig` by `FriConfig::params`. Both sides read
+/// them from here, so that they can't drift apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FriParams {
+    pub log_blowup: usize,
+    pub log_final_poly_len: usize,
+    pub num_queries: usize,
+    /// The minimum difficulty of the proof of work, in bits.
+    pub pow_bits: usize,
+    /// The number of extra low bits of each query index, which FRI passes to the PCS but
+    /// otherwise ignores; see `FriGenericConfig::extra_query_index_bits`.
+    pub extra_index_bits: usize,
+    /// The log2 of the folding arity; see `FriGenericConfig::log_arity`.
+    pub log_arity: usize,
+}
+
+impl FriParams {
+    pub const fn blowup(&self) -> usize {
+        1 << self.log_blowup
+    }
+
+    pub const fn final_poly_len(&self) -> usize {
+        1 << self.log_final_poly_len
+    }
+
+    /// The number of bits sampled for each query index, for an input with `2^log_max_height`
+    /// evaluations.
+    pub const fn query_index_bits(&self, log_max_height: usize) -> usize {
+        log_max_height + self.extra_index_bits
+    }
+}
+
+/// The number of commit phase rounds, i.e. of commit phase commitments, in a proof for an input
+/// of `2^log_max_height` evaluations, when `fixed_rounds` isn't set and the folding arity is the
+/// default of 2. Mixing in smaller inputs
+/// doesn't change it. This lets e.g. a recursive verifier be sized without a proof at hand.
+///
+/// An input no larger than `blowup() * final_poly_len()` isn't folded at all, in which case this
+/// returns zero, although the prover then rejects the input.
+pub const fn num_commit_rounds(
+    log_max_height: usize,
+    log_blowup: usize,
+    log_final_poly_len: usize,
+) -> usize {
+    log_max_height.saturating_sub(log_blowup + log_final_poly_len)
+}
+
 /// Whereas `FriConfig` encompasses parameters the end user can set, `FriGenericConfig` is
 /// set by the PCS calling FRI, and abstracts over implementation details of the PCS.
 pub trait FriGenericConfig<F: Field> {
@@ -38,8 +485,7 @@ pub trait FriGenericConfig<F: Field> {
     fn extra_query_index_bits(&self) -> usize;
 
     /// Fold a row, returning a single column.
-    /// Right now the input row will always be 2 columns wide,
-    /// but we may support higher folding arity in the future.
+    /// The input row is `2^log_arity()` columns wide.
     fn fold_row(
         &self,
         index: usize,
@@ -50,4 +496,146 @@ pub trait FriGenericConfig<F: Field> {
 
     /// Same as applying fold_row to every row, possibly faster.
     fn fold_matrix<M: Matrix<F>>(&self, beta: F, m: M) -> Vec<F>;
+
+    /// The log2 of the folding arity, i.e. of the number of sibling evaluations in each committed
+    /// row, which each commit phase round folds into one. The largest input must then be folded
+    /// down to the final codeword in whole rounds, and every other input must have a length the
+    /// codeword is folded to.
+    ///
+    /// The default is 1, folding pairs of evaluations.
+    fn log_arity(&self) -> usize {
+        1
+    }
+
+    /// The number of folding challenges sampled in each commit phase round, all of which are
+    /// passed to `fold_row_with_betas` and `fold_matrix_with_betas`. This is one for arity 2
+    /// folding, but e.g. folding with arity 4 in one round might use two.
+    fn challenges_per_round(&self) -> usize {
+        1
+    }
+
+    /// Fold a row using all of a round's `challenges_per_round()` challenges.
+    ///
+    /// The default implementation supports a single challenge, and defers to `fold_row`.
+    fn fold_row_with_betas(
+        &self,
+        index: usize,
+        log_height: usize,
+        betas: &[F],
+        evals: impl Iterator<Item = F>,
+    ) -> F {
+        assert_eq!(betas.len(), 1);
+        self.fold_row(index, log_height, betas[0], evals)
+    }
+
+    /// Same as applying fold_row_with_betas to every row, possibly faster.
+    ///
+    /// The default implementation supports a single challenge, and defers to `fold_matrix`.
+    fn fold_matrix_with_betas<M: Matrix<F>>(&self, betas: &[F], m: M) -> Vec<F> {
+        assert_eq!(betas.len(), 1);
+        self.fold_matrix(betas[0], m)
+    }
+
+    /// Sample the challenge used to mix inputs into the codeword folded in a commit phase round,
+    /// or `None` if mixing doesn't use one.
+    ///
+    /// Both the prover and the verifier call this once per round, right after sampling the
+    /// round's folding challenges and before observing the next round's commitment, whether or
+    /// not an input is mixed in that round. The default samples nothing.
+    fn sample_mix_challenge<Val, Challenger>(&self, _challenger: &mut Challenger) -> Option<F>
+    where
+        Val: Field,
+        F: ExtensionField<Val>,
+        Challenger: FieldChallenger<Val>,
+    {
+        None
+    }
+
+    /// Mix an evaluation of an input into the corresponding evaluation of the folded codeword it
+    /// has the same height as, using the challenge returned by `sample_mix_challenge` in the
+    /// round that produced the folded codeword.
+    ///
+    /// The first input is the initial codeword rather than being mixed into anything. The
+    /// default adds the input, ignoring the challenge.
+    fn mix_input(&self, folded: F, input: F, _mix_challenge: Option<F>) -> F {
+        folded + input
+    }
+
+    /// The matrix to commit to in a commit phase round, given the round's codeword `evals` with
+    /// each row holding `2^log_arity()` sibling evaluations, or `None` to commit to `evals` itself.
+    ///
+    /// This lets a config commit to another representation of each row, e.g. the coefficients of
+    /// the line through them, while folding is still done on the evaluations. Row `i` of the
+    /// result must equal `encode_commit_phase_row(i, log_height, row_i)` where `row_i` is row `i`
+    /// of `evals` and `log_height` is the log2 of its height, since the verifier checks openings
+    /// against encoded rows, and `decode_commit_phase_row` must invert the encoding, since the
+    /// prover decodes the opened rows to find the sibling evaluations it sends.
+    ///
+    /// The default commits to the evaluations.
+    fn commit_phase_matrix(&self, _evals: &RowMajorMatrix<F>) -> Option<RowMajorMatrix<F>> {
+        None
+    }
+
+    /// Encode a row of sibling evaluations as committed by `commit_phase_matrix`. The row has
+    /// index `index` in a codeword of `2^log_height` rows, as for `fold_row`. The encoded row may
+    /// be wider than the evaluations, but every row must be encoded to the same width, as the
+    /// verifier derives the committed matrix's width from it.
+    ///
+    /// The default is the identity, matching the default `commit_phase_matrix`.
+    fn encode_commit_phase_row(&self, _index: usize, _log_height: usize, row: Vec<F>) -> Vec<F> {
+        row
+    }
+
+    /// The inverse of `encode_commit_phase_row`, applied in place. The evaluations must be left
+    /// at the start of the row.
+    fn decode_commit_phase_row(&self, _index: usize, _log_height: usize, _row: &mut [F]) {}
+
+    /// The most coefficients a final polynomial may have, padding included, for
+    /// `interpolate_final_poly` and `eval_final_poly` to support it. The prover fails with
+    /// `FriProverError::UnsupportedFinalPolyLen`, and the verifier rejects the proof, rather than
+    /// pass them a longer one.
+    ///
+    /// The default is one, matching the default `interpolate_final_poly` and `eval_final_poly`.
+    fn max_final_poly_len(&self) -> usize {
+        1
+    }
+
+    /// Interpolate the evaluations left after the last fold, in the order produced by
+    /// `fold_matrix`, returning the coefficients of the final polynomial, lowest degree first.
+    ///
+    /// The default implementation only supports constant final polynomials, i.e.
+    /// `log_final_poly_len == 0`, and requires all evaluations to agree.
+    fn interpolate_final_poly(&self, evals: Vec<F>) -> Vec<F> {
+        let constant = evals[0];
+        assert!(
+            evals.iter().all(|&e| e == constant),
+            "only constant final polynomials are supported"
+        );
+        vec![constant]
+    }
+
+    /// Evaluate the final polynomial, given by its coefficients, at the point of the final
+    /// (folded) domain of size `2^log_height` which corresponds to `index`. This must agree with
+    /// `interpolate_final_poly`.
+    ///
+    /// The default implementation only supports constant final polynomials.
+    fn eval_final_poly(&self, _index: usize, _log_height: usize, final_poly: &[F]) -> F {
+        assert_eq!(
+            final_poly.len(),
+            1,
+            "only constant final polynomials are supported"
+        );
+        final_poly[0]
+    }
+}
+
+/// Fold a codeword, laid out in rows of `2` sibling evaluations, once with the challenge `beta`,
+/// exactly as a commit phase round does when `challenges_per_round()` is one, but without
+/// committing to anything. Mainly useful for benchmarking the fold on its own.
+pub fn fold_once<G, F>(g: &G, beta: F, matrix: RowMajorMatrix<F>) -> Vec<F>
+where
+    F: Field,
+    G: FriGenericConfig<F>,
+{
+    g.fold_matrix_with_betas(&[beta], matrix)
 }
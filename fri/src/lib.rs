@@ -8,6 +8,8 @@ mod config;
 mod fold_even_odd;
 mod proof;
 pub mod prover;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 mod two_adic_pcs;
 pub mod verifier;
 
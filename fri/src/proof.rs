@@ -1,7 +1,14 @@
+use alloc::vec;
 use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
 
+use itertools::izip;
 use p3_commit::Mmcs;
-use p3_field::Field;
+use p3_field::{
+    AbstractExtensionField, AbstractField, ExtensionField, Field, PrimeField32, PrimeField64,
+};
+use p3_symmetric::Hash;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -12,10 +19,514 @@ use serde::{Deserialize, Serialize};
 pub struct FriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
     pub query_proofs: Vec<QueryProof<F, M, InputProof>>,
-    // This could become Vec<FC::Challenge> if this library was generalized to support non-constant
-    // final polynomials.
-    pub final_poly: F,
-    pub pow_witness: Witness,
+    /// The coefficients of the final polynomial, lowest degree first. There are exactly
+    /// `final_poly_len()` of them if `pad_final_poly` is set, some of which may be zero, and
+    /// otherwise one per `blowup()` evaluations of the final codeword.
+    pub final_poly: Vec<F>,
+    /// The proof of work witness, or `None` if `proof_of_work_bits` is zero, in which case no
+    /// grinding is done at all.
+    pub pow_witness: Option<Witness>,
+    /// The difficulty `pow_witness` was ground to, in bits. This is `proof_of_work_bits`, unless
+    /// `grind_time_budget` let the prover grind further.
+    pub pow_bits: usize,
+    /// The witness of the proof of work done after sampling the query indices, or `None` if
+    /// `post_query_pow_bits` is zero.
+    pub post_query_pow_witness: Option<Witness>,
+    /// The log2 of the length of the largest input. This normally follows from the number of
+    /// commit phase rounds, but not when `fixed_rounds` is set.
+    pub log_max_height: usize,
+    /// For each commit phase round, the indices of the inputs mixed into the codeword folded in
+    /// that round, in the order they were mixed in, which is index order, followed by those of the
+    /// inputs mixed into the final codeword after the last fold. Those of the first round are
+    /// mixed into the initial codeword, which is the first input, so index 0 never appears here.
+    /// This follows from the input heights, but recording it lets the verifier check that it mixes
+    /// the same inputs in the same order as the prover did.
+    pub mixed_inputs: Vec<Vec<usize>>,
+    /// The commitment to the final polynomial and its opening, present if and only if
+    /// `commit_final_poly` is set.
+    pub final_poly_commitment: Option<FinalPolyCommitment<F, M>>,
+    /// For each commit phase round, a single proof opening the rows of every query at once, if
+    /// the round's MMCS supports it (see `Mmcs::open_multi_batch`), in which case the queries'
+    /// own openings of that round carry no opening proof. The rows are opened at the queries'
+    /// indices in the order the queries were sampled.
+    pub commit_phase_batch_proofs: Vec<Option<M::Proof>>,
+}
+
+impl<F: Field, M: Mmcs<F>, Witness, InputProof> FriProof<F, M, Witness, InputProof> {
+    /// The log2 of the length of the largest input, i.e. of the size of the domain the query
+    /// indices are sampled from, before any extra index bits of the PCS.
+    pub const fn log_max_height(&self) -> usize {
+        self.log_max_height
+    }
+
+    /// Convert this proof to its compact form, see `CompactFriProof`.
+    pub fn compact(mut self) -> CompactFriProof<F, M, Witness, InputProof>
+    where
+        M::Proof: PartialEq,
+    {
+        let num_rounds = self
+            .query_proofs
+            .iter()
+            .map(|qp| qp.commit_phase_openings.len())
+            .max()
+            .unwrap_or(0);
+        let mut opening_proofs = vec![vec![]; num_rounds];
+        let mut steps = vec![vec![]; num_rounds];
+
+        let query_proofs = mem::take(&mut self.query_proofs)
+            .into_iter()
+            .map(|qp| CompactQueryProof {
+                input_proof: qp.input_proof,
+                commit_phase_openings: qp
+                    .commit_phase_openings
+                    .into_iter()
+                    .enumerate()
+                    .map(|(round, step)| {
+                        let opening_proof = step
+                            .opening_proof
+                            .map(|opening_proof| intern(&mut opening_proofs[round], opening_proof));
+                        let step = CompactCommitPhaseProofStep {
+                            sibling_values: step.sibling_values,
+                            extra_sibling_values: step.extra_sibling_values,
+                            opening_proof,
+                        };
+                        intern(&mut steps[round], step)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        CompactFriProof {
+            proof: self,
+            opening_proofs,
+            steps,
+            query_proofs,
+        }
+    }
+}
+
+/// Return the index of `value` in `pool`, adding it to the end of `pool` if it isn't there yet.
+/// A pool only holds the distinct values of one round, at most one per query, so it's searched
+/// linearly.
+fn intern<T: PartialEq>(pool: &mut Vec<T>, value: T) -> usize {
+    pool.iter().position(|v| *v == value).unwrap_or_else(|| {
+        pool.push(value);
+        pool.len() - 1
+    })
+}
+
+/// A `FriProof` in which the commit phase openings shared by several queries are only stored once.
+///
+/// Queries whose indices agree in all but the lowest bits land in the same row of the later
+/// commit phase codewords, and so open the same row with the same opening proof, and queries with
+/// equal indices in a round also have the same sibling values. This is common once the codewords
+/// get small, so for proofs with many queries over a small domain the compact form can be much
+/// smaller. Opening proofs which only partly overlap, e.g. Merkle paths which share their top
+/// nodes, aren't shared, as FRI treats opening proofs as opaque.
+///
+/// The format is:
+/// - `proof` is the original proof, with `query_proofs` empty;
+/// - `opening_proofs[r]` holds the distinct opening proofs of commit phase round `r`;
+/// - `steps[r]` holds the distinct openings of round `r`, each referring to its opening proof, if
+///   it has one, by its index in `opening_proofs[r]`;
+/// - `query_proofs[i]` holds the input proof of query `i`, and for each round `r` the index in
+///   `steps[r]` of its opening.
+///
+/// `expand` recovers the original proof, which is what the verifier checks.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize, InputProof: Serialize",
+    deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
+))]
+pub struct CompactFriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
+    pub proof: FriProof<F, M, Witness, InputProof>,
+    pub opening_proofs: Vec<Vec<M::Proof>>,
+    pub steps: Vec<Vec<CompactCommitPhaseProofStep<F>>>,
+    pub query_proofs: Vec<CompactQueryProof<InputProof>>,
+}
+
+/// A `CommitPhaseProofStep` whose opening proof is stored separately, see `CompactFriProof`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(bound = "")]
+pub struct CompactCommitPhaseProofStep<F: Field> {
+    pub sibling_values: Vec<F>,
+    pub extra_sibling_values: Vec<F>,
+    /// The index of the opening proof among those of its round, or `None` if the round was opened
+    /// for all queries at once.
+    pub opening_proof: Option<usize>,
+}
+
+/// A `QueryProof` whose commit phase openings are stored separately, see `CompactFriProof`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CompactQueryProof<InputProof> {
+    pub input_proof: InputProof,
+    /// For each commit phase round, the index of the opening among those of its round.
+    pub commit_phase_openings: Vec<usize>,
+}
+
+impl<F: Field, M: Mmcs<F>, Witness, InputProof> CompactFriProof<F, M, Witness, InputProof> {
+    /// Recover the original proof, or `None` if an index is out of range.
+    pub fn expand(self) -> Option<FriProof<F, M, Witness, InputProof>> {
+        let CompactFriProof {
+            mut proof,
+            opening_proofs,
+            steps,
+            query_proofs,
+        } = self;
+        proof.query_proofs = query_proofs
+            .into_iter()
+            .map(|qp| {
+                let commit_phase_openings = qp
+                    .commit_phase_openings
+                    .iter()
+                    .enumerate()
+                    .map(|(round, &i)| {
+                        let step = steps.get(round)?.get(i)?;
+                        Some(CommitPhaseProofStep {
+                            sibling_values: step.sibling_values.clone(),
+                            extra_sibling_values: step.extra_sibling_values.clone(),
+                            opening_proof: match step.opening_proof {
+                                Some(i) => Some(opening_proofs.get(round)?.get(i)?.clone()),
+                                None => None,
+                            },
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(QueryProof {
+                    input_proof: qp.input_proof,
+                    commit_phase_openings,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(proof)
+    }
+}
+
+#[cfg(feature = "proof-size")]
+impl<F: Field, M: Mmcs<F>, Witness, InputProof: Serialize> FriProof<F, M, Witness, InputProof> {
+    /// The size in bytes of the query proofs when serialized with `postcard`, i.e. the cost of
+    /// the query phase, including the input openings. Together with `final_poly_size`, this
+    /// shows how a change to `final_poly_len` trades the final polynomial against the number of
+    /// rounds each query has to open.
+    pub fn query_phase_size(&self) -> usize {
+        serialized_size(&self.query_proofs)
+    }
+
+    /// The size in bytes of the final polynomial when serialized with `postcard`.
+    pub fn final_poly_size(&self) -> usize {
+        serialized_size(&self.final_poly)
+    }
+}
+
+#[cfg(feature = "proof-size")]
+fn serialized_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    postcard::to_allocvec(value)
+        .expect("serializing to a vector can't fail")
+        .len()
+}
+
+/// Types which can be written as a sequence of elements of the field `Val` and read back, so that
+/// a proof can be handed to a verifier which only deals in field elements, such as a recursive one.
+///
+/// Lengths and other integers are written as single field elements, so they must be smaller than
+/// the field's order, and elements of an extension field as their `D` base field coordinates.
+pub trait Flatten<Val>: Sized {
+    /// Append the field elements representing `self` to `out`.
+    fn flatten_into(&self, out: &mut Vec<Val>);
+
+    /// Read a value from the start of `elems`, the inverse of `flatten_into`, or return `None` if
+    /// `elems` doesn't start with a valid representation.
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self>;
+
+    /// The number of field elements `flatten_into` appends.
+    fn flattened_len(&self) -> usize;
+}
+
+impl<Val: PrimeField64, F: ExtensionField<Val>, M: Mmcs<F>, InputProof>
+    FriProof<F, M, Val, InputProof>
+where
+    M::Commitment: Flatten<Val>,
+    M::Proof: Flatten<Val>,
+    InputProof: Flatten<Val>,
+{
+    /// The proof as a flat sequence of base field elements, e.g. to be passed to a verifier
+    /// circuit.
+    ///
+    /// The fields are written in declaration order, i.e. `commit_phase_commits`, `query_proofs`,
+    /// `final_poly`, `pow_witness`, `pow_bits`, `post_query_pow_witness`, `log_max_height`,
+    /// `mixed_inputs`, `final_poly_commitment` and `commit_phase_batch_proofs`, and the fields of
+    /// each nested struct likewise. Each value is written as follows:
+    /// - a `usize` or a proof of work witness is a single element;
+    /// - an element of `F` is its `D` base field coordinates;
+    /// - a `Vec` is its length, followed by its items;
+    /// - an `Option` is `0` if it's `None`, or `1` followed by its value;
+    /// - the MMCS commitments and proofs and the input proofs are given by their `Flatten` impls.
+    pub fn flatten(&self) -> Vec<Val> {
+        let mut out = Vec::with_capacity(self.flattened_len());
+        self.flatten_into(&mut out);
+        out
+    }
+
+    /// Recover a proof from the output of `flatten`, or `None` if `elems` isn't a valid
+    /// flattened proof, including if it has elements left over at the end.
+    pub fn unflatten(elems: &[Val]) -> Option<Self> {
+        let mut elems = elems.iter().copied();
+        let proof = Self::unflatten_from(&mut elems)?;
+        elems.next().is_none().then_some(proof)
+    }
+}
+
+impl<Val: PrimeField64, F: ExtensionField<Val>, M: Mmcs<F>, InputProof> Flatten<Val>
+    for FriProof<F, M, Val, InputProof>
+where
+    M::Commitment: Flatten<Val>,
+    M::Proof: Flatten<Val>,
+    InputProof: Flatten<Val>,
+{
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        self.commit_phase_commits.flatten_into(out);
+        self.query_proofs.flatten_into(out);
+        flatten_values(&self.final_poly, out);
+        self.pow_witness.map(|w| [w]).flatten_into(out);
+        self.pow_bits.flatten_into(out);
+        self.post_query_pow_witness.map(|w| [w]).flatten_into(out);
+        self.log_max_height.flatten_into(out);
+        self.mixed_inputs.flatten_into(out);
+        self.final_poly_commitment.flatten_into(out);
+        self.commit_phase_batch_proofs.flatten_into(out);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        Some(Self {
+            commit_phase_commits: Flatten::unflatten_from(elems)?,
+            query_proofs: Flatten::unflatten_from(elems)?,
+            final_poly: unflatten_values(elems)?,
+            pow_witness: Option::<[Val; 1]>::unflatten_from(elems)?.map(|[w]| w),
+            pow_bits: Flatten::unflatten_from(elems)?,
+            post_query_pow_witness: Option::<[Val; 1]>::unflatten_from(elems)?.map(|[w]| w),
+            log_max_height: Flatten::unflatten_from(elems)?,
+            mixed_inputs: Flatten::unflatten_from(elems)?,
+            final_poly_commitment: Flatten::unflatten_from(elems)?,
+            commit_phase_batch_proofs: Flatten::unflatten_from(elems)?,
+        })
+    }
+
+    fn flattened_len(&self) -> usize {
+        self.commit_phase_commits.flattened_len()
+            + self.query_proofs.flattened_len()
+            + values_flattened_len::<Val, F>(&self.final_poly)
+            + self.pow_witness.map(|w| [w]).flattened_len()
+            + self.pow_bits.flattened_len()
+            + self.post_query_pow_witness.map(|w| [w]).flattened_len()
+            + self.log_max_height.flattened_len()
+            + self.mixed_inputs.flattened_len()
+            + self.final_poly_commitment.flattened_len()
+            + self.commit_phase_batch_proofs.flattened_len()
+    }
+}
+
+impl<Val: PrimeField64, F: ExtensionField<Val>, M: Mmcs<F>, InputProof> Flatten<Val>
+    for QueryProof<F, M, InputProof>
+where
+    M::Proof: Flatten<Val>,
+    InputProof: Flatten<Val>,
+{
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        self.input_proof.flatten_into(out);
+        self.commit_phase_openings.flatten_into(out);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        Some(Self {
+            input_proof: Flatten::unflatten_from(elems)?,
+            commit_phase_openings: Flatten::unflatten_from(elems)?,
+        })
+    }
+
+    fn flattened_len(&self) -> usize {
+        self.input_proof.flattened_len() + self.commit_phase_openings.flattened_len()
+    }
+}
+
+impl<Val: PrimeField64, F: ExtensionField<Val>, M: Mmcs<F>> Flatten<Val>
+    for CommitPhaseProofStep<F, M>
+where
+    M::Proof: Flatten<Val>,
+{
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        flatten_values(&self.sibling_values, out);
+        flatten_values(&self.extra_sibling_values, out);
+        self.opening_proof.flatten_into(out);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        Some(Self {
+            sibling_values: unflatten_values(elems)?,
+            extra_sibling_values: unflatten_values(elems)?,
+            opening_proof: Flatten::unflatten_from(elems)?,
+        })
+    }
+
+    fn flattened_len(&self) -> usize {
+        values_flattened_len::<Val, F>(&self.sibling_values)
+            + values_flattened_len::<Val, F>(&self.extra_sibling_values)
+            + self.opening_proof.flattened_len()
+    }
+}
+
+impl<Val: PrimeField64, F: ExtensionField<Val>, M: Mmcs<F>> Flatten<Val>
+    for FinalPolyCommitment<F, M>
+where
+    M::Commitment: Flatten<Val>,
+    M::Proof: Flatten<Val>,
+{
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        self.commit.flatten_into(out);
+        self.opening_proof.flatten_into(out);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        Some(Self {
+            commit: Flatten::unflatten_from(elems)?,
+            opening_proof: Flatten::unflatten_from(elems)?,
+        })
+    }
+
+    fn flattened_len(&self) -> usize {
+        self.commit.flattened_len() + self.opening_proof.flattened_len()
+    }
+}
+
+impl<Val: PrimeField64> Flatten<Val> for usize {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        out.push(Val::from_canonical_usize(*self));
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        elems.next()?.as_canonical_u64().try_into().ok()
+    }
+
+    fn flattened_len(&self) -> usize {
+        1
+    }
+}
+
+impl<Val: PrimeField64, T: Flatten<Val>> Flatten<Val> for Vec<T> {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        self.len().flatten_into(out);
+        for item in self {
+            item.flatten_into(out);
+        }
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        // Don't preallocate, as the length hasn't been checked against the number of elements.
+        let len = usize::unflatten_from(elems)?;
+        (0..len).map(|_| T::unflatten_from(elems)).collect()
+    }
+
+    fn flattened_len(&self) -> usize {
+        1 + self.iter().map(T::flattened_len).sum::<usize>()
+    }
+}
+
+impl<Val: PrimeField64, T: Flatten<Val>> Flatten<Val> for Option<T> {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        match self {
+            None => out.push(Val::zero()),
+            Some(value) => {
+                out.push(Val::one());
+                value.flatten_into(out);
+            }
+        }
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        match usize::unflatten_from(elems)? {
+            0 => Some(None),
+            1 => Some(Some(T::unflatten_from(elems)?)),
+            _ => None,
+        }
+    }
+
+    fn flattened_len(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::flattened_len)
+    }
+}
+
+impl<Val: PrimeField64, const N: usize> Flatten<Val> for [Val; N] {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        out.extend_from_slice(self);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        elems.take(N).collect::<Vec<_>>().try_into().ok()
+    }
+
+    fn flattened_len(&self) -> usize {
+        N
+    }
+}
+
+impl<Val: PrimeField64, const N: usize> Flatten<Val> for Hash<Val, Val, N> {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        <[Val; N]>::from(*self).flatten_into(out);
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        <[Val; N]>::unflatten_from(elems).map(Self::from)
+    }
+
+    fn flattened_len(&self) -> usize {
+        N
+    }
+}
+
+/// An index paired with a value, as in the input proofs of `TwoAdicFriGenericConfig` when used
+/// directly with FRI.
+impl<Val: PrimeField64, F: ExtensionField<Val>> Flatten<Val> for (usize, F) {
+    fn flatten_into(&self, out: &mut Vec<Val>) {
+        self.0.flatten_into(out);
+        out.extend_from_slice(<F as AbstractExtensionField<Val>>::as_base_slice(&self.1));
+    }
+
+    fn unflatten_from(elems: &mut impl Iterator<Item = Val>) -> Option<Self> {
+        Some((usize::unflatten_from(elems)?, unflatten_value(elems)?))
+    }
+
+    fn flattened_len(&self) -> usize {
+        1 + <F as AbstractExtensionField<Val>>::D
+    }
+}
+
+/// Write the length of `values`, followed by the base field coordinates of each of them.
+pub(crate) fn flatten_values<Val: Field, F: ExtensionField<Val>>(values: &[F], out: &mut Vec<Val>) {
+    out.push(Val::from_canonical_usize(values.len()));
+    for value in values {
+        out.extend_from_slice(<F as AbstractExtensionField<Val>>::as_base_slice(value));
+    }
+}
+
+/// The inverse of `flatten_values`.
+pub(crate) fn unflatten_values<Val: PrimeField64, F: ExtensionField<Val>>(
+    elems: &mut impl Iterator<Item = Val>,
+) -> Option<Vec<F>> {
+    let len = usize::unflatten_from(elems)?;
+    (0..len).map(|_| unflatten_value(elems)).collect()
+}
+
+pub(crate) fn values_flattened_len<Val: Field, F: ExtensionField<Val>>(values: &[F]) -> usize {
+    1 + values.len() * <F as AbstractExtensionField<Val>>::D
+}
+
+fn unflatten_value<Val: Field, F: ExtensionField<Val>>(
+    elems: &mut impl Iterator<Item = Val>,
+) -> Option<F> {
+    let coords = elems
+        .take(<F as AbstractExtensionField<Val>>::D)
+        .collect::<Vec<_>>();
+    (coords.len() == <F as AbstractExtensionField<Val>>::D)
+        .then(|| <F as AbstractExtensionField<Val>>::from_base_slice(&coords))
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -30,13 +541,191 @@ pub struct QueryProof<F: Field, M: Mmcs<F>, InputProof> {
     pub commit_phase_openings: Vec<CommitPhaseProofStep<F, M>>,
 }
 
+/// A commitment to the final polynomial, as a matrix with a single row holding its coefficients,
+/// along with the proof opening that row.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(bound = "")]
+pub struct FinalPolyCommitment<F: Field, M: Mmcs<F>> {
+    pub commit: M::Commitment,
+    pub opening_proof: M::Proof,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
 pub struct CommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
+    /// The openings of the commit phase codeword at every sibling location, i.e. the opened row
+    /// with the queried location removed. For folding arity 2 this is a single value.
+    pub sibling_values: Vec<F>,
 
-    pub opening_proof: M::Proof,
+    /// The sibling values of any further matrices committed in the same round as the commit phase
+    /// codeword, in the order they were committed, i.e. of the inputs committed alongside it when
+    /// `FriConfig::commit_mixed_inputs` is set. Empty otherwise.
+    pub extra_sibling_values: Vec<F>,
+
+    /// The proof opening the round's row at the queried location, or `None` if the round was
+    /// opened for all queries at once, see `FriProof::commit_phase_batch_proofs`.
+    pub opening_proof: Option<M::Proof>,
+}
+
+impl<F: Field, M: Mmcs<F>, InputProof> QueryProof<F, M, InputProof> {
+    /// Convert this query proof to its packed form, see `PackedQueryProof`.
+    pub fn pack<Val: PrimeField32>(self) -> PackedQueryProof<F, M, InputProof>
+    where
+        F: ExtensionField<Val>,
+    {
+        let num_sibling_values = self
+            .commit_phase_openings
+            .iter()
+            .map(|step| step.sibling_values.len())
+            .collect();
+        let num_extra_sibling_values = self
+            .commit_phase_openings
+            .iter()
+            .map(|step| step.extra_sibling_values.len())
+            .collect();
+        let coords = self.commit_phase_openings.iter().flat_map(|step| {
+            step.sibling_values
+                .iter()
+                .chain(&step.extra_sibling_values)
+                .flat_map(|v| <F as AbstractExtensionField<Val>>::as_base_slice(v))
+                .map(|c| c.as_canonical_u32())
+        });
+        let packed_sibling_values = pack_bits(coords, Val::bits());
+        let opening_proofs = self
+            .commit_phase_openings
+            .into_iter()
+            .map(|step| step.opening_proof)
+            .collect();
+
+        PackedQueryProof {
+            input_proof: self.input_proof,
+            packed_sibling_values,
+            num_sibling_values,
+            num_extra_sibling_values,
+            opening_proofs,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// A `QueryProof` with all of its sibling values stored as tightly packed integers, for fields
+/// which are extensions of a prime field of at most 32 bits. Each base field coordinate of a
+/// sibling value takes exactly `Val::bits()` bits (e.g. 31 for BabyBear or Mersenne31) rather than
+/// a whole machine word, which shrinks serialized proofs.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "InputProof: Serialize",
+    deserialize = "InputProof: Deserialize<'de>",
+))]
+pub struct PackedQueryProof<F: Field, M: Mmcs<F>, InputProof> {
+    pub input_proof: InputProof,
+    /// The canonical base field coordinates of each commit phase step's sibling values, followed
+    /// by its extra sibling values, in order, as a little-endian bit string.
+    pub packed_sibling_values: Vec<u8>,
+    /// The number of sibling values of each commit phase step.
+    pub num_sibling_values: Vec<usize>,
+    /// The number of extra sibling values of each commit phase step.
+    pub num_extra_sibling_values: Vec<usize>,
+    /// The opening proof of each commit phase step.
+    pub opening_proofs: Vec<Option<M::Proof>>,
+    #[serde(skip)]
+    _phantom: PhantomData<F>,
+}
+
+impl<F: Field, M: Mmcs<F>, InputProof> PackedQueryProof<F, M, InputProof> {
+    /// Recover the original query proof, or `None` if the packed sibling values are malformed.
+    pub fn unpack<Val: PrimeField32>(self) -> Option<QueryProof<F, M, InputProof>>
+    where
+        F: ExtensionField<Val>,
+    {
+        if self.num_sibling_values.len() != self.opening_proofs.len()
+            || self.num_extra_sibling_values.len() != self.opening_proofs.len()
+        {
+            return None;
+        }
+        let d = <F as AbstractExtensionField<Val>>::D;
+        let num_values = self
+            .num_sibling_values
+            .iter()
+            .chain(&self.num_extra_sibling_values)
+            .try_fold(0usize, |acc, &n| acc.checked_add(n))?;
+        let coords = unpack_bits(
+            &self.packed_sibling_values,
+            Val::bits(),
+            num_values.checked_mul(d)?,
+        )?;
+        if coords.iter().any(|&c| c >= Val::ORDER_U32) {
+            return None;
+        }
+
+        let mut values = coords.chunks_exact(d).map(|coords| {
+            <F as AbstractExtensionField<Val>>::from_base_fn(|i| Val::from_canonical_u32(coords[i]))
+        });
+        let commit_phase_openings = izip!(
+            self.num_sibling_values,
+            self.num_extra_sibling_values,
+            self.opening_proofs
+        )
+        .map(
+            |(num_siblings, num_extra, opening_proof)| CommitPhaseProofStep {
+                sibling_values: values.by_ref().take(num_siblings).collect(),
+                extra_sibling_values: values.by_ref().take(num_extra).collect(),
+                opening_proof,
+            },
+        )
+        .collect();
+
+        Some(QueryProof {
+            input_proof: self.input_proof,
+            commit_phase_openings,
+        })
+    }
+}
+
+/// Concatenate the low `bits` bits of each value into a little-endian bit string.
+fn pack_bits(values: impl Iterator<Item = u32>, bits: usize) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let mut acc = 0u64;
+    let mut acc_bits = 0;
+    for value in values {
+        debug_assert!(bits == 32 || value >> bits == 0);
+        acc |= (value as u64) << acc_bits;
+        acc_bits += bits;
+        while acc_bits >= 8 {
+            packed.push(acc as u8);
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+    if acc_bits > 0 {
+        packed.push(acc as u8);
+    }
+    packed
+}
+
+/// The inverse of `pack_bits`, or `None` if `packed` doesn't have the expected length or any of
+/// the padding bits of its last byte are set, so that each list of values has a single packing.
+fn unpack_bits(packed: &[u8], bits: usize, num_values: usize) -> Option<Vec<u32>> {
+    if packed.len() != num_values.checked_mul(bits)?.div_ceil(8) {
+        return None;
+    }
+    let mask = (1u64 << bits) - 1;
+    let mut values = Vec::with_capacity(num_values);
+    let mut bytes = packed.iter();
+    let mut acc = 0u64;
+    let mut acc_bits = 0;
+    for _ in 0..num_values {
+        while acc_bits < bits {
+            acc |= (*bytes.next()? as u64) << acc_bits;
+            acc_bits += 8;
+        }
+        values.push((acc & mask) as u32);
+        acc >>= bits;
+        acc_bits -= bits;
+    }
+    // What's left of `acc` is the padding.
+    if acc != 0 {
+        return None;
+    }
+    Some(values)
 }
@@ -3,77 +3,537 @@ use alloc::vec::Vec;
 use core::iter;
 
 use itertools::{izip, Itertools};
-use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::Mmcs;
 use p3_field::{ExtensionField, Field};
 use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
 use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
-use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof, QueryProof};
+use crate::verifier::{check_final_poly_degree, observe_query_responses, round_betas};
+use crate::{
+    CommitPhaseProofStep, FinalPolyCommitment, FriConfig, FriGenericConfig, FriProof,
+    GrindingStrategy, PowStage, QueryProof,
+};
 
-#[instrument(name = "FRI prover", skip_all)]
-pub fn prove<G, Val, Challenge, M, Challenger>(
+#[derive(Debug, PartialEq, Eq)]
+pub enum FriProverError {
+    /// `num_queries` is larger than the number of points which can be queried, so some would
+    /// certainly be queried twice. This means the FRI parameters don't suit such a small domain.
+    TooManyQueries,
+    /// `fixed_rounds` is set, but folding the largest input that many times would leave fewer
+    /// than `blowup()` or more than `blowup() * final_poly_len()` evaluations.
+    IncompatibleFixedRounds,
+    /// The largest input has no more than `blowup() * final_poly_len()` evaluations, so the commit
+    /// phase would not fold it at all and the whole input would become the final polynomial.
+    FinalPolyLenExceedsInput,
+    /// The commit phase would commit to more than `max_committed_elements` evaluations in total.
+    CommitBudgetExceeded,
+    /// The folding arity is 1, or the largest input can't be folded down to the final codeword in
+    /// whole rounds of `FriGenericConfig::log_arity` bits each.
+    IncompatibleArity,
+    /// The largest input has `len` evaluations, which isn't a power of two.
+    NonPowerOfTwoInput { len: usize },
+    /// The input at `index`, with `len` evaluations, would never be mixed into the codeword,
+    /// since the codeword is never folded to that length: it's shorter than the final codeword,
+    /// isn't a power of two, or is out of order. With `commit_mixed_inputs`, an input shorter than
+    /// the folding arity can't be committed in rows of the arity, so isn't mixed in either.
+    UnmixableInput { index: usize, len: usize },
+    /// The final polynomial would have `len` coefficients, padding included, more than the
+    /// `FriGenericConfig` supports; see `FriGenericConfig::max_final_poly_len`.
+    UnsupportedFinalPolyLen { len: usize },
+    /// `grind_time_budget` is set, but the grinding strategy can't grind within a time budget;
+    /// see `GrindingStrategy::grind_with_budget`.
+    GrindTimeBudgetUnsupported,
+}
+
+/// Statistics gathered by `prove_with_metrics`, for calibrating the FRI parameters.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FriProveMetrics {
+    /// The number of candidate proof of work witnesses checked, if any grinding was done and the
+    /// grinding strategy keeps track of it. This should be about `2^bits` on average.
+    pub grind_attempts: Option<u64>,
+    /// The difficulty the proof of work witness actually meets, which may be higher than
+    /// `proof_of_work_bits`, if grinding was done and the grinding strategy can tell. With a
+    /// `grind_time_budget` this is the difficulty reached within the budget, as recorded in the
+    /// proof.
+    pub achieved_pow_bits: Option<usize>,
+}
+
+/// Prove that `inputs`, sorted by descending length, are close to low-degree codewords.
+///
+/// The challenger samples the folding challenges as extension field elements and the query
+/// indices with `sample_bits`. A challenger built on a byte-oriented sponge, such as Keccak, can
+/// be used by wrapping it in `SerializingChallenger32` or `SerializingChallenger64`, which observe
+/// field elements and commitments as their little-endian bytes, sample each base field
+/// coordinate by rejection sampling from little-endian words masked to the bit length of the
+/// field, and sample query indices by masking a single word.
+///
+/// `prove` doesn't observe the commitments to the inputs, which `open_input` opens. The caller
+/// must have observed them already, in the same order as the verifier will, or the proof won't
+/// verify; `prove_with_input_commitments` does this itself.
+pub fn prove<G, Val, Challenge, M, Challenger, Gr>(
     g: &G,
-    config: &FriConfig<M>,
+    config: &FriConfig<M, Gr>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
     open_input: impl Fn(usize) -> G::InputProof,
-) -> FriProof<Challenge, M, Challenger::Witness, G::InputProof>
+) -> Result<FriProof<Challenge, M, Gr::Witness, G::InputProof>, FriProverError>
 where
     Val: Field,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
 {
-    // check sorted descending
-    assert!(inputs
-        .iter()
-        .tuple_windows()
-        .all(|(l, r)| l.len() >= r.len()));
+    prove_with_metrics(g, config, inputs, challenger, open_input).map(|(proof, _)| proof)
+}
+
+/// Same as `prove`, but also returns statistics about the work done.
+#[allow(clippy::type_complexity)]
+pub fn prove_with_metrics<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    inputs: Vec<Vec<Challenge>>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+) -> Result<
+    (
+        FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+        FriProveMetrics,
+    ),
+    FriProverError,
+>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    let (first_input, other_inputs) = split_first_input(inputs);
+    prove_inner(
+        g,
+        config,
+        checked_log2(first_input.len())?,
+        || first_input,
+        other_inputs,
+        None,
+        challenger,
+        open_input,
+        |_, _| {},
+    )
+}
+
+/// Same as `prove`, but passes each commit phase commitment, along with its round, to `on_commit`
+/// as soon as it's made, e.g. so that it can be sent to the verifier while the rest of the proof is
+/// computed. `on_commit` is called in round order, and the returned proof, which still contains
+/// the commitments, is the same as `prove` would return.
+///
+/// The commitments are observed by `challenger` as usual; `on_commit` only gets a copy.
+pub fn prove_streaming<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    inputs: Vec<Vec<Challenge>>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+    on_commit: impl FnMut(usize, &M::Commitment),
+) -> Result<FriProof<Challenge, M, Gr::Witness, G::InputProof>, FriProverError>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    let (first_input, other_inputs) = split_first_input(inputs);
+    prove_inner(
+        g,
+        config,
+        checked_log2(first_input.len())?,
+        || first_input,
+        other_inputs,
+        None,
+        challenger,
+        open_input,
+        on_commit,
+    )
+    .map(|(proof, _)| proof)
+}
+
+/// Same as `prove`, but rather than taking the first (and largest) input up front, calls
+/// `first_input` for it once the commit phase starts, e.g. so that its evaluations can be computed
+/// only once they're needed, reducing peak memory. It must return `2^log_max_height` evaluations,
+/// and the remaining inputs are given by `other_inputs`. The proof is the same as `prove` would
+/// return with the first input passed eagerly.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_lazy<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    log_max_height: usize,
+    first_input: impl FnOnce() -> Vec<Challenge>,
+    other_inputs: Vec<Vec<Challenge>>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+) -> Result<FriProof<Challenge, M, Gr::Witness, G::InputProof>, FriProverError>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    prove_inner(
+        g,
+        config,
+        log_max_height,
+        first_input,
+        other_inputs,
+        None,
+        challenger,
+        open_input,
+        |_, _| {},
+    )
+    .map(|(proof, _)| proof)
+}
+
+/// Same as `prove`, but uses `first_beta` as the first folding challenge of the first commit
+/// phase round, rather than sampling it, e.g. when an earlier step of the protocol has already
+/// fixed it. `first_beta` is observed where it would otherwise have been sampled, and all the later
+/// challenges are sampled as usual. The verifier must be given the same `first_beta`, see
+/// `verifier::verify_with_first_beta`.
+///
+/// For this to be sound, `first_beta` must itself be bound by the transcript: it must be a
+/// challenge sampled (e.g. by the caller's protocol) after the commitments to all the inputs were
+/// observed. A prover who can choose `first_beta`, or learn it before committing to the inputs,
+/// can make far inputs fold to a low-degree codeword.
+pub fn prove_with_first_beta<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    first_beta: Challenge,
+    inputs: Vec<Vec<Challenge>>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+) -> Result<FriProof<Challenge, M, Gr::Witness, G::InputProof>, FriProverError>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    let (first_input, other_inputs) = split_first_input(inputs);
+    prove_inner(
+        g,
+        config,
+        checked_log2(first_input.len())?,
+        || first_input,
+        other_inputs,
+        Some(first_beta),
+        challenger,
+        open_input,
+        |_, _| {},
+    )
+    .map(|(proof, _)| proof)
+}
+
+fn split_first_input<F>(inputs: Vec<Vec<F>>) -> (Vec<F>, Vec<Vec<F>>) {
+    let mut inputs = inputs.into_iter();
+    let first_input = inputs.next().expect("FRI needs at least one input");
+    (first_input, inputs.collect())
+}
+
+/// The log2 of an input length, or an error if it isn't a power of two.
+fn checked_log2(len: usize) -> Result<usize, FriProverError> {
+    if len.is_power_of_two() {
+        Ok(len.trailing_zeros() as usize)
+    } else {
+        Err(FriProverError::NonPowerOfTwoInput { len })
+    }
+}
+
+#[instrument(name = "FRI prover", skip_all)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn prove_inner<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    log_max_height: usize,
+    first_input: impl FnOnce() -> Vec<Challenge>,
+    other_inputs: Vec<Vec<Challenge>>,
+    first_beta: Option<Challenge>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+    on_commit: impl FnMut(usize, &M::Commitment),
+) -> Result<
+    (
+        FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+        FriProveMetrics,
+    ),
+    FriProverError,
+>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    let mut metrics = FriProveMetrics::default();
+    let params = config.params(g);
 
-    let log_max_height = log2_strict_usize(inputs[0].len());
+    // Callers are required to sort the inputs by descending height; this is only checked in debug
+    // builds, to keep it off the hot path.
+    let heights = iter::once(1 << log_max_height)
+        .chain(other_inputs.iter().map(|v| v.len()))
+        .collect_vec();
+    debug_assert!(heights.iter().tuple_windows().all(|(l, r)| l >= r));
 
-    let commit_phase_result = commit_phase(g, config, inputs, challenger);
+    if params.num_queries > 1 << params.query_index_bits(log_max_height) {
+        return Err(FriProverError::TooManyQueries);
+    }
+    let log_final_height = config
+        .log_final_height(log_max_height, params.log_arity)
+        .ok_or(FriProverError::IncompatibleFixedRounds)?;
+    if log_final_height >= log_max_height {
+        return Err(FriProverError::FinalPolyLenExceedsInput);
+    }
+    if params.log_arity == 0 || (log_max_height - log_final_height) % params.log_arity != 0 {
+        return Err(FriProverError::IncompatibleArity);
+    }
+    let final_poly_len = if config.pad_final_poly {
+        params.final_poly_len()
+    } else {
+        1 << (log_final_height - params.log_blowup)
+    };
+    if final_poly_len > g.max_final_poly_len() {
+        return Err(FriProverError::UnsupportedFinalPolyLen {
+            len: final_poly_len,
+        });
+    }
 
-    let pow_witness = challenger.grind(config.proof_of_work_bits);
+    let first_input = || {
+        let first_input = first_input();
+        assert_eq!(first_input.len(), 1 << log_max_height);
+        first_input
+    };
+    let commit_phase_result = commit_phase(
+        g,
+        config,
+        first_input,
+        other_inputs,
+        log_final_height,
+        first_beta,
+        challenger,
+        on_commit,
+    )?;
+
+    let mut pow = (0, None);
+    if config.pow_stage == PowStage::AfterCommitPhase {
+        pow = grind(config, params.pow_bits, challenger, &mut metrics)?;
+    }
+    match &commit_phase_result.final_poly_commitment {
+        Some(final_poly_commitment) => challenger.observe(final_poly_commitment.commit.clone()),
+        None => {
+            for &coeff in &commit_phase_result.final_poly {
+                challenger.observe_ext_element(coeff);
+            }
+        }
+    }
+    if config.pow_stage == PowStage::BeforeQueries {
+        pow = grind(config, params.pow_bits, challenger, &mut metrics)?;
+    }
+    let (pow_bits, pow_witness) = pow;
+
+    let indices: Vec<usize> =
+        iter::repeat_with(|| challenger.sample_bits(params.query_index_bits(log_max_height)))
+            .take(params.num_queries)
+            .collect();
+
+    let commit_phase_batch_proofs = info_span!("batch commit phase openings").in_scope(|| {
+        let domain_indices: Vec<usize> = indices
+            .iter()
+            .map(|&index| index >> params.extra_index_bits)
+            .collect();
+        open_rounds_for_all_queries(config, &commit_phase_result.data, domain_indices)
+    });
+    let batched_rounds: Vec<bool> = commit_phase_batch_proofs
+        .iter()
+        .map(Option::is_some)
+        .collect();
 
     let query_proofs = info_span!("query phase").in_scope(|| {
-        iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
-            .take(config.num_queries)
-            .map(|index| QueryProof {
-                input_proof: open_input(index),
-                commit_phase_openings: answer_query(
-                    config,
-                    &commit_phase_result.data,
-                    index >> g.extra_query_index_bits(),
-                ),
+        // Answer the queries in increasing index order, so that consecutive openings walk the
+        // committed trees from one end to the other rather than jumping around them, then put the
+        // answers back in the order the verifier samples the indices.
+        let mut order: Vec<usize> = (0..indices.len()).collect();
+        order.sort_by_key(|&i| indices[i]);
+        let mut answers: Vec<_> = order
+            .into_iter()
+            .map(|i| {
+                let index = indices[i];
+                let query_proof = QueryProof {
+                    input_proof: open_input(index),
+                    commit_phase_openings: answer_query(
+                        g,
+                        config,
+                        &commit_phase_result.data,
+                        index >> params.extra_index_bits,
+                        &batched_rounds,
+                    ),
+                };
+                (i, query_proof)
             })
+            .collect();
+        answers.sort_by_key(|&(i, _)| i);
+        answers
+            .into_iter()
+            .map(|(_, query_proof)| query_proof)
             .collect()
     });
 
-    FriProof {
+    let mut post_query_pow_witness = None;
+    if config.post_query_pow_bits > 0 {
+        observe_query_responses(challenger, &query_proofs);
+        post_query_pow_witness = Some(
+            config
+                .grinding
+                .grind(challenger, config.post_query_pow_bits),
+        );
+    }
+
+    let proof = FriProof {
         commit_phase_commits: commit_phase_result.commits,
         query_proofs,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
+        pow_bits,
+        post_query_pow_witness,
+        log_max_height,
+        mixed_inputs: commit_phase_result.mixed_inputs,
+        final_poly_commitment: commit_phase_result.final_poly_commitment,
+        commit_phase_batch_proofs,
+    };
+    Ok((proof, metrics))
+}
+
+/// Same as `prove`, but takes the challenger by value and returns it alongside the proof, in the
+/// state it's left in once the proof is complete.
+///
+/// This makes it explicit which transcript state the next proof continues from when proofs are
+/// chained, e.g. when each of several leaf proofs is made with a challenger forked from a common
+/// parent, and an aggregator must re-derive their challenges.
+#[allow(clippy::type_complexity)]
+pub fn prove_with_challenger<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    inputs: Vec<Vec<Challenge>>,
+    mut challenger: Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+) -> Result<
+    (
+        FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+        Challenger,
+    ),
+    FriProverError,
+>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    let proof = prove(g, config, inputs, &mut challenger, open_input)?;
+    Ok((proof, challenger))
+}
+
+/// Same as `prove`, but first observes `input_commitments`, the commitments to the inputs which
+/// `open_input` opens, in the order given. The verifier must observe them in the same order, which
+/// `verifier::verify_with_input_commitments` does.
+pub fn prove_with_input_commitments<G, Val, Challenge, M, Challenger, Gr, C>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    input_commitments: &[C],
+    inputs: Vec<Vec<Challenge>>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize) -> G::InputProof,
+) -> Result<FriProof<Challenge, M, Gr::Witness, G::InputProof>, FriProverError>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment> + CanObserve<C>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+    C: Clone,
+{
+    for commit in input_commitments {
+        challenger.observe(commit.clone());
+    }
+    prove(g, config, inputs, challenger, open_input)
+}
+
+/// Grind for a proof of work of at least `min_bits`, returning the difficulty reached and the
+/// witness, if any.
+fn grind<M, Challenger, Gr: GrindingStrategy<Challenger>>(
+    config: &FriConfig<M, Gr>,
+    min_bits: usize,
+    challenger: &mut Challenger,
+    metrics: &mut FriProveMetrics,
+) -> Result<(usize, Option<Gr::Witness>), FriProverError> {
+    match (min_bits, config.grind_time_budget) {
+        (0, _) => Ok((0, None)),
+        (_, None) => {
+            let (witness, attempts, achieved_bits) = config
+                .grinding
+                .grind_with_achieved_bits(challenger, min_bits);
+            metrics.grind_attempts = attempts;
+            metrics.achieved_pow_bits = achieved_bits;
+            Ok((min_bits, Some(witness)))
+        }
+        (_, Some(budget)) => {
+            let (bits, witness, attempts) = config
+                .grinding
+                .grind_with_budget(challenger, min_bits, budget)
+                .ok_or(FriProverError::GrindTimeBudgetUnsupported)?;
+            assert!(bits >= min_bits);
+            metrics.grind_attempts = attempts;
+            metrics.achieved_pow_bits = Some(bits);
+            Ok((bits, Some(witness)))
+        }
     }
 }
 
 struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
     commits: Vec<M::Commitment>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
-    final_poly: F,
+    final_poly: Vec<F>,
+    mixed_inputs: Vec<Vec<usize>>,
+    final_poly_commitment: Option<FinalPolyCommitment<F, M>>,
+    /// The codeword after each round's fold (and any mixed-in inputs), for tests to inspect.
+    #[cfg(test)]
+    folded_layers: Vec<Vec<F>>,
 }
 
 #[instrument(name = "commit phase", skip_all)]
-fn commit_phase<G, Val, Challenge, M, Challenger>(
+fn commit_phase<G, Val, Challenge, M, Challenger, Gr>(
     g: &G,
-    config: &FriConfig<M>,
-    inputs: Vec<Vec<Challenge>>,
+    config: &FriConfig<M, Gr>,
+    first_input: impl FnOnce() -> Vec<Challenge>,
+    other_inputs: Vec<Vec<Challenge>>,
+    log_final_height: usize,
+    first_beta: Option<Challenge>,
     challenger: &mut Challenger,
-) -> CommitPhaseResult<Challenge, M>
+    mut on_commit: impl FnMut(usize, &M::Commitment),
+) -> Result<CommitPhaseResult<Challenge, M>, FriProverError>
 where
     Val: Field,
     Challenge: ExtensionField<Val>,
@@ -81,71 +541,582 @@ where
     Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
-    let mut inputs_iter = inputs.into_iter().peekable();
-    let mut folded = inputs_iter.next().unwrap();
+    // Inputs of equal height are mixed in one after another, in the order they were given (i.e.
+    // by index), which is the same order the verifier uses. Any of the height of the first input
+    // are mixed straight into the initial codeword.
+    let mut folded = first_input();
+    let mut inputs_iter = (1..).zip(other_inputs).peekable();
+    let mut mixed_inputs = vec![vec![]];
+    while let Some((i, v)) = inputs_iter.next_if(|(_, v)| v.len() == folded.len()) {
+        izip!(&mut folded, v).for_each(|(c, x)| *c = g.mix_input(*c, x, None));
+        mixed_inputs[0].push(i);
+    }
     let mut commits = vec![];
     let mut data = vec![];
+    let mut num_committed = 0;
+    #[cfg(test)]
+    let mut folded_layers = vec![];
 
-    while folded.len() > config.blowup() {
-        let leaves = RowMajorMatrix::new(folded, 2);
-        let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
+    let final_height = 1 << log_final_height;
+    let log_arity = g.log_arity();
+    let arity = 1 << log_arity;
+
+    while folded.len() > final_height {
+        let prev_len = folded.len();
+        // The inputs to mix into the codeword once it's folded. Those of the final height are mixed
+        // into the final codeword, after the last fold, which the verifier does too before
+        // comparing against the final polynomial. Committed inputs are in rows of the arity, so
+        // they must have at least one row.
+        let next_len = prev_len >> log_arity;
+        let mixable = !config.commit_mixed_inputs || next_len >= arity;
+        let next_inputs: Vec<(usize, Vec<Challenge>)> =
+            iter::from_fn(|| inputs_iter.next_if(|(_, v)| mixable && v.len() == next_len))
+                .collect();
+        let input_matrices: Vec<RowMajorMatrix<Challenge>> = if config.commit_mixed_inputs {
+            next_inputs
+                .iter()
+                .map(|(_, v)| RowMajorMatrix::new(v.clone(), arity))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        num_committed += prev_len + input_matrices.len() * next_len;
+        if config
+            .max_committed_elements
+            .is_some_and(|max| num_committed > max)
+        {
+            return Err(FriProverError::CommitBudgetExceeded);
+        }
+        let mmcs = config.commit_phase_mmcs(commits.len());
+        let evals = RowMajorMatrix::new(folded, arity);
+        let (commit, prover_data, evals) = match g.commit_phase_matrix(&evals) {
+            Some(committed) => {
+                let (commit, prover_data) =
+                    mmcs.commit(iter::once(committed).chain(input_matrices).collect());
+                (commit, prover_data, Some(evals))
+            }
+            None => {
+                let (commit, prover_data) =
+                    mmcs.commit(iter::once(evals).chain(input_matrices).collect());
+                (commit, prover_data, None)
+            }
+        };
         challenger.observe(commit.clone());
+        on_commit(commits.len(), &commit);
 
-        let beta: Challenge = challenger.sample_ext_element();
-        // We passed ownership of `current` to the MMCS, so get a reference to it
-        let leaves = config.mmcs.get_matrices(&prover_data).pop().unwrap();
-        folded = g.fold_matrix(beta, leaves.as_view());
+        let betas = round_betas::<_, Val, _, _>(g, commits.len(), first_beta, challenger);
+        let mix_challenge = g.sample_mix_challenge::<Val, _>(challenger);
+        folded = match &evals {
+            Some(evals) => g.fold_matrix_with_betas(&betas, evals.as_view()),
+            None => {
+                // We passed ownership of the evaluations to the MMCS, so get a reference to them
+                let leaves = mmcs.get_matrices(&prover_data)[0];
+                g.fold_matrix_with_betas(&betas, leaves.as_view())
+            }
+        };
+        // A fold which doesn't shrink the codeword would have us commit to the same data round
+        // after round, which almost certainly means `fold_matrix` (or the transcript) is broken.
+        debug_assert!(
+            folded.len() < prev_len,
+            "fold did not reduce the codeword length ({} -> {})",
+            prev_len,
+            folded.len()
+        );
 
         commits.push(commit);
         data.push(prover_data);
 
-        if let Some(v) = inputs_iter.next_if(|v| v.len() == folded.len()) {
-            izip!(&mut folded, v).for_each(|(c, x)| *c += x);
+        mixed_inputs.push(next_inputs.iter().map(|&(i, _)| i).collect());
+        for (_, v) in next_inputs {
+            izip!(&mut folded, v).for_each(|(c, x)| *c = g.mix_input(*c, x, mix_challenge));
         }
+        #[cfg(test)]
+        folded_layers.push(folded.clone());
     }
-
-    // We should be left with `blowup` evaluations of a constant polynomial.
-    assert_eq!(folded.len(), config.blowup());
-    let final_poly = folded[0];
-    for x in folded {
-        assert_eq!(x, final_poly);
+    if let Some((index, v)) = inputs_iter.next() {
+        return Err(FriProverError::UnmixableInput {
+            index,
+            len: v.len(),
+        });
     }
-    challenger.observe_ext_element(final_poly);
 
-    CommitPhaseResult {
+    // We should be left with `final_height` evaluations of a polynomial of degree less than
+    // `final_height / blowup`, which is `final_poly_len` unless `fixed_rounds` is set.
+    assert_eq!(folded.len(), final_height);
+    let mut final_poly = g.interpolate_final_poly(folded);
+    assert!(
+        check_final_poly_degree(&final_poly, final_height >> config.log_blowup),
+        "final polynomial has degree at least final_height / blowup"
+    );
+    let final_poly_len = if config.pad_final_poly {
+        config.final_poly_len()
+    } else {
+        final_height >> config.log_blowup
+    };
+    final_poly.resize(final_poly_len, Challenge::zero());
+
+    let final_poly_commitment = config.commit_final_poly.then(|| {
+        let width = final_poly.len();
+        let (commit, prover_data) = config
+            .mmcs
+            .commit_matrix(RowMajorMatrix::new(final_poly.clone(), width));
+        let (_, opening_proof) = config.mmcs.open_batch(0, &prover_data);
+        FinalPolyCommitment {
+            commit,
+            opening_proof,
+        }
+    });
+
+    Ok(CommitPhaseResult {
         commits,
         data,
         final_poly,
-    }
+        mixed_inputs,
+        final_poly_commitment,
+        #[cfg(test)]
+        folded_layers,
+    })
+}
+
+/// For each commit phase round, open the rows of all the queries, at `indices` into the largest
+/// codeword, with a single proof, if the round's MMCS can open several indices at once (see
+/// `Mmcs::open_multi_batch`). Rounds it can't are left to be opened query by query.
+fn open_rounds_for_all_queries<F, M, Gr>(
+    config: &FriConfig<M, Gr>,
+    commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
+    mut indices: Vec<usize>,
+) -> Vec<Option<M::Proof>>
+where
+    F: Field,
+    M: Mmcs<F>,
+{
+    commit_phase_commits
+        .iter()
+        .enumerate()
+        .map(|(i, commit)| {
+            let mmcs = config.commit_phase_mmcs(i);
+            let arity = mmcs.get_matrices(commit)[0].width();
+            indices
+                .iter_mut()
+                .for_each(|index| *index >>= log2_strict_usize(arity));
+            mmcs.open_multi_batch(&indices, commit)
+                .map(|(_, proof)| proof)
+        })
+        .collect()
 }
 
-fn answer_query<F, M>(
-    config: &FriConfig<M>,
+/// Answer a query at `index` into the largest codeword. Rounds flagged in `batched_rounds` have
+/// already been opened for all queries at once, so only their sibling values are recorded.
+fn answer_query<G, F, M, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
     commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
     index: usize,
+    batched_rounds: &[bool],
 ) -> Vec<CommitPhaseProofStep<F, M>>
 where
     F: Field,
     M: Mmcs<F>,
+    G: FriGenericConfig<F>,
 {
+    let log_arity = g.log_arity();
+    let arity = 1 << log_arity;
+    // Reused across rounds, so that opening a round doesn't allocate a `Vec` per opened row.
+    let mut opened_values = Vec::new();
+    let mut open_scratch = M::OpenScratch::default();
+    let mut index_i = index;
     commit_phase_commits
         .iter()
         .enumerate()
         .map(|(i, commit)| {
-            let index_i = index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
+            // The commit phase codeword is the first (and tallest) matrix of the round, with one
+            // encoded row per folded element, which may be wider than the folding arity. Any other
+            // matrices committed alongside it are in rows of the arity, and are opened at the
+            // correspondingly shifted index.
+            let mmcs = config.commit_phase_mmcs(i);
+            let matrices = mmcs.get_matrices(commit);
+            assert!(!matrices.is_empty());
+            let codeword_width = matrices[0].width();
+            assert!(codeword_width >= arity);
+            assert!(matrices[1..].iter().all(|m| m.width() == arity));
+            let heights: Vec<usize> = matrices.iter().map(|m| m.height()).collect();
+            assert!(heights.iter().all(|&h| h <= heights[0]));
+            let log_max_height = log2_strict_usize(heights[0]);
+
+            let index_row = index_i >> log_arity;
+            let opening_proof = if batched_rounds[i] {
+                opened_values.clear();
+                for (matrix, &height) in izip!(&matrices, &heights) {
+                    let bits_reduced = log_max_height - log2_strict_usize(height);
+                    opened_values.extend(matrix.row(index_row >> bits_reduced));
+                }
+                None
+            } else {
+                Some(mmcs.open_batch_into(index_row, commit, &mut opened_values, &mut open_scratch))
+            };
+            assert_eq!(
+                opened_values.len(),
+                codeword_width + arity * (heights.len() - 1),
+                "Committed inputs should be in rows of the folding arity"
+            );
+            let (codeword_row, input_rows) = opened_values.split_at_mut(codeword_width);
+            g.decode_commit_phase_row(index_row, log_max_height, codeword_row);
 
-            let (mut opened_rows, opening_proof) = config.mmcs.open_batch(index_pair, commit);
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
+            let position = index_i % arity;
+            let sibling_values = codeword_row[..arity]
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != position)
+                .map(|(_, &v)| v)
+                .collect();
+            let extra_sibling_values = izip!(input_rows.chunks_exact(arity), &heights[1..])
+                .flat_map(|(opened_row, &height)| {
+                    let bits_reduced = log_max_height - log2_strict_usize(height);
+                    let position = (index_i >> bits_reduced) % arity;
+                    opened_row
+                        .iter()
+                        .enumerate()
+                        .filter(move |&(j, _)| j != position)
+                        .map(|(_, &v)| v)
+                })
+                .collect();
+            index_i = index_row;
 
             CommitPhaseProofStep {
-                sibling_value,
+                sibling_values,
+                extra_sibling_values,
                 opening_proof,
             }
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
+    use p3_challenger::{CanSampleBits, DuplexChallenger};
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
+    use p3_field::extension::BinomialExtensionField;
+    use p3_field::{AbstractExtensionField, AbstractField};
+    use p3_matrix::util::reverse_matrix_index_bits;
+    use p3_matrix::Matrix;
+    use p3_merkle_tree::FieldMerkleTreeMmcs;
+    use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::testing::SeededTestChallenger;
+    use crate::{fold_once, verifier, TwoAdicFriGenericConfig};
+
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+    type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixBabyBear, 16, 7>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs = FieldMerkleTreeMmcs<
+        <Val as Field>::Packing,
+        <Val as Field>::Packing,
+        MyHash,
+        MyCompress,
+        8,
+    >;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    fn test_config(rng: &mut ChaCha20Rng) -> FriConfig<ChallengeMmcs> {
+        let perm = Perm::new_from_rng_128(
+            Poseidon2ExternalMatrixGeneral,
+            DiffusionMatrixBabyBear::default(),
+            rng,
+        );
+        let mmcs = ChallengeMmcs::new(ValMmcs::new(
+            MyHash::new(perm.clone()),
+            MyCompress::new(perm),
+        ));
+        FriConfig::new(1, 0, 1, 0, mmcs)
+    }
+
+    #[test]
+    fn answer_query_with_two_matrices_per_round() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        // A codeword of length 16 committed alongside a second codeword of length 8.
+        let codeword = RowMajorMatrix::<Challenge>::rand(&mut rng, 8, 2);
+        let other = RowMajorMatrix::<Challenge>::rand(&mut rng, 4, 2);
+        let (commit, prover_data) = config.mmcs.commit(vec![codeword.clone(), other.clone()]);
+
+        for index in 0..16 {
+            let steps = answer_query(
+                &g,
+                &config,
+                core::slice::from_ref(&prover_data),
+                index,
+                &[false],
+            );
+            assert_eq!(steps.len(), 1);
+            let step = &steps[0];
+            assert_eq!(
+                step.sibling_values,
+                vec![codeword.get(index >> 1, (index ^ 1) % 2)]
+            );
+            assert_eq!(
+                step.extra_sibling_values,
+                vec![other.get(index >> 2, ((index >> 1) ^ 1) % 2)]
+            );
+
+            let opened_values = vec![
+                codeword.row(index >> 1).collect(),
+                other.row(index >> 2).collect(),
+            ];
+            let dims = [codeword.dimensions(), other.dimensions()];
+            config
+                .mmcs
+                .verify_batch(
+                    &commit,
+                    &dims,
+                    index >> 1,
+                    &opened_values,
+                    step.opening_proof.as_ref().unwrap(),
+                )
+                .expect("opening of both matrices should verify");
+        }
+    }
+
+    #[test]
+    fn answer_query_records_all_siblings_of_arity_four_row() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        // A codeword of length 32 committed in rows of 4, as if folding by 4 at a time.
+        let codeword = RowMajorMatrix::<Challenge>::rand(&mut rng, 8, 4);
+        let (commit, prover_data) = config.mmcs.commit_matrix(codeword.clone());
+
+        for index in 0..32 {
+            let steps = answer_query(
+                &g,
+                &config,
+                core::slice::from_ref(&prover_data),
+                index,
+                &[false],
+            );
+            assert_eq!(steps.len(), 1);
+            let step = &steps[0];
+            let row: Vec<Challenge> = codeword.row(index >> 2).collect();
+            let expected: Vec<Challenge> =
+                (0..4).filter(|&j| j != index % 4).map(|j| row[j]).collect();
+            assert_eq!(step.sibling_values, expected);
+            assert!(step.extra_sibling_values.is_empty());
+
+            config
+                .mmcs
+                .verify_batch(
+                    &commit,
+                    &[codeword.dimensions()],
+                    index >> 2,
+                    &[row],
+                    step.opening_proof.as_ref().unwrap(),
+                )
+                .expect("opening of the arity 4 row should verify");
+        }
+    }
+
+    #[test]
+    fn fold_once_matches_commit_phase() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Val::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input: Vec<Challenge> = lde.values.into_iter().map(Challenge::from_base).collect();
+
+        let result = commit_phase::<_, Val, _, _, _, _>(
+            &g,
+            &config,
+            || input.clone(),
+            vec![],
+            1,
+            None,
+            &mut SeededTestChallenger::<Val>::new(0),
+            |_, _| {},
+        )
+        .unwrap();
+        // The seeded challenger ignores observations, so the first beta is its first sample.
+        let beta: Challenge = SeededTestChallenger::<Val>::new(0).sample_ext_element();
+        let folded = fold_once(&g, beta, RowMajorMatrix::new(input, 2));
+
+        let second_layer = config.mmcs.get_matrices(&result.data[1]).pop().unwrap();
+        assert_eq!(folded, second_layer.values);
+    }
+
+    #[test]
+    fn commit_phase_folded_layers() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Val::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input: Vec<Challenge> = lde.values.into_iter().map(Challenge::from_base).collect();
+
+        let result = commit_phase::<_, Val, _, _, _, _>(
+            &g,
+            &config,
+            || input.clone(),
+            vec![],
+            1,
+            None,
+            &mut SeededTestChallenger::<Val>::new(0),
+            |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(result.folded_layers.len(), result.commits.len());
+
+        // The seeded challenger ignores observations, so the betas are its successive samples.
+        let mut betas = SeededTestChallenger::<Val>::new(0);
+        let mut prev = input;
+        for layer in result.folded_layers {
+            let beta: Challenge = betas.sample_ext_element();
+            assert_eq!(layer, fold_once(&g, beta, RowMajorMatrix::new(prev, 2)));
+            prev = layer;
+        }
+        assert_eq!(prev.len(), 2);
+    }
+
+    #[test]
+    fn commit_phase_with_first_beta() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Val::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input: Vec<Challenge> = lde.values.into_iter().map(Challenge::from_base).collect();
+
+        let first_beta: Challenge = rng.gen();
+        let result = commit_phase::<_, Val, _, _, _, _>(
+            &g,
+            &config,
+            || input.clone(),
+            vec![],
+            1,
+            Some(first_beta),
+            &mut SeededTestChallenger::<Val>::new(0),
+            |_, _| {},
+        )
+        .unwrap();
+
+        // The first round folds with `first_beta`, and since it isn't sampled, the later rounds'
+        // betas are the seeded challenger's successive samples from the start.
+        let mut betas = SeededTestChallenger::<Val>::new(0);
+        let mut prev = input;
+        for (round, layer) in result.folded_layers.into_iter().enumerate() {
+            let beta = if round == 0 {
+                first_beta
+            } else {
+                betas.sample_ext_element()
+            };
+            assert_eq!(layer, fold_once(&g, beta, RowMajorMatrix::new(prev, 2)));
+            prev = layer;
+        }
+    }
+
+    #[test]
+    fn prove_is_reproducible_with_seeded_challenger() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let config = test_config(&mut rng);
+        let g = TwoAdicFriGenericConfig::<(), ()>(PhantomData);
+
+        // The LDE of a random polynomial of degree less than 2^5, in bit-reversed order.
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Val::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input: Vec<Challenge> = lde.values.into_iter().map(Challenge::from_base).collect();
+
+        let prove_with_seed = |seed| {
+            let mut challenger = SeededTestChallenger::<Val>::new(seed);
+            prove(&g, &config, vec![input.clone()], &mut challenger, |_| ()).unwrap()
+        };
+        let proof = prove_with_seed(1);
+        let same_seed_proof = prove_with_seed(1);
+        let other_seed_proof = prove_with_seed(2);
+
+        assert_eq!(
+            proof.commit_phase_commits,
+            same_seed_proof.commit_phase_commits
+        );
+        assert_eq!(proof.final_poly, same_seed_proof.final_poly);
+        // The first layer is the input itself, but the later layers depend on the betas.
+        assert_eq!(
+            proof.commit_phase_commits[0],
+            other_seed_proof.commit_phase_commits[0]
+        );
+        assert_ne!(
+            proof.commit_phase_commits[1],
+            other_seed_proof.commit_phase_commits[1]
+        );
+        assert_ne!(proof.final_poly, other_seed_proof.final_poly);
+    }
+
+    #[test]
+    fn proofs_pass_verify() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let perm = Perm::new_from_rng_128(
+            Poseidon2ExternalMatrixGeneral,
+            DiffusionMatrixBabyBear::default(),
+            &mut rng,
+        );
+        let config = FriConfig {
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            ..test_config(&mut rng)
+        };
+        let g = TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData);
+
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 6, 1);
+        let mut lde = Radix2Dit::default().coset_lde_batch(evals, 1, Val::generator());
+        reverse_matrix_index_bits(&mut lde);
+        let input: Vec<Challenge> = lde.values.into_iter().map(Challenge::from_base).collect();
+        let log_height = log2_strict_usize(input.len());
+
+        let mut p_challenger = DuplexChallenger::<Val, Perm, 16, 8>::new(perm.clone());
+        let proof = prove(
+            &g,
+            &config,
+            vec![input.clone()],
+            &mut p_challenger,
+            |index| vec![(log_height, input[index])],
+        )
+        .unwrap();
+
+        // The verifier re-derives the betas, the proof of work and the query indices from its
+        // own challenger, so it only accepts if these match what `prove` used.
+        let verify_proof = |proof| {
+            let mut v_challenger = DuplexChallenger::<Val, Perm, 16, 8>::new(perm.clone());
+            verifier::verify(&g, &config, proof, &mut v_challenger, |_index, openings| {
+                Ok(openings.clone())
+            })
+            .map(|()| v_challenger)
+        };
+        let mut v_challenger = verify_proof(&proof).unwrap();
+        assert_eq!(p_challenger.sample_bits(16), v_challenger.sample_bits(16));
+
+        let mut bad_final_poly = proof.clone();
+        bad_final_poly.final_poly[0] += Challenge::one();
+        assert!(matches!(
+            verify_proof(&bad_final_poly),
+            Err(verifier::FriError::FinalPolyMismatch)
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use core::marker::PhantomData;
+
+use p3_challenger::{CanObserve, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger};
+use p3_field::Field;
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// A challenger whose samples are drawn from a seeded `ChaCha8Rng`, for reproducible tests.
+///
+/// Observed values are ignored, so the samples depend only on the seed and on how many were drawn
+/// before. This is of course completely insecure; its only use is to make the challenges of e.g.
+/// the commit phase reproducible without setting up a cryptographic challenger. Proof of work is
+/// trivial: `grind` returns zero, which `check_witness` always accepts.
+#[derive(Clone, Debug)]
+pub struct SeededTestChallenger<F> {
+    rng: ChaCha8Rng,
+    _phantom: PhantomData<F>,
+}
+
+impl<F> SeededTestChallenger<F> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F, T> CanObserve<T> for SeededTestChallenger<F> {
+    fn observe(&mut self, _value: T) {}
+}
+
+impl<F> CanSample<F> for SeededTestChallenger<F>
+where
+    Standard: Distribution<F>,
+{
+    fn sample(&mut self) -> F {
+        self.rng.gen()
+    }
+}
+
+impl<F> CanSampleBits<usize> for SeededTestChallenger<F> {
+    fn sample_bits(&mut self, bits: usize) -> usize {
+        debug_assert!(bits < usize::BITS as usize);
+        self.rng.gen::<usize>() & ((1 << bits) - 1)
+    }
+}
+
+impl<F: Field> FieldChallenger<F> for SeededTestChallenger<F> where Standard: Distribution<F> {}
+
+impl<F: Field> GrindingChallenger for SeededTestChallenger<F> {
+    type Witness = F;
+
+    fn grind(&mut self, _bits: usize) -> F {
+        F::zero()
+    }
+
+    fn grind_with_stats(&mut self, bits: usize) -> (F, u64) {
+        (self.grind(bits), 1)
+    }
+
+    fn check_witness(&mut self, _bits: usize, _witness: F) -> bool {
+        true
+    }
+}
@@ -1,13 +1,17 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::{izip, Itertools};
-use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{BatchOpeningRef, Mmcs};
 use p3_field::{ExtensionField, Field};
 use p3_matrix::Dimensions;
 
-use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof};
+use crate::{
+    CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof, GrindingStrategy, PowStage,
+    QueryProof,
+};
 
 #[derive(Debug)]
 pub enum FriError<CommitMmcsErr, InputError> {
@@ -16,131 +20,750 @@ pub enum FriError<CommitMmcsErr, InputError> {
     InputError(InputError),
     FinalPolyMismatch,
     InvalidPowWitness,
+    /// The proof opens a commit phase round for all queries at once, but the commit phase MMCS
+    /// can't verify such openings, i.e. `verify_multi_batch` returned `None`.
+    MultiOpeningUnsupported,
+    /// The proof is for an input of `2^actual` evaluations, but the verifier expected `2^expected`.
+    LogMaxHeightMismatch {
+        expected: usize,
+        actual: usize,
+    },
 }
 
-pub fn verify<G, Val, Challenge, M, Challenger>(
+/// Same as `verify`, but first checks that the proof is for an input of `2^log_max_height`
+/// evaluations, as the caller expects from its own context, e.g. the sizes of the committed
+/// matrices. A proof made for a different input size would otherwise only fail later, for less
+/// obvious reasons.
+pub fn verify_with_log_max_height<G, Val, Challenge, M, Challenger, Gr>(
     g: &G,
-    config: &FriConfig<M>,
-    proof: &FriProof<Challenge, M, Challenger::Witness, G::InputProof>,
+    config: &FriConfig<M, Gr>,
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+    log_max_height: usize,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+) -> Result<(), FriError<M::Error, G::InputError>>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    if proof.log_max_height() != log_max_height {
+        return Err(FriError::LogMaxHeightMismatch {
+            expected: log_max_height,
+            actual: proof.log_max_height(),
+        });
+    }
+    verify(g, config, proof, challenger, open_input)
+}
+
+/// Same as `verify`, but first observes `input_commitments`, the commitments to the inputs which
+/// `open_input` checks openings against, in the order given, as
+/// `prover::prove_with_input_commitments` does.
+pub fn verify_with_input_commitments<G, Val, Challenge, M, Challenger, Gr, C>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    input_commitments: &[C],
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+) -> Result<(), FriError<M::Error, G::InputError>>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment> + CanObserve<C>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+    C: Clone,
+{
+    for commit in input_commitments {
+        challenger.observe(commit.clone());
+    }
+    verify(g, config, proof, challenger, open_input)
+}
+
+pub fn verify<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+) -> Result<(), FriError<M::Error, G::InputError>>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    verify_inner(g, config, proof, challenger, open_input, None, |_| true)
+}
+
+/// Same as `verify`, but uses `first_beta` as the first folding challenge of the first commit
+/// phase round, for proofs made by `prover::prove_with_first_beta`. `first_beta` must be bound by
+/// the transcript, as described there.
+pub fn verify_with_first_beta<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    first_beta: Challenge,
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+) -> Result<(), FriError<M::Error, G::InputError>>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    verify_inner(
+        g,
+        config,
+        proof,
+        challenger,
+        open_input,
+        Some(first_beta),
+        |_| true,
+    )
+}
+
+/// Same as `verify`, but only checks the query proofs at the positions `indices_to_check` within
+/// `proof.query_proofs`, e.g. for an auditor spot-checking proofs.
+///
+/// This does NOT give the soundness of `verify`: a proof is accepted if the checked queries are
+/// consistent, whatever the other queries contain, so checking `k` of the `num_queries` queries
+/// gives roughly the soundness of a proof with only `k` queries. Everything other than the
+/// queries, including the proof of work, is still checked, and the challenger is left in the same
+/// state as by `verify`.
+///
+/// If any commit phase round was opened for all queries at once (see
+/// `FriProof::commit_phase_batch_proofs`), checking that opening needs every query, so all of them
+/// are checked, as by `verify`.
+///
+/// Panics if any of `indices_to_check` is not the position of a query proof.
+pub fn verify_queries<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
+    indices_to_check: &[usize],
+    challenger: &mut Challenger,
+    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+) -> Result<(), FriError<M::Error, G::InputError>>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
+{
+    assert!(
+        indices_to_check
+            .iter()
+            .all(|&i| i < proof.query_proofs.len()),
+        "query index out of range"
+    );
+    verify_inner(g, config, proof, challenger, open_input, None, |i| {
+        indices_to_check.contains(&i)
+    })
+}
+
+fn verify_inner<G, Val, Challenge, M, Challenger, Gr>(
+    g: &G,
+    config: &FriConfig<M, Gr>,
+    proof: &FriProof<Challenge, M, Gr::Witness, G::InputProof>,
     challenger: &mut Challenger,
     open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+    first_beta: Option<Challenge>,
+    check_query: impl Fn(usize) -> bool,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
     Val: Field,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
+    Gr: GrindingStrategy<Challenger>,
 {
-    let betas: Vec<Challenge> = proof
+    let (betas, mix_challenges): (Vec<Vec<Challenge>>, Vec<Option<Challenge>>) = proof
         .commit_phase_commits
         .iter()
-        .map(|comm| {
+        .enumerate()
+        .map(|(round, comm)| {
             challenger.observe(comm.clone());
-            challenger.sample_ext_element()
+            let betas = round_betas::<_, Val, _, _>(g, round, first_beta, challenger);
+            (betas, g.sample_mix_challenge::<Val, _>(challenger))
         })
+        .unzip();
+
+    let params = config.params(g);
+    if proof.query_proofs.len() != params.num_queries {
+        return Err(FriError::InvalidProofShape);
+    }
+    // One entry per round, and one for the final codeword.
+    if proof.mixed_inputs.len() != proof.commit_phase_commits.len() + 1 {
+        return Err(FriError::InvalidProofShape);
+    }
+    if proof.commit_phase_batch_proofs.len() != proof.commit_phase_commits.len() {
+        return Err(FriError::InvalidProofShape);
+    }
+    let batched_rounds: Vec<bool> = proof
+        .commit_phase_batch_proofs
+        .iter()
+        .map(Option::is_some)
         .collect();
-    challenger.observe_ext_element(proof.final_poly);
+    let any_batched = batched_rounds.contains(&true);
 
-    if proof.query_proofs.len() != config.num_queries {
+    let log_max_height = proof.log_max_height();
+    let log_arity = params.log_arity;
+    let log_final_height = config
+        .log_final_height(log_max_height, log_arity)
+        .ok_or(FriError::InvalidProofShape)?;
+    if log_arity == 0
+        || log_max_height != proof.commit_phase_commits.len() * log_arity + log_final_height
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+    // Inputs committed alongside the last round's codeword have the final codeword's length, in
+    // rows of the arity, so there must be at least one such row.
+    if config.commit_mixed_inputs
+        && proof
+            .mixed_inputs
+            .last()
+            .is_some_and(|inputs| !inputs.is_empty())
+        && log_final_height < log_arity
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+    let final_poly_len = if config.pad_final_poly {
+        params.final_poly_len()
+    } else {
+        1 << (log_final_height - params.log_blowup)
+    };
+    if proof.final_poly.len() != final_poly_len || final_poly_len > g.max_final_poly_len() {
+        return Err(FriError::InvalidProofShape);
+    }
+    // With `fixed_rounds`, the final codeword may be too short for a polynomial with
+    // `final_poly_len` coefficients, in which case the final polynomial must be padded.
+    if !check_final_poly_degree(
+        &proof.final_poly,
+        1 << (log_final_height - params.log_blowup),
+    ) {
         return Err(FriError::InvalidProofShape);
     }
 
-    // Check PoW.
-    if !challenger.check_witness(config.proof_of_work_bits, proof.pow_witness) {
-        return Err(FriError::InvalidPowWitness);
+    match (config.commit_final_poly, &proof.final_poly_commitment) {
+        (false, None) => {}
+        (true, Some(final_poly_commitment)) => {
+            let dims = Dimensions {
+                width: proof.final_poly.len(),
+                height: 1,
+            };
+            config
+                .mmcs
+                .verify_batch(
+                    &final_poly_commitment.commit,
+                    &[dims],
+                    0,
+                    &[proof.final_poly.clone()],
+                    &final_poly_commitment.opening_proof,
+                )
+                .map_err(FriError::CommitPhaseMmcsError)?;
+        }
+        _ => return Err(FriError::InvalidProofShape),
+    }
+
+    if config.pow_stage == PowStage::AfterCommitPhase {
+        check_pow(
+            config,
+            challenger,
+            params.pow_bits,
+            proof.pow_bits,
+            proof.pow_witness.clone(),
+        )?;
+    }
+    match &proof.final_poly_commitment {
+        Some(final_poly_commitment) => challenger.observe(final_poly_commitment.commit.clone()),
+        None => {
+            for &coeff in &proof.final_poly {
+                challenger.observe_ext_element(coeff);
+            }
+        }
+    }
+    if config.pow_stage == PowStage::BeforeQueries {
+        check_pow(
+            config,
+            challenger,
+            params.pow_bits,
+            proof.pow_bits,
+            proof.pow_witness.clone(),
+        )?;
     }
 
-    let log_max_height = proof.commit_phase_commits.len() + config.log_blowup;
+    let indices: Vec<usize> = proof
+        .query_proofs
+        .iter()
+        .map(|_| challenger.sample_bits(params.query_index_bits(log_max_height)))
+        .collect();
+
+    match (config.post_query_pow_bits, &proof.post_query_pow_witness) {
+        (0, None) => {}
+        (0, Some(_)) | (_, None) => return Err(FriError::InvalidProofShape),
+        (bits, Some(witness)) => {
+            observe_query_responses(challenger, &proof.query_proofs);
+            if !config
+                .grinding
+                .check_witness(challenger, bits, witness.clone())
+            {
+                return Err(FriError::InvalidPowWitness);
+            }
+        }
+    }
 
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
+    // For each round opened for all queries at once, every query's index and opened row.
+    let mut batch_indices = vec![vec![]; proof.commit_phase_commits.len()];
+    let mut batch_rows = vec![vec![]; proof.commit_phase_commits.len()];
+    for (i, (index, qp)) in izip!(indices, &proof.query_proofs).enumerate() {
+        if !any_batched && !check_query(i) {
+            continue;
+        }
+        if qp.commit_phase_openings.len() != proof.commit_phase_commits.len() {
+            return Err(FriError::InvalidProofShape);
+        }
         let ro = open_input(index, &qp.input_proof).map_err(FriError::InputError)?;
 
         debug_assert!(
-            ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
+            ro.iter().tuple_windows().all(|((l, _), (r, _))| l >= r),
             "reduced openings sorted by height descending"
         );
 
-        let folded_eval = verify_query(
+        let domain_index = index >> params.extra_index_bits;
+        let (folded_eval, opened_rows) = verify_query(
             g,
             config,
-            index >> g.extra_query_index_bits(),
+            domain_index,
             izip!(
                 &betas,
                 &proof.commit_phase_commits,
                 &qp.commit_phase_openings
             ),
             ro,
+            &mix_challenges,
+            &proof.mixed_inputs,
+            &batched_rounds,
             log_max_height,
         )?;
+        for (round, index_row, rows) in opened_rows {
+            batch_indices[round].push(index_row);
+            batch_rows[round].push(rows);
+        }
 
-        if folded_eval != proof.final_poly {
+        let final_index = domain_index >> (proof.commit_phase_commits.len() * log_arity);
+        let final_eval = g.eval_final_poly(final_index, log_final_height, &proof.final_poly);
+        if folded_eval != final_eval {
             return Err(FriError::FinalPolyMismatch);
         }
     }
 
+    for (round, (commit, batch_proof)) in izip!(
+        &proof.commit_phase_commits,
+        &proof.commit_phase_batch_proofs
+    )
+    .enumerate()
+    {
+        let Some(batch_proof) = batch_proof else {
+            continue;
+        };
+        let dims = commit_phase_dims(
+            g,
+            log_max_height - (round + 1) * log_arity,
+            committed_inputs(config, &proof.mixed_inputs, round).len(),
+        );
+        config
+            .commit_phase_mmcs(round)
+            .verify_multi_batch(
+                commit,
+                &dims,
+                &batch_indices[round],
+                &batch_rows[round],
+                batch_proof,
+            )
+            .ok_or(FriError::MultiOpeningUnsupported)?
+            .map_err(FriError::CommitPhaseMmcsError)?;
+    }
+
     Ok(())
 }
 
+/// Observe the sibling values of every query's commit phase openings, query by query and round by
+/// round, ahead of the proof of work done after the queries (see `FriConfig::post_query_pow_bits`).
+pub(crate) fn observe_query_responses<F, Val, M, InputProof, Challenger>(
+    challenger: &mut Challenger,
+    query_proofs: &[QueryProof<F, M, InputProof>],
+) where
+    Val: Field,
+    F: ExtensionField<Val>,
+    M: Mmcs<F>,
+    Challenger: FieldChallenger<Val>,
+{
+    for step in query_proofs.iter().flat_map(|qp| &qp.commit_phase_openings) {
+        for &value in step.sibling_values.iter().chain(&step.extra_sibling_values) {
+            challenger.observe_ext_element(value);
+        }
+    }
+}
+
+fn check_pow<M, Challenger, Gr, CommitMmcsErr, InputError>(
+    config: &FriConfig<M, Gr>,
+    challenger: &mut Challenger,
+    min_bits: usize,
+    pow_bits: usize,
+    pow_witness: Option<Gr::Witness>,
+) -> Result<(), FriError<CommitMmcsErr, InputError>>
+where
+    Gr: GrindingStrategy<Challenger>,
+{
+    // The difficulty is taken from the proof. Without a time budget the prover grinds to exactly
+    // `min_bits`; with one it may go further, but never below.
+    let expected_bits = match config.grind_time_budget {
+        None => pow_bits == min_bits,
+        Some(_) => pow_bits >= min_bits,
+    };
+    if !expected_bits {
+        return Err(FriError::InvalidProofShape);
+    }
+    match (pow_bits, pow_witness) {
+        (0, None) => Ok(()),
+        (0, Some(_)) | (_, None) => Err(FriError::InvalidProofShape),
+        (bits, Some(witness)) => {
+            if config.grinding.check_witness(challenger, bits, witness) {
+                Ok(())
+            } else {
+                Err(FriError::InvalidPowWitness)
+            }
+        }
+    }
+}
+
+/// The folding challenges of commit phase round `round`, sampled after observing its commitment.
+/// If `first_beta` is given, it's observed and used in place of the first challenge of round 0.
+pub(crate) fn round_betas<G, Val, Challenge, Challenger>(
+    g: &G,
+    round: usize,
+    first_beta: Option<Challenge>,
+    challenger: &mut Challenger,
+) -> Vec<Challenge>
+where
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+    Challenger: FieldChallenger<Val>,
+    G: FriGenericConfig<Challenge>,
+{
+    (0..g.challenges_per_round())
+        .map(|i| match first_beta {
+            Some(beta) if round == 0 && i == 0 => {
+                challenger.observe_ext_element(beta);
+                beta
+            }
+            _ => challenger.sample_ext_element(),
+        })
+        .collect()
+}
+
+/// Returns whether `final_poly` has degree less than `max_len`, i.e. whether it is a valid final
+/// polynomial for a FRI instance whose final polynomial has at most `max_len` coefficients.
+///
+/// `final_poly` must be in *coefficient* form, lowest degree first; this is the form in which FRI
+/// proofs carry the final polynomial. Trailing zero coefficients are accepted, since they don't
+/// change the polynomial, so a polynomial padded beyond `max_len` with zeros still passes.
+pub fn check_final_poly_degree<F: Field>(final_poly: &[F], max_len: usize) -> bool {
+    final_poly.iter().skip(max_len).all(|c| c.is_zero())
+}
+
+/// Recompute the fold chain of a single query from its sibling values, as `verify` does, but
+/// without checking any openings.
+///
+/// `index` is the query's index into the largest codeword, `betas` are the folding challenges and
+/// `sibling_values` the sibling of the query's location in each round, and `reduced_openings` are
+/// the query's reduced input openings, sorted by log height descending. The `i`-th element of the
+/// result is the expected value of the round `i` commit phase codeword at the query's location,
+/// and the last element is the final folded value, with any input of the final height added, which
+/// should match the final polynomial. This
+/// is useful for finding the round at which a failing proof diverges from the committed values.
+///
+/// This assumes folding with arity 2 and one folding challenge per round, i.e.
+/// `g.log_arity() == 1` and `g.challenges_per_round() == 1`, and that inputs are mixed by adding
+/// them, as with the default `mix_input`.
+pub fn fold_query<G, F>(
+    g: &G,
+    mut index: usize,
+    log_max_height: usize,
+    betas: &[F],
+    sibling_values: &[F],
+    reduced_openings: &[(usize, F)],
+) -> Vec<F>
+where
+    F: Field,
+    G: FriGenericConfig<F>,
+{
+    assert_eq!(betas.len(), sibling_values.len());
+    let mut folded_eval = F::zero();
+    let mut folded_evals = Vec::with_capacity(betas.len() + 1);
+    let mut ro_iter = reduced_openings.iter().peekable();
+
+    for (log_folded_height, &beta, &sibling_value) in
+        izip!((0..log_max_height).rev(), betas, sibling_values)
+    {
+        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
+            folded_eval += *ro;
+        }
+        folded_evals.push(folded_eval);
+
+        let mut evals = vec![folded_eval; 2];
+        evals[(index ^ 1) % 2] = sibling_value;
+        index >>= 1;
+        folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
+    }
+    if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_max_height - betas.len()) {
+        folded_eval += *ro;
+    }
+    folded_evals.push(folded_eval);
+
+    folded_evals
+}
+
 type CommitStep<'a, F, M> = (
-    &'a F,
+    &'a Vec<F>,
     &'a <M as Mmcs<F>>::Commitment,
     &'a CommitPhaseProofStep<F, M>,
 );
 
-fn verify_query<'a, G, F, M>(
+/// The inputs committed alongside the codeword in commit phase round `round`, i.e. those mixed in
+/// after its fold, if `commit_mixed_inputs` is set.
+fn committed_inputs<'a, M, Gr>(
+    config: &FriConfig<M, Gr>,
+    mixed_inputs: &'a [Vec<usize>],
+    round: usize,
+) -> &'a [usize] {
+    match mixed_inputs.get(round + 1) {
+        Some(inputs) if config.commit_mixed_inputs => inputs,
+        _ => &[],
+    }
+}
+
+/// The dimensions of the matrices committed in a commit phase round whose codeword is folded to
+/// `2^log_folded_height` evaluations: the codeword, in rows as encoded by
+/// `encode_commit_phase_row`, followed by `num_committed_inputs` inputs of the folded codeword's
+/// length, in rows of `2^log_arity()` evaluations.
+fn commit_phase_dims<G, F>(
+    g: &G,
+    log_folded_height: usize,
+    num_committed_inputs: usize,
+) -> Vec<Dimensions>
+where
+    F: Field,
+    G: FriGenericConfig<F>,
+{
+    let log_arity = g.log_arity();
+    // The encoding may widen the rows, so the width is that of an encoded row.
+    let encoded_row =
+        g.encode_commit_phase_row(0, log_folded_height, vec![F::zero(); 1 << log_arity]);
+    let codeword_dims = Dimensions {
+        width: encoded_row.len(),
+        height: 1 << log_folded_height,
+    };
+    let input_dims = Dimensions {
+        width: 1 << log_arity,
+        height: 1 << (log_folded_height - log_arity),
+    };
+    iter::once(codeword_dims)
+        .chain(iter::repeat(input_dims).take(num_committed_inputs))
+        .collect()
+}
+
+/// Fold a single query, checking its openings of every round not in `batched_rounds`. Returns the
+/// final folded value, along with the round, index and opened rows of each round in
+/// `batched_rounds`, for the caller to check, as those were opened for all queries at once.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn verify_query<'a, G, F, M, Gr>(
     g: &G,
-    config: &FriConfig<M>,
+    config: &FriConfig<M, Gr>,
     mut index: usize,
     steps: impl Iterator<Item = CommitStep<'a, F, M>>,
     reduced_openings: Vec<(usize, F)>,
+    mix_challenges: &[Option<F>],
+    mixed_inputs: &[Vec<usize>],
+    batched_rounds: &[bool],
     log_max_height: usize,
-) -> Result<F, FriError<M::Error, G::InputError>>
+) -> Result<(F, Vec<(usize, usize, Vec<Vec<F>>)>), FriError<M::Error, G::InputError>>
 where
     F: Field,
     M: Mmcs<F> + 'a,
     G: FriGenericConfig<F>,
 {
-    let mut folded_eval = F::zero();
-    let mut ro_iter = reduced_openings.into_iter().peekable();
+    // The first input is the initial codeword, and every other one is mixed into the codeword in
+    // exactly one round, in index order, so must be listed exactly once in `mixed_inputs`.
+    if !mixed_inputs
+        .iter()
+        .flatten()
+        .copied()
+        .eq(1..reduced_openings.len())
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+    // The reduced opening of input `i`, which must have `2^log_height` evaluations.
+    let input_eval =
+        |i: usize, log_height: usize| -> Result<F, FriError<M::Error, G::InputError>> {
+            match reduced_openings.get(i) {
+                Some(&(lh, ro)) if lh == log_height => Ok(ro),
+                _ => Err(FriError::InvalidProofShape),
+            }
+        };
 
-    for (log_folded_height, (&beta, comm, opening)) in izip!((0..log_max_height).rev(), steps) {
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
-            folded_eval += ro;
+    let mut folded_eval = input_eval(0, log_max_height)?;
+    // The openings of every round are collected and checked together once the query is folded.
+    let mut openings = vec![];
+    let mut opened_rows = vec![];
+
+    let log_arity = g.log_arity();
+    let arity = 1 << log_arity;
+    for (round, (betas, comm, opening)) in steps.enumerate() {
+        let log_height = log_max_height - round * log_arity;
+        let log_folded_height = log_height - log_arity;
+
+        // The inputs of this round are mixed into its codeword in order, those of the first round
+        // without a challenge and the rest with the one sampled in the previous round.
+        let mix_challenge = round
+            .checked_sub(1)
+            .and_then(|prev_round| mix_challenges[prev_round]);
+        for &i in &mixed_inputs[round] {
+            let input = input_eval(i, log_height)?;
+            folded_eval = g.mix_input(folded_eval, input, mix_challenge);
+        }
+
+        // Every committed matrix has a row of `arity` values opened, of which the proof carries
+        // all but the one at the query's location, which the verifier knows.
+        let committed_inputs = committed_inputs(config, mixed_inputs, round);
+        if opening.sibling_values.len() != arity - 1
+            || opening.extra_sibling_values.len() != committed_inputs.len() * (arity - 1)
+        {
+            return Err(FriError::InvalidProofShape);
         }
+        let with_value_at = |position: usize, siblings: &[F], value: F| {
+            let mut row = siblings.to_vec();
+            row.insert(position, value);
+            row
+        };
 
-        let index_sibling = index ^ 1;
-        let index_pair = index >> 1;
+        let index_row = index >> log_arity;
+        let evals = with_value_at(index % arity, &opening.sibling_values, folded_eval);
 
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        // The committed inputs have the folded codeword's length, so the query's location in them
+        // is the index of its row in the codeword.
+        let mut rows = vec![g.encode_commit_phase_row(index_row, log_folded_height, evals.clone())];
+        for (&i, siblings) in izip!(
+            committed_inputs,
+            opening.extra_sibling_values.chunks_exact(arity - 1)
+        ) {
+            let input = input_eval(i, log_folded_height)?;
+            rows.push(with_value_at(index_row % arity, siblings, input));
+        }
 
-        let dims = &[Dimensions {
-            width: 2,
-            height: 1 << log_folded_height,
-        }];
-        config
-            .mmcs
-            .verify_batch(
-                comm,
-                dims,
-                index_pair,
-                &[evals.clone()],
-                &opening.opening_proof,
-            )
-            .map_err(FriError::CommitPhaseMmcsError)?;
+        let dims = commit_phase_dims(g, log_folded_height, committed_inputs.len());
+        match (batched_rounds[round], &opening.opening_proof) {
+            (true, None) => opened_rows.push((round, index_row, rows)),
+            (false, Some(opening_proof)) => {
+                openings.push((comm, dims, index_row, rows, opening_proof))
+            }
+            _ => return Err(FriError::InvalidProofShape),
+        }
 
-        index = index_pair;
+        index = index_row;
 
-        folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
+        folded_eval = g.fold_row_with_betas(index, log_folded_height, betas, evals.into_iter());
+    }
+
+    // The inputs of the final codeword's height are mixed in after the last fold, with the last
+    // round's challenge.
+    let num_rounds = mixed_inputs.len() - 1;
+    let log_final_height = log_max_height - num_rounds * log_arity;
+    let mix_challenge = num_rounds
+        .checked_sub(1)
+        .and_then(|last_round| mix_challenges[last_round]);
+    for &i in &mixed_inputs[num_rounds] {
+        let input = input_eval(i, log_final_height)?;
+        folded_eval = g.mix_input(folded_eval, input, mix_challenge);
+    }
+
+    let openings: Vec<_> = openings
+        .iter()
+        .map(
+            |(commit, dims, index_row, opened_values, proof)| BatchOpeningRef::<F, M> {
+                commit,
+                dimensions: dims,
+                index: *index_row,
+                opened_values,
+                proof,
+            },
+        )
+        .collect();
+    match &config.first_layer_mmcs {
+        None => config.mmcs.verify_batches(&openings),
+        Some(first_layer_mmcs) => {
+            // The first round's opening, if it isn't batched, comes first.
+            let num_first = match batched_rounds.first() {
+                Some(false) => openings.len().min(1),
+                _ => 0,
+            };
+            let (first, rest) = openings.split_at(num_first);
+            first_layer_mmcs
+                .verify_batches(first)
+                .and_then(|()| config.mmcs.verify_batches(rest))
+        }
     }
+    .map_err(FriError::CommitPhaseMmcsError)?;
 
-    debug_assert!(index < config.blowup(), "index was {}", index);
     debug_assert!(
-        ro_iter.next().is_none(),
-        "verifier reduced_openings were not in descending order?"
+        index < config.blowup() * config.final_poly_len(),
+        "index was {}",
+        index
     );
 
-    Ok(folded_eval)
+    Ok((folded_eval, opened_rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::AbstractField;
+
+    use super::*;
+
+    #[test]
+    fn test_check_final_poly_degree() {
+        type F = BabyBear;
+
+        let constant = [F::from_canonical_u32(7)];
+        assert!(check_final_poly_degree(&constant, 1));
+        assert!(check_final_poly_degree(&constant, 4));
+
+        let linear = [F::one(), F::two()];
+        assert!(check_final_poly_degree(&linear, 2));
+        assert!(!check_final_poly_degree(&linear, 1));
+
+        // Trailing zeros don't raise the degree.
+        let padded = [F::one(), F::two(), F::zero(), F::zero()];
+        assert!(check_final_poly_degree(&padded, 2));
+
+        // Degree 3 with a zero coefficient in the middle is still degree 3.
+        let cubic = [F::one(), F::zero(), F::zero(), F::two()];
+        assert!(!check_final_poly_degree(&cubic, 3));
+        assert!(check_final_poly_degree(&cubic, 4));
+    }
 }
@@ -1,19 +1,35 @@
+use core::cell::RefCell;
 use core::cmp::Reverse;
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+use core::ops::Range;
+use core::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
-use p3_challenger::{CanSampleBits, DuplexChallenger, FieldChallenger};
-use p3_commit::ExtensionMmcs;
+use p3_challenger::{
+    CanObserve, CanSample, CanSampleBits, DuplexChallenger, FieldChallenger, GrindingChallenger,
+    HashChallenger, SerializingChallenger32, TranscriptEvent, TranscriptRecorder,
+};
+use p3_commit::{ExtensionMmcs, Mmcs};
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::extension::BinomialExtensionField;
-use p3_field::{AbstractField, Field};
-use p3_fri::{prover, verifier, FriConfig, TwoAdicFriGenericConfig};
+use p3_field::{AbstractField, ExtensionField, Field};
+use p3_fri::prover::FriProverError;
+use p3_fri::verifier::FriError;
+use p3_fri::{
+    num_commit_rounds, prover, verifier, ChallengerGrinding, CompactFriProof, Flatten, FriConfig,
+    FriGenericConfig, FriParams, FriProof, GrindClock, GrindingStrategy, NoClock, PowStage,
+    TwoAdicFriGenericConfig,
+};
+use p3_keccak::Keccak256Hash;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::util::reverse_matrix_index_bits;
-use p3_matrix::Matrix;
-use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_matrix::{Dimensions, Matrix};
+use p3_merkle_tree::{FieldMerkleTreeError, FieldMerkleTreeMmcs};
 use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
-use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
 use p3_util::log2_strict_usize;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
@@ -29,8 +45,11 @@ type ValMmcs =
 type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
 type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
 type MyFriConfig = FriConfig<ChallengeMmcs>;
+type MyGenericConfig = TwoAdicFriGenericConfig<Vec<(usize, Challenge)>, ()>;
+type MyProof = FriProof<Challenge, ChallengeMmcs, Val, Vec<(usize, Challenge)>>;
+type MyFriError = FriError<<ChallengeMmcs as Mmcs<Challenge>>::Error, ()>;
 
-fn get_ldt_for_testing<R: Rng>(rng: &mut R) -> (Perm, MyFriConfig) {
+fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm, MyFriConfig) {
     let perm = Perm::new_from_rng_128(
         Poseidon2ExternalMatrixGeneral,
         DiffusionMatrixBabyBear::default(),
@@ -39,103 +58,2277 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R) -> (Perm, MyFriConfig) {
     let hash = MyHash::new(perm.clone());
     let compress = MyCompress::new(perm.clone());
     let mmcs = ChallengeMmcs::new(ValMmcs::new(hash, compress));
-    let fri_config = FriConfig {
-        log_blowup: 1,
-        num_queries: 10,
-        proof_of_work_bits: 8,
-        mmcs,
-    };
+    let fri_config = FriConfig::new(1, log_final_poly_len, 10, 8, mmcs);
     (perm, fri_config)
 }
 
-fn do_test_fri_ldt<R: Rng>(rng: &mut R) {
-    let (perm, fc) = get_ldt_for_testing(rng);
-    let dft = Radix2Dit::default();
+fn generic_config() -> MyGenericConfig {
+    TwoAdicFriGenericConfig(PhantomData)
+}
 
+fn get_ldes<R: Rng>(rng: &mut R, deg_bits: Range<usize>) -> Vec<RowMajorMatrix<Val>> {
+    let dft = Radix2Dit::default();
     let shift = Val::generator();
-
-    let ldes: Vec<RowMajorMatrix<Val>> = (3..10)
+    deg_bits
         .map(|deg_bits| {
             let evals = RowMajorMatrix::<Val>::rand_nonzero(rng, 1 << deg_bits, 16);
             let mut lde = dft.coset_lde_batch(evals, 1, shift);
             reverse_matrix_index_bits(&mut lde);
             lde
         })
+        .collect()
+}
+
+/// Combine the LDEs of each height with powers of `alpha`, giving the FRI inputs in descending
+/// order of height.
+fn reduce_ldes(ldes: &[RowMajorMatrix<Val>], alpha: Challenge) -> Vec<Vec<Challenge>> {
+    let input: [_; 32] = core::array::from_fn(|log_height| {
+        let matrices_with_log_height: Vec<&RowMajorMatrix<Val>> = ldes
+            .iter()
+            .filter(|m| log2_strict_usize(m.height()) == log_height)
+            .collect();
+        if matrices_with_log_height.is_empty() {
+            None
+        } else {
+            let reduced: Vec<Challenge> = (0..(1 << log_height))
+                .map(|r| {
+                    alpha
+                        .powers()
+                        .zip(matrices_with_log_height.iter().flat_map(|m| m.row(r)))
+                        .map(|(alpha_pow, v)| alpha_pow * v)
+                        .sum()
+                })
+                .collect();
+            Some(reduced)
+        }
+    });
+
+    input.into_iter().rev().flatten().collect()
+}
+
+/// As our "input opening proof", just pass through the literal reduced openings.
+fn open_input(input: &[Vec<Challenge>], idx: usize) -> Vec<(usize, Challenge)> {
+    let log_max_height = log2_strict_usize(input[0].len());
+    let mut ro = vec![];
+    for v in input {
+        let log_height = log2_strict_usize(v.len());
+        ro.push((log_height, v[idx >> (log_max_height - log_height)]));
+    }
+    ro.sort_by_key(|(lh, _)| Reverse(*lh));
+    ro
+}
+
+/// Prove the low-degreeness of the given LDEs, returning the proof and a sample of the prover's
+/// transcript after FRI.
+fn prove_ldes(perm: &Perm, fc: &MyFriConfig, ldes: &[RowMajorMatrix<Val>]) -> (MyProof, usize) {
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(ldes, alpha);
+
+    let proof = prover::prove(&generic_config(), fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+
+    (proof, chal.sample_bits(8))
+}
+
+/// Verify a proof produced by `prove_ldes`, returning a sample of the verifier's transcript after
+/// FRI.
+fn verify_ldes(perm: &Perm, fc: &MyFriConfig, proof: &MyProof) -> Result<usize, MyFriError> {
+    let mut v_challenger = Challenger::new(perm.clone());
+    let _alpha: Challenge = v_challenger.sample_ext_element();
+    verifier::verify(
+        &generic_config(),
+        fc,
+        proof,
+        &mut v_challenger,
+        |_index, proof| Ok(proof.clone()),
+    )?;
+    Ok(v_challenger.sample_bits(8))
+}
+
+fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
+    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len);
+    let ldes = get_ldes(rng, 3..10);
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+
+    assert_eq!(
+        p_sample, v_sample,
+        "prover and verifier transcript have same state after FRI"
+    );
+}
+
+#[test]
+fn test_packed_query_proofs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+
+    let packed: Vec<_> = proof
+        .query_proofs
+        .iter()
+        .cloned()
+        .map(|qp| qp.pack::<Val>())
         .collect();
+    for (qp, packed_qp) in proof.query_proofs.iter().zip(&packed) {
+        // Each sibling value is 4 BabyBear coordinates of 31 bits each.
+        let num_steps = qp.commit_phase_openings.len();
+        assert_eq!(
+            packed_qp.packed_sibling_values.len(),
+            (num_steps * 4 * 31).div_ceil(8)
+        );
+    }
 
-    let (proof, p_sample) = {
-        // Prover world
-        let mut chal = Challenger::new(perm.clone());
-        let alpha: Challenge = chal.sample_ext_element();
+    let unpacked_proof = MyProof {
+        query_proofs: packed
+            .into_iter()
+            .map(|qp| qp.unpack::<Val>().unwrap())
+            .collect(),
+        ..proof.clone()
+    };
+    for (qp, unpacked_qp) in proof.query_proofs.iter().zip(&unpacked_proof.query_proofs) {
+        for (step, unpacked_step) in qp
+            .commit_phase_openings
+            .iter()
+            .zip(&unpacked_qp.commit_phase_openings)
+        {
+            assert_eq!(step.sibling_values, unpacked_step.sibling_values);
+        }
+    }
+    verify_ldes(&perm, &fc, &unpacked_proof).unwrap();
+}
 
-        let input: [_; 32] = core::array::from_fn(|log_height| {
-            let matrices_with_log_height: Vec<&RowMajorMatrix<Val>> = ldes
-                .iter()
-                .filter(|m| log2_strict_usize(m.height()) == log_height)
-                .collect();
-            if matrices_with_log_height.is_empty() {
-                None
-            } else {
-                let reduced: Vec<Challenge> = (0..(1 << log_height))
-                    .map(|r| {
-                        alpha
-                            .powers()
-                            .zip(matrices_with_log_height.iter().flat_map(|m| m.row(r)))
-                            .map(|(alpha_pow, v)| alpha_pow * v)
-                            .sum()
-                    })
-                    .collect();
-                Some(reduced)
-            }
+#[test]
+fn test_packed_query_proofs_reject_malformed() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+    let mut packed = proof.query_proofs[0].clone().pack::<Val>();
+
+    // Each step packs one sibling value of 4 * 31 = 124 bits, so an odd number of steps leaves 4
+    // bits of padding. Drop the last step if needed to get some.
+    if packed.num_sibling_values.len() % 2 == 0 {
+        packed.num_sibling_values.pop();
+        packed.num_extra_sibling_values.pop();
+        packed.opening_proofs.pop();
+        let num_bits = packed.num_sibling_values.len() * 4 * 31;
+        packed.packed_sibling_values.truncate(num_bits.div_ceil(8));
+        *packed.packed_sibling_values.last_mut().unwrap() &= 0x0f;
+    }
+    assert!(packed.clone().unpack::<Val>().is_some());
+    let mut padded = packed.clone();
+    *padded.packed_sibling_values.last_mut().unwrap() |= 0x80;
+    assert!(padded.unpack::<Val>().is_none());
+
+    // Counts whose sum overflows are rejected rather than wrapping round.
+    let mut overflowing = packed.clone();
+    overflowing.num_sibling_values[0] = usize::MAX;
+    assert!(overflowing.unpack::<Val>().is_none());
+
+    // As are counts whose number of bits overflows.
+    let mut overflowing = packed;
+    overflowing.num_sibling_values[0] = usize::MAX / 4;
+    overflowing.num_extra_sibling_values.fill(0);
+    assert!(overflowing.unpack::<Val>().is_none());
+}
+
+#[cfg(feature = "proof-size")]
+#[test]
+fn test_proof_sizes() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+    let total = postcard::to_allocvec(&proof).unwrap().len();
+    assert!(proof.query_phase_size() + proof.final_poly_size() < total);
+
+    // A larger final polynomial means fewer rounds, so each query opens fewer siblings.
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 3);
+    let (larger_final_poly_proof, _) = prove_ldes(&perm, &fc, &ldes);
+    assert!(larger_final_poly_proof.final_poly_size() > proof.final_poly_size());
+    assert!(larger_final_poly_proof.query_phase_size() < proof.query_phase_size());
+}
+
+#[test]
+fn test_fold_query() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+
+    // Replay the verifier's transcript to recover the betas and query indices.
+    let mut chal = Challenger::new(perm.clone());
+    let _alpha: Challenge = chal.sample_ext_element();
+    let betas: Vec<Challenge> = proof
+        .commit_phase_commits
+        .iter()
+        .map(|comm| {
+            chal.observe(*comm);
+            chal.sample_ext_element()
+        })
+        .collect();
+    for &coeff in &proof.final_poly {
+        chal.observe_ext_element(coeff);
+    }
+    assert!(chal.check_witness(fc.proof_of_work_bits, proof.pow_witness.unwrap()));
+
+    let log_max_height = betas.len() + fc.log_blowup;
+    for qp in &proof.query_proofs {
+        let index = chal.sample_bits(log_max_height);
+        let sibling_values: Vec<Challenge> = qp
+            .commit_phase_openings
+            .iter()
+            .map(|step| step.sibling_values[0])
+            .collect();
+        let folded_evals = verifier::fold_query(
+            &generic_config(),
+            index,
+            log_max_height,
+            &betas,
+            &sibling_values,
+            &qp.input_proof,
+        );
+        assert_eq!(folded_evals.len(), betas.len() + 1);
+        // The first codeword is the largest input itself.
+        assert_eq!(folded_evals[0], qp.input_proof[0].1);
+        assert_eq!(*folded_evals.last().unwrap(), proof.final_poly[0]);
+    }
+}
+
+#[test]
+fn test_fri_ldt_without_pow() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    fc.proof_of_work_bits = 0;
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    assert!(proof.pow_witness.is_none());
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    // A witness is rejected when no proof of work is expected, and required when it is.
+    let mut with_witness = proof.clone();
+    with_witness.pow_witness = Some(Val::zero());
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &with_witness),
+        Err(FriError::InvalidProofShape)
+    ));
+    fc.proof_of_work_bits = 8;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_fri_ldt_pow_after_commit_phase() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    fc.pow_stage = PowStage::AfterCommitPhase;
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    // The verifier must grind at the same stage as the prover.
+    fc.pow_stage = PowStage::BeforeQueries;
+    assert!(verify_ldes(&perm, &fc, &proof).is_err());
+}
+
+#[test]
+fn test_post_query_pow() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    fc.post_query_pow_bits = 16;
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    assert!(proof.post_query_pow_witness.is_some());
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    // The post-query witness binds the query responses, so changing one invalidates it before
+    // the queries themselves are even checked.
+    let mut bad_proof = proof.clone();
+    bad_proof.query_proofs[0].commit_phase_openings[0].sibling_values[0] += Challenge::one();
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::InvalidPowWitness)
+    ));
+
+    // The witness must be present exactly when a post-query proof of work is expected.
+    fc.post_query_pow_bits = 0;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_commit_final_poly() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 2);
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    for commit_final_poly in [false, true] {
+        fc.commit_final_poly = commit_final_poly;
+        let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+        assert_eq!(proof.final_poly_commitment.is_some(), commit_final_poly);
+        let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+        assert_eq!(p_sample, v_sample);
+    }
+
+    // The committed final polynomial must match the coefficients in the proof.
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+    let mut bad_proof = proof.clone();
+    bad_proof.final_poly[0] += Challenge::one();
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+
+    // The commitment must be present exactly when the verifier expects one.
+    fc.commit_final_poly = false;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_too_many_queries() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // A single LDE of height 16.
+    let ldes = get_ldes(&mut rng, 3..4);
+    let mut chal = Challenger::new(perm);
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+
+    fc.num_queries = 17;
+    let result = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    });
+    assert!(matches!(result, Err(FriProverError::TooManyQueries)));
+}
+
+#[test]
+fn test_equal_height_inputs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // Grinding isn't deterministic, so leave it out to compare proofs.
+    fc.proof_of_work_bits = 0;
+    let ldes = get_ldes(&mut rng, 3..8);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let triple = |v: &Vec<Challenge>| {
+        v.iter()
+            .map(|&x| x * Challenge::from_canonical_u32(3))
+            .collect::<Vec<_>>()
+    };
+
+    let prove = |input: Vec<Vec<Challenge>>| {
+        let mut chal = chal.clone();
+        let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+            open_input(&input, idx)
+        })
+        .unwrap();
+        verify_ldes(&perm, &fc, &proof).unwrap();
+        postcard::to_allocvec(&proof).unwrap()
+    };
+
+    // Two inputs of height 2^6, which are both mixed in after the same round, in either order.
+    let mut input_a = input.clone();
+    input_a.insert(2, triple(&input[2]));
+    let mut input_b = input.clone();
+    input_b.insert(3, triple(&input[2]));
+    assert_eq!(prove(input_a), prove(input_b));
+
+    // Likewise two inputs of the largest height, the second of which is mixed into the first.
+    let mut input_a = input.clone();
+    input_a.insert(0, triple(&input[0]));
+    let mut input_b = input.clone();
+    input_b.insert(1, triple(&input[0]));
+    assert_eq!(prove(input_a), prove(input_b));
+}
+
+#[test]
+fn test_commit_budget_exceeded() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // The largest input has 2^9 evaluations, so the commit phase commits to
+    // 2^9 + 2^8 + ... + 2^2 = 1020 in total.
+    let ldes = get_ldes(&mut rng, 3..9);
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+
+    fc.max_committed_elements = Some(1019);
+    let commits = RefCell::new(0);
+    let result = prover::prove_streaming(
+        &generic_config(),
+        &fc,
+        input.clone(),
+        &mut chal.clone(),
+        |idx| open_input(&input, idx),
+        |_, _| *commits.borrow_mut() += 1,
+    );
+    assert!(matches!(result, Err(FriProverError::CommitBudgetExceeded)));
+    // Every round but the last fits in the budget.
+    assert_eq!(commits.into_inner(), 7);
+
+    fc.max_committed_elements = Some(1020);
+    prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_final_poly_len_exceeds_input() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    // The largest input has 2^4 evaluations, and the final codeword 2^4 or 2^5.
+    for log_final_poly_len in 3..5 {
+        let (perm, fc) = get_ldt_for_testing(&mut rng, log_final_poly_len);
+        let ldes = get_ldes(&mut rng, 3..4);
+        let mut chal = Challenger::new(perm);
+        let alpha: Challenge = chal.sample_ext_element();
+        let input = reduce_ldes(&ldes, alpha);
+        let result = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+            open_input(&input, idx)
         });
+        assert!(matches!(
+            result,
+            Err(FriProverError::FinalPolyLenExceedsInput)
+        ));
+    }
+}
 
-        let input: Vec<Vec<Challenge>> = input.into_iter().rev().flatten().collect();
+#[test]
+fn test_non_power_of_two_input() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let input: Vec<Challenge> = (0..100).map(|_| rng.gen()).collect();
+    let result = prover::prove(
+        &generic_config(),
+        &fc,
+        vec![input],
+        &mut Challenger::new(perm),
+        |_| unreachable!("no queries should be answered"),
+    );
+    assert_eq!(
+        result.err(),
+        Some(FriProverError::NonPowerOfTwoInput { len: 100 })
+    );
+}
 
-        let log_max_height = log2_strict_usize(input[0].len());
+#[test]
+fn test_unmixable_input() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let alpha: Challenge = rng.gen();
+    let input = reduce_ldes(&ldes, alpha);
 
-        let proof = prover::prove(
-            &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+    let prove_with_extra_input = |position: usize, len: usize| {
+        let mut inputs = input.clone();
+        inputs.insert(position, (0..len).map(|_| Challenge::one()).collect());
+        prover::prove(
+            &generic_config(),
             &fc,
-            input.clone(),
-            &mut chal,
-            |idx| {
-                // As our "input opening proof", just pass through the literal reduced openings.
-                let mut ro = vec![];
-                for v in &input {
-                    let log_height = log2_strict_usize(v.len());
-                    ro.push((log_height, v[idx >> (log_max_height - log_height)]));
-                }
-                ro.sort_by_key(|(lh, _)| Reverse(*lh));
-                ro
-            },
-        );
+            inputs,
+            &mut Challenger::new(perm.clone()),
+            |_| unreachable!("no queries should be answered"),
+        )
+    };
+
+    // The final codeword has 2 evaluations, so the codeword is never folded to a single one.
+    assert_eq!(
+        prove_with_extra_input(input.len(), 1).err(),
+        Some(FriProverError::UnmixableInput {
+            index: input.len(),
+            len: 1,
+        })
+    );
+    // Nor to a length which isn't a power of two. The inputs after it are stuck behind it too.
+    assert_eq!(
+        prove_with_extra_input(3, 24).err(),
+        Some(FriProverError::UnmixableInput { index: 3, len: 24 })
+    );
+}
+
+#[test]
+fn test_input_mixed_in_last_round() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let alpha: Challenge = rng.gen();
+    let mut input = reduce_ldes(&ldes, alpha);
+    // The final codeword has 2 evaluations, so the first of these is mixed into the codeword
+    // folded in the last round, and the second, the shortest an input can be, into the final
+    // codeword after the last fold.
+    input.push(vec![Challenge::from_canonical_u32(5); 4]);
+    input.push(vec![Challenge::from_canonical_u32(7); 2]);
 
-        (proof, chal.sample_bits(8))
+    let proof = prover::prove(
+        &generic_config(),
+        &fc,
+        input.clone(),
+        &mut Challenger::new(perm.clone()),
+        |idx| open_input(&input, idx),
+    )
+    .unwrap();
+    let num_rounds = proof.commit_phase_commits.len();
+    assert_eq!(proof.mixed_inputs.len(), num_rounds + 1);
+    assert_eq!(proof.mixed_inputs[num_rounds - 1], vec![input.len() - 2]);
+    assert_eq!(proof.mixed_inputs[num_rounds], vec![input.len() - 1]);
+    let verify = |proof: &MyProof| {
+        verifier::verify(
+            &generic_config(),
+            &fc,
+            proof,
+            &mut Challenger::new(perm.clone()),
+            |_index, proof| Ok(proof.clone()),
+        )
     };
+    verify(&proof).unwrap();
 
-    let mut v_challenger = Challenger::new(perm);
-    let _alpha: Challenge = v_challenger.sample_ext_element();
-    verifier::verify(
-        &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+    // The verifier mixes the final input in too, so it must be listed.
+    let mut bad_proof = proof.clone();
+    bad_proof.mixed_inputs[num_rounds].clear();
+    assert!(matches!(
+        verify(&bad_proof),
+        Err(FriError::InvalidProofShape)
+    ));
+    // And its value matters: a final polynomial which ignores it doesn't match.
+    let mut bad_input = input.clone();
+    bad_input.last_mut().unwrap().fill(Challenge::zero());
+    let proof_opening_bad_input = prover::prove(
+        &generic_config(),
         &fc,
-        &proof,
-        &mut v_challenger,
-        |_index, proof| Ok(proof.clone()),
+        input.clone(),
+        &mut Challenger::new(perm.clone()),
+        |idx| open_input(&bad_input, idx),
     )
     .unwrap();
+    assert!(matches!(
+        verify(&proof_opening_bad_input),
+        Err(FriError::FinalPolyMismatch)
+    ));
+}
+
+#[test]
+fn test_first_layer_mmcs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // A second MMCS of the same type, but hashing with a different permutation.
+    let (_, other) = get_ldt_for_testing(&mut rng, 0);
+    fc.first_layer_mmcs = Some(other.mmcs);
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    // The first layer can't be verified with the MMCS used for the later layers.
+    fc.first_layer_mmcs = None;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+}
+
+#[test]
+fn test_queries_answered_in_index_order() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let opened = RefCell::new(vec![]);
+    let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        opened.borrow_mut().push(idx);
+        open_input(&input, idx)
+    })
+    .unwrap();
+
+    let opened = opened.into_inner();
+    assert_eq!(opened.len(), fc.num_queries);
+    assert!(opened.windows(2).all(|w| w[0] <= w[1]));
+    // The query proofs are still in the order the verifier samples the indices.
+    verify_ldes(&perm, &fc, &proof).unwrap();
+}
+
+#[test]
+fn test_mixed_inputs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    // Inputs of heights 2^9, 2^7 and 2^6.
+    let ldes = [8..9, 6..7, 5..6]
+        .into_iter()
+        .flat_map(|deg_bits| get_ldes(&mut rng, deg_bits))
+        .collect::<Vec<_>>();
+
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+    // The codeword folded in the third round has height 2^7, and in the fourth 2^6. There are 8
+    // rounds, and an entry for the final codeword.
+    let mut expected = vec![vec![]; 9];
+    expected[2] = vec![1];
+    expected[3] = vec![2];
+    assert_eq!(proof.mixed_inputs, expected);
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    let mut bad_proof = proof.clone();
+    bad_proof.mixed_inputs.swap(2, 3);
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::InvalidProofShape)
+    ));
+
+    // Every round's entry is checked, including the last and the final codeword's, and must list
+    // exactly what was mixed.
+    for round in [0, 3, 7, 8] {
+        let mut bad_proof = proof.clone();
+        bad_proof.mixed_inputs[round].push(3);
+        assert!(matches!(
+            verify_ldes(&perm, &fc, &bad_proof),
+            Err(FriError::InvalidProofShape)
+        ));
+    }
+    let mut bad_proof = proof.clone();
+    bad_proof.mixed_inputs[3].clear();
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_commit_mixed_inputs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    fc.commit_mixed_inputs = true;
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let mut input = reduce_ldes(&ldes, alpha);
+    // A second input of height 2^7, so that the round folding to that height commits two inputs
+    // alongside its codeword.
+    let second = input[3].iter().map(|&x| x.double()).collect();
+    input.insert(4, second);
+    let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+    let p_sample = chal.sample_bits(8);
+
+    // The inputs have heights 2^10 down to 2^4, so the rounds folding to 2^9 down to 2^4 each
+    // commit the inputs of that height.
+    for qp in &proof.query_proofs {
+        let num_extra_siblings = qp
+            .commit_phase_openings
+            .iter()
+            .map(|step| step.extra_sibling_values.len())
+            .collect::<Vec<_>>();
+        assert_eq!(num_extra_siblings, [1, 1, 2, 1, 1, 1, 0, 0, 0]);
+    }
+    let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+    assert_eq!(p_sample, v_sample);
+
+    // The committed inputs' openings are checked.
+    let mut bad_proof = proof.clone();
+    bad_proof.query_proofs[0].commit_phase_openings[2].extra_sibling_values[1] += Challenge::one();
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+
+    // A verifier which doesn't expect the inputs to be committed rejects the proof.
+    fc.commit_mixed_inputs = false;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+/// Prove the low-degreeness of a single LDE of height `2^log_height`, with only a couple of queries
+/// so that tiny inputs can be proven.
+fn prove_small_lde(
+    log_blowup: usize,
+    log_final_poly_len: usize,
+    log_height: usize,
+) -> Result<(MyProof, Perm, MyFriConfig), FriProverError> {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, log_final_poly_len);
+    // The LDEs always have a blowup of 2, but a smaller polynomial is still of low degree.
+    fc.log_blowup = log_blowup;
+    fc.num_queries = 2;
+    let ldes = get_ldes(&mut rng, log_height - 1..log_height);
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })?;
+    Ok((proof, perm, fc))
+}
+
+#[test]
+fn test_single_round_boundary() {
+    // Folding stops at `blowup() * final_poly_len()` evaluations, so an input of twice that is
+    // folded exactly once, and the final polynomial always has `final_poly_len()` coefficients.
+    // These inputs have `2 * blowup()`, `2 * final_poly_len()` and `2 * blowup() *
+    // final_poly_len()` evaluations respectively.
+    for (log_blowup, log_final_poly_len, log_height) in [(1, 0, 2), (0, 2, 3), (1, 2, 4)] {
+        let (proof, perm, fc) =
+            prove_small_lde(log_blowup, log_final_poly_len, log_height).unwrap();
+        assert_eq!(proof.commit_phase_commits.len(), 1);
+        assert_eq!(proof.final_poly.len(), fc.final_poly_len());
+        verify_ldes(&perm, &fc, &proof).unwrap();
+    }
+
+    // With a larger final polynomial, inputs of `2 * blowup()` and `2 * final_poly_len()`
+    // evaluations wouldn't be folded at all.
+    for log_height in [2, 3] {
+        assert!(matches!(
+            prove_small_lde(1, 2, log_height),
+            Err(FriProverError::FinalPolyLenExceedsInput)
+        ));
+    }
+}
+
+#[test]
+fn test_num_commit_rounds() {
+    for (log_blowup, log_final_poly_len, log_height) in
+        [(1, 0, 2), (1, 0, 7), (1, 2, 6), (0, 2, 5), (0, 1, 8)]
+    {
+        let (proof, _, _) = prove_small_lde(log_blowup, log_final_poly_len, log_height).unwrap();
+        assert_eq!(
+            num_commit_rounds(log_height, log_blowup, log_final_poly_len),
+            proof.commit_phase_commits.len()
+        );
+    }
 
+    // Mixing in smaller inputs doesn't add any rounds.
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 1);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
     assert_eq!(
-        p_sample,
-        v_challenger.sample_bits(8),
-        "prover and verifier transcript have same state after FRI"
+        num_commit_rounds(proof.log_max_height(), fc.log_blowup, fc.log_final_poly_len),
+        proof.commit_phase_commits.len()
     );
 }
 
 #[test]
-fn test_fri_ldt() {
-    // FRI is kind of flaky depending on indexing luck
-    for i in 0..4 {
-        let mut rng = ChaCha20Rng::seed_from_u64(i);
-        do_test_fri_ldt(&mut rng);
+fn test_fri_params() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, fc) = get_ldt_for_testing(&mut rng, 2);
+    let g = generic_config();
+    let params = FriConfig::params::<Challenge, _>(&fc, &g);
+    assert_eq!(
+        params,
+        FriParams {
+            log_blowup: fc.log_blowup,
+            log_final_poly_len: fc.log_final_poly_len,
+            num_queries: fc.num_queries,
+            pow_bits: fc.proof_of_work_bits,
+            extra_index_bits: g.extra_query_index_bits(),
+            log_arity: g.log_arity(),
+        }
+    );
+    assert_eq!(params.blowup(), fc.blowup());
+    assert_eq!(params.final_poly_len(), fc.final_poly_len());
+    assert_eq!(params.query_index_bits(10), 10 + g.extra_query_index_bits());
+}
+
+#[test]
+fn test_fri_config_new() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, fc) = get_ldt_for_testing(&mut rng, 2);
+    let fc = FriConfig::new(3, 2, 20, 16, fc.mmcs);
+    assert_eq!(fc.blowup(), 8);
+    assert_eq!(fc.final_poly_len(), 4);
+    assert_eq!(fc.num_queries, 20);
+    assert_eq!(fc.proof_of_work_bits, 16);
+    assert_eq!(fc.pow_stage, PowStage::BeforeQueries);
+    assert_eq!(fc.fixed_rounds, None);
+    assert!(!fc.commit_final_poly);
+    assert!(fc.pad_final_poly);
+    assert!(fc.first_layer_mmcs.is_none());
+}
+
+#[test]
+#[should_panic]
+fn test_fri_config_new_rejects_oversized_final_codeword() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, fc) = get_ldt_for_testing(&mut rng, 0);
+    FriConfig::new(usize::BITS as usize - 1, 1, 10, 8, fc.mmcs);
+}
+
+#[test]
+fn test_fold_to_constant() {
+    // With `log_blowup == 0` the codeword is folded all the way down to a single evaluation,
+    // otherwise down to `blowup()` evaluations of a constant.
+    for log_blowup in [0, 1] {
+        let log_height = 6;
+        let (proof, perm, fc) = prove_small_lde(log_blowup, 0, log_height).unwrap();
+        assert_eq!(proof.final_poly.len(), 1);
+        assert_eq!(proof.commit_phase_commits.len(), log_height - fc.log_blowup);
+        verify_ldes(&perm, &fc, &proof).unwrap();
+    }
+}
+
+#[test]
+fn test_corrupted_commit_phase_path() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+
+    // Every query's openings are verified together, but a single bad path must still be caught.
+    for (query, round) in [(0, 0), (4, 3), (9, 7)] {
+        let mut corrupted = proof.clone();
+        corrupted.query_proofs[query].commit_phase_openings[round]
+            .opening_proof
+            .as_mut()
+            .unwrap()[0][0] += Val::one();
+        assert!(matches!(
+            verify_ldes(&perm, &fc, &corrupted),
+            Err(FriError::CommitPhaseMmcsError(_))
+        ));
+    }
+}
+
+#[test]
+fn test_compact_proof() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // Many queries into a codeword of 2^6 evaluations, so that they often share openings.
+    fc.num_queries = 40;
+    let ldes = get_ldes(&mut rng, 3..6);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+
+    let compact = proof.clone().compact();
+    let compact_bytes = postcard::to_allocvec(&compact).unwrap();
+    assert!(compact_bytes.len() < postcard::to_allocvec(&proof).unwrap().len());
+    // The last round commits to 4 evaluations in 2 rows, so it has at most 2 opening proofs.
+    assert!(compact.opening_proofs.last().unwrap().len() <= 2);
+
+    let compact: CompactFriProof<_, _, _, _> = postcard::from_bytes(&compact_bytes).unwrap();
+    let expanded: MyProof = compact.expand().unwrap();
+    assert_eq!(
+        postcard::to_allocvec(&expanded).unwrap(),
+        postcard::to_allocvec(&proof).unwrap()
+    );
+    verify_ldes(&perm, &fc, &expanded).unwrap();
+
+    // Out of range indices are rejected.
+    let mut bad_compact = proof.compact();
+    bad_compact.query_proofs[0].commit_phase_openings[0] = usize::MAX;
+    assert!(bad_compact.expand().is_none());
+}
+
+#[test]
+fn test_verify_queries() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+
+    let verify_subset = |proof: &MyProof, indices_to_check: &[usize]| {
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        verifier::verify_queries(
+            &generic_config(),
+            &fc,
+            proof,
+            indices_to_check,
+            &mut v_challenger,
+            |_index, proof| Ok(proof.clone()),
+        )
+        .map(|()| v_challenger.sample_bits(8))
+    };
+    // The transcript ends up in the same state as after a full verification.
+    assert_eq!(verify_subset(&proof, &[0, 3]).unwrap(), p_sample);
+
+    // A corrupted query is caught if it's checked, and only then.
+    let mut bad_proof = proof.clone();
+    bad_proof.query_proofs[3].commit_phase_openings[0].sibling_values[0] += Challenge::one();
+    assert!(verify_subset(&bad_proof, &[0, 3]).is_err());
+    assert!(verify_subset(&bad_proof, &[0, 5]).is_ok());
+    assert!(verify_ldes(&perm, &fc, &bad_proof).is_err());
+}
+
+#[test]
+fn test_input_commitments_order() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    // Stand-ins for the commitments to the inputs; any two distinct commitments will do.
+    let commits = input
+        .iter()
+        .take(2)
+        .map(|v| fc.mmcs.commit_matrix(RowMajorMatrix::new(v.clone(), 1)).0)
+        .collect::<Vec<_>>();
+
+    let proof = prover::prove_with_input_commitments(
+        &generic_config(),
+        &fc,
+        &commits,
+        input.clone(),
+        &mut chal,
+        |idx| open_input(&input, idx),
+    )
+    .unwrap();
+
+    let verify_observing = |commits: &[_]| {
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        verifier::verify_with_input_commitments(
+            &generic_config(),
+            &fc,
+            commits,
+            &proof,
+            &mut v_challenger,
+            |_index, proof| Ok(proof.clone()),
+        )
+    };
+    verify_observing(&commits).unwrap();
+    let reversed = commits.iter().rev().cloned().collect::<Vec<_>>();
+    assert!(verify_observing(&reversed).is_err());
+}
+
+#[test]
+fn test_verify_with_log_max_height() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    // The largest LDE has 2^10 evaluations.
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+    assert_eq!(proof.log_max_height(), 10);
+
+    let verify_expecting = |log_max_height| {
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        verifier::verify_with_log_max_height(
+            &generic_config(),
+            &fc,
+            &proof,
+            log_max_height,
+            &mut v_challenger,
+            |_index, proof| Ok(proof.clone()),
+        )
+    };
+    verify_expecting(10).unwrap();
+    assert!(matches!(
+        verify_expecting(11),
+        Err(FriError::LogMaxHeightMismatch {
+            expected: 11,
+            actual: 10
+        })
+    ));
+}
+
+#[test]
+fn test_fixed_rounds() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 2);
+    fc.fixed_rounds = Some(8);
+
+    // Inputs of heights 2^9, 2^10 and 2^11 leave final codewords of 2, 4 and 8 evaluations.
+    for max_deg_bits in 8..11 {
+        let ldes = get_ldes(&mut rng, 3..max_deg_bits + 1);
+        let (proof, p_sample) = prove_ldes(&perm, &fc, &ldes);
+        assert_eq!(proof.commit_phase_commits.len(), 8);
+        assert_eq!(proof.final_poly.len(), fc.final_poly_len());
+        assert_eq!(proof.log_max_height(), max_deg_bits + 1);
+        let v_sample = verify_ldes(&perm, &fc, &proof).unwrap();
+        assert_eq!(p_sample, v_sample);
     }
+
+    // Folding an input of height 2^12 eight times leaves too many evaluations.
+    let ldes = get_ldes(&mut rng, 3..12);
+    let mut chal = Challenger::new(perm);
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let result = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    });
+    assert!(matches!(
+        result,
+        Err(FriProverError::IncompatibleFixedRounds)
+    ));
+}
+
+#[test]
+fn test_pad_final_poly() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 2);
+    fc.fixed_rounds = Some(8);
+    // An input of height 2^9 leaves a final codeword of 2 evaluations, i.e. a constant polynomial,
+    // while `final_poly_len` is 4.
+    let ldes = get_ldes(&mut rng, 3..9);
+
+    let (padded, _) = prove_ldes(&perm, &fc, &ldes);
+    assert_eq!(padded.final_poly.len(), fc.final_poly_len());
+    assert!(padded.final_poly[1..].iter().all(|c| c.is_zero()));
+    verify_ldes(&perm, &fc, &padded).unwrap();
+
+    fc.pad_final_poly = false;
+    let (unpadded, _) = prove_ldes(&perm, &fc, &ldes);
+    assert_eq!(unpadded.final_poly, padded.final_poly[..1]);
+    verify_ldes(&perm, &fc, &unpadded).unwrap();
+    // The verifier expects the length the config calls for.
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &padded),
+        Err(FriError::InvalidProofShape)
+    ));
+
+    // The padding doesn't loosen the degree bound.
+    fc.pad_final_poly = true;
+    let mut bad_proof = padded;
+    bad_proof.final_poly[3] = Challenge::one();
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &bad_proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_flatten_round_trip() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 1);
+    // Exercise the optional parts of the proof too.
+    fc.post_query_pow_bits = 4;
+    fc.commit_final_poly = true;
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (proof, _) = prove_ldes(&perm, &fc, &ldes);
+
+    let flat = proof.flatten();
+    assert_eq!(flat.len(), proof.flattened_len());
+
+    let unflattened = MyProof::unflatten(&flat).unwrap();
+    assert_eq!(
+        postcard::to_allocvec(&unflattened).unwrap(),
+        postcard::to_allocvec(&proof).unwrap()
+    );
+    verify_ldes(&perm, &fc, &unflattened).unwrap();
+
+    // Truncated or overlong inputs are rejected.
+    assert!(MyProof::unflatten(&flat[..flat.len() - 1]).is_none());
+    let mut overlong = flat;
+    overlong.push(Val::zero());
+    assert!(MyProof::unflatten(&overlong).is_none());
+}
+
+#[test]
+fn test_first_beta() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 1);
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    // As in an earlier protocol step, the first beta is sampled from the transcript, after
+    // everything the inputs depend on has been observed.
+    let mut p_challenger = Challenger::new(perm.clone());
+    let alpha: Challenge = p_challenger.sample_ext_element();
+    let first_beta: Challenge = p_challenger.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove_with_first_beta(
+        &generic_config(),
+        &fc,
+        first_beta,
+        input.clone(),
+        &mut p_challenger,
+        |idx| open_input(&input, idx),
+    )
+    .unwrap();
+    let p_sample: usize = p_challenger.sample_bits(8);
+
+    let verify = |first_beta: Option<Challenge>| {
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        let sampled_beta: Challenge = v_challenger.sample_ext_element();
+        let open_input = |_index, proof: &Vec<(usize, Challenge)>| Ok(proof.clone());
+        match first_beta {
+            Some(beta) => {
+                assert_eq!(beta, sampled_beta);
+                verifier::verify_with_first_beta(
+                    &generic_config(),
+                    &fc,
+                    beta,
+                    &proof,
+                    &mut v_challenger,
+                    open_input,
+                )
+            }
+            None => verifier::verify(
+                &generic_config(),
+                &fc,
+                &proof,
+                &mut v_challenger,
+                open_input,
+            ),
+        }
+        .map(|()| v_challenger.sample_bits(8))
+    };
+    assert_eq!(verify(Some(first_beta)).unwrap(), p_sample);
+    // Sampling the first beta instead folds the first round differently.
+    assert!(verify(None).is_err());
+}
+
+#[test]
+fn test_chained_proofs_with_owned_challenger() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let leaves = [get_ldes(&mut rng, 3..8), get_ldes(&mut rng, 4..9)];
+
+    // Each leaf proof continues from the state the previous one left the challenger in.
+    let mut chal = Challenger::new(perm.clone());
+    let mut proofs = vec![];
+    for ldes in &leaves {
+        let alpha: Challenge = chal.sample_ext_element();
+        let input = reduce_ldes(ldes, alpha);
+        let (proof, next_chal) =
+            prover::prove_with_challenger(&generic_config(), &fc, input.clone(), chal, |idx| {
+                open_input(&input, idx)
+            })
+            .unwrap();
+        proofs.push(proof);
+        chal = next_chal;
+    }
+
+    let mut v_chal = Challenger::new(perm);
+    for proof in &proofs {
+        let _alpha: Challenge = v_chal.sample_ext_element();
+        verifier::verify(
+            &generic_config(),
+            &fc,
+            proof,
+            &mut v_chal,
+            |_index, proof| Ok(proof.clone()),
+        )
+        .unwrap();
+    }
+    let p_sample: usize = chal.sample_bits(8);
+    let v_sample: usize = v_chal.sample_bits(8);
+    assert_eq!(p_sample, v_sample);
+}
+
+#[test]
+fn test_prove_with_metrics() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..8);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let (proof, metrics) =
+        prover::prove_with_metrics(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+            open_input(&input, idx)
+        })
+        .unwrap();
+
+    assert!(metrics.grind_attempts.unwrap() >= 1);
+    assert!(metrics.achieved_pow_bits.unwrap() >= fc.proof_of_work_bits);
+    verify_ldes(&perm, &fc, &proof).unwrap();
+}
+
+#[test]
+fn test_achieved_pow_bits() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, _) = get_ldt_for_testing(&mut rng, 0);
+    let start = Challenger::new(perm);
+
+    let (witness, _, achieved_bits) =
+        ChallengerGrinding::<NoClock>::default().grind_with_achieved_bits(&mut start.clone(), 4);
+    let achieved_bits = achieved_bits.unwrap();
+    assert!(achieved_bits >= 4);
+    // The witness meets exactly the achieved difficulty, and no more.
+    assert!(start.clone().check_witness(achieved_bits, witness));
+    assert!(!start.clone().check_witness(achieved_bits + 1, witness));
+}
+
+#[test]
+fn test_prove_streaming() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // Without grinding, both proofs are fully determined by the transcript.
+    fc.proof_of_work_bits = 0;
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (expected, _) = prove_ldes(&perm, &fc, &ldes);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let mut streamed = vec![];
+    let proof = prover::prove_streaming(
+        &generic_config(),
+        &fc,
+        input.clone(),
+        &mut chal,
+        |idx| open_input(&input, idx),
+        |round, commit| streamed.push((round, *commit)),
+    )
+    .unwrap();
+
+    let rounds = streamed.iter().map(|&(round, _)| round).collect::<Vec<_>>();
+    assert_eq!(
+        rounds,
+        (0..proof.commit_phase_commits.len()).collect::<Vec<_>>()
+    );
+    for ((_, commit), proof_commit) in streamed.iter().zip(&proof.commit_phase_commits) {
+        assert_eq!(commit, proof_commit);
+    }
+    assert_eq!(
+        postcard::to_allocvec(&proof).unwrap(),
+        postcard::to_allocvec(&expected).unwrap()
+    );
+    verify_ldes(&perm, &fc, &proof).unwrap();
+}
+
+#[test]
+fn test_prove_lazy() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    // Without grinding, both proofs are fully determined by the transcript.
+    fc.proof_of_work_bits = 0;
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (expected, _) = prove_ldes(&perm, &fc, &ldes);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove_lazy(
+        &generic_config(),
+        &fc,
+        log2_strict_usize(input[0].len()),
+        || input[0].clone(),
+        input[1..].to_vec(),
+        &mut chal,
+        |idx| open_input(&input, idx),
+    )
+    .unwrap();
+
+    assert_eq!(
+        postcard::to_allocvec(&proof).unwrap(),
+        postcard::to_allocvec(&expected).unwrap()
+    );
+    verify_ldes(&perm, &fc, &proof).unwrap();
+}
+
+/// Grinds with a fresh challenger, seeded from the transcript, rather than with the transcript's own
+/// challenger.
+struct SeparateGrinding(Perm);
+
+impl SeparateGrinding {
+    fn pow_challenger<C: CanSample<Val>>(&self, challenger: &mut C) -> Challenger {
+        let mut pow_challenger = Challenger::new(self.0.clone());
+        pow_challenger.observe_slice(&challenger.sample_vec(8));
+        pow_challenger
+    }
+}
+
+impl<C: FieldChallenger<Val>> GrindingStrategy<C> for SeparateGrinding {
+    type Witness = Val;
+
+    fn grind(&self, challenger: &mut C, bits: usize) -> Val {
+        let witness = self.pow_challenger(challenger).grind(bits);
+        challenger.observe(witness);
+        witness
+    }
+
+    fn check_witness(&self, challenger: &mut C, bits: usize, witness: Val) -> bool {
+        let valid = self.pow_challenger(challenger).check_witness(bits, witness);
+        challenger.observe(witness);
+        valid
+    }
+}
+
+#[test]
+fn test_separate_grinding() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let fc = fc.with_grinding(SeparateGrinding(perm.clone()));
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+    let p_sample = chal.sample_bits(8);
+
+    let verify = |proof: &MyProof| {
+        let mut v_chal = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_chal.sample_ext_element();
+        verifier::verify(
+            &generic_config(),
+            &fc,
+            proof,
+            &mut v_chal,
+            |_index, proof| Ok::<_, ()>(proof.clone()),
+        )
+        .map(|()| v_chal.sample_bits(8))
+    };
+    assert_eq!(verify(&proof).unwrap(), p_sample);
+
+    // The witness is checked with the separate challenger.
+    let mut bad_witness = proof.clone();
+    bad_witness.pow_witness = Some(proof.pow_witness.unwrap() + Val::one());
+    assert!(matches!(
+        verify(&bad_witness),
+        Err(FriError::InvalidPowWitness)
+    ));
+}
+
+/// Same as `MyGenericConfig`, except that it samples two challenges per round, of which only the
+/// first is used for folding.
+struct TwoChallengeConfig(MyGenericConfig);
+
+impl FriGenericConfig<Challenge> for TwoChallengeConfig {
+    type InputProof = Vec<(usize, Challenge)>;
+    type InputError = ();
+
+    fn extra_query_index_bits(&self) -> usize {
+        self.0.extra_query_index_bits()
+    }
+
+    fn fold_row(
+        &self,
+        index: usize,
+        log_height: usize,
+        beta: Challenge,
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        self.0.fold_row(index, log_height, beta, evals)
+    }
+
+    fn fold_matrix<M: Matrix<Challenge>>(&self, beta: Challenge, m: M) -> Vec<Challenge> {
+        self.0.fold_matrix(beta, m)
+    }
+
+    fn challenges_per_round(&self) -> usize {
+        2
+    }
+
+    fn fold_row_with_betas(
+        &self,
+        index: usize,
+        log_height: usize,
+        betas: &[Challenge],
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        assert_eq!(betas.len(), 2);
+        self.0.fold_row(index, log_height, betas[0], evals)
+    }
+
+    fn fold_matrix_with_betas<M: Matrix<Challenge>>(
+        &self,
+        betas: &[Challenge],
+        m: M,
+    ) -> Vec<Challenge> {
+        assert_eq!(betas.len(), 2);
+        self.0.fold_matrix(betas[0], m)
+    }
+
+    fn max_final_poly_len(&self) -> usize {
+        self.0.max_final_poly_len()
+    }
+
+    fn interpolate_final_poly(&self, evals: Vec<Challenge>) -> Vec<Challenge> {
+        self.0.interpolate_final_poly(evals)
+    }
+
+    fn eval_final_poly(
+        &self,
+        index: usize,
+        log_height: usize,
+        final_poly: &[Challenge],
+    ) -> Challenge {
+        self.0.eval_final_poly(index, log_height, final_poly)
+    }
+}
+
+#[test]
+fn test_two_challenges_per_round() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let g = TwoChallengeConfig(generic_config());
+
+    let mut chal = TranscriptRecorder::new(Challenger::new(perm.clone()));
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&g, &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+
+    // `alpha`, then two challenges per round, each made of 4 base field samples.
+    let num_rounds = proof.commit_phase_commits.len();
+    let num_samples = chal
+        .events()
+        .iter()
+        .filter(|e| matches!(e, TranscriptEvent::Sample(_)))
+        .count();
+    assert_eq!(num_samples, 4 + num_rounds * 2 * 4);
+
+    let mut v_chal = TranscriptRecorder::new(Challenger::new(perm));
+    let _alpha: Challenge = v_chal.sample_ext_element();
+    verifier::verify(&g, &fc, &proof, &mut v_chal, |_index, proof| {
+        Ok(proof.clone())
+    })
+    .unwrap();
+    assert_eq!(chal.events(), v_chal.events());
+}
+
+/// Same as `MyGenericConfig`, except that each round folds rows of 4 evaluations, as two arity 2
+/// folds with a challenge each.
+struct Arity4Config(MyGenericConfig);
+
+impl FriGenericConfig<Challenge> for Arity4Config {
+    type InputProof = Vec<(usize, Challenge)>;
+    type InputError = ();
+
+    fn extra_query_index_bits(&self) -> usize {
+        self.0.extra_query_index_bits()
+    }
+
+    fn fold_row(
+        &self,
+        index: usize,
+        log_height: usize,
+        beta: Challenge,
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        self.0.fold_row(index, log_height, beta, evals)
+    }
+
+    fn fold_matrix<M: Matrix<Challenge>>(&self, beta: Challenge, m: M) -> Vec<Challenge> {
+        self.0.fold_matrix(beta, m)
+    }
+
+    fn log_arity(&self) -> usize {
+        2
+    }
+
+    fn challenges_per_round(&self) -> usize {
+        2
+    }
+
+    fn fold_row_with_betas(
+        &self,
+        index: usize,
+        log_height: usize,
+        betas: &[Challenge],
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        // The row holds two rows of the codeword folded once, at `2 * index` and `2 * index + 1`.
+        let evals = evals.collect::<Vec<_>>();
+        let halves = evals.chunks_exact(2).enumerate().map(|(i, pair)| {
+            self.0.fold_row(
+                2 * index + i,
+                log_height + 1,
+                betas[0],
+                pair.iter().copied(),
+            )
+        });
+        self.0.fold_row(index, log_height, betas[1], halves)
+    }
+
+    fn fold_matrix_with_betas<M: Matrix<Challenge>>(
+        &self,
+        betas: &[Challenge],
+        m: M,
+    ) -> Vec<Challenge> {
+        let pairs = RowMajorMatrix::new(m.to_row_major_matrix().values, 2);
+        let halves = RowMajorMatrix::new(self.0.fold_matrix(betas[0], pairs), 2);
+        self.0.fold_matrix(betas[1], halves)
+    }
+
+    fn max_final_poly_len(&self) -> usize {
+        self.0.max_final_poly_len()
+    }
+
+    fn interpolate_final_poly(&self, evals: Vec<Challenge>) -> Vec<Challenge> {
+        self.0.interpolate_final_poly(evals)
+    }
+
+    fn eval_final_poly(
+        &self,
+        index: usize,
+        log_height: usize,
+        final_poly: &[Challenge],
+    ) -> Challenge {
+        self.0.eval_final_poly(index, log_height, final_poly)
+    }
+}
+
+#[test]
+fn test_arity_4() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    // The final codeword has 4 evaluations, so inputs of heights 2^10, 2^8, 2^6 and 2^4 can all be
+    // folded to it, and each round commits the input of the next height alongside its codeword.
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 1);
+    fc.commit_mixed_inputs = true;
+    let g = Arity4Config(generic_config());
+    let ldes = [9..10, 7..8, 5..6, 3..4]
+        .into_iter()
+        .flat_map(|deg_bits| get_ldes(&mut rng, deg_bits))
+        .collect::<Vec<_>>();
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&g, &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+    let p_sample = chal.sample_bits(8);
+
+    assert_eq!(proof.commit_phase_commits.len(), 4);
+    for qp in &proof.query_proofs {
+        for (round, step) in qp.commit_phase_openings.iter().enumerate() {
+            assert_eq!(step.sibling_values.len(), 3);
+            let num_committed_inputs = if round < 3 { 1 } else { 0 };
+            assert_eq!(step.extra_sibling_values.len(), 3 * num_committed_inputs);
+        }
+    }
+
+    let verify = |fc: &MyFriConfig, proof: &MyProof| {
+        let mut v_chal = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_chal.sample_ext_element();
+        verifier::verify(
+            &g,
+            fc,
+            proof,
+            &mut v_chal,
+            |_index, proof| Ok(proof.clone()),
+        )
+        .map(|()| v_chal.sample_bits(8))
+    };
+    assert_eq!(verify(&fc, &proof).unwrap(), p_sample);
+
+    // Every sibling of the codeword and of the committed inputs is checked.
+    for k in 0..3 {
+        let mut bad_proof = proof.clone();
+        bad_proof.query_proofs[0].commit_phase_openings[1].sibling_values[k] += Challenge::one();
+        assert!(verify(&fc, &bad_proof).is_err());
+        let mut bad_proof = proof.clone();
+        bad_proof.query_proofs[0].commit_phase_openings[1].extra_sibling_values[k] +=
+            Challenge::one();
+        assert!(matches!(
+            verify(&fc, &bad_proof),
+            Err(FriError::CommitPhaseMmcsError(_))
+        ));
+    }
+
+    // An input of height 2^9 is never reached by folding 2 bits at a time.
+    let mut odd_input = input.clone();
+    odd_input.insert(1, vec![Challenge::one(); 1 << 9]);
+    assert_eq!(
+        prover::prove(
+            &g,
+            &fc,
+            odd_input,
+            &mut Challenger::new(perm.clone()),
+            |_| { unreachable!("no queries should be answered") }
+        )
+        .err(),
+        Some(FriProverError::UnmixableInput {
+            index: 1,
+            len: 1 << 9
+        })
+    );
+    // Nor is a final codeword of 2 evaluations.
+    let (_, fc) = get_ldt_for_testing(&mut rng, 0);
+    assert_eq!(
+        prover::prove(&g, &fc, input, &mut Challenger::new(perm.clone()), |_| {
+            unreachable!("no queries should be answered")
+        })
+        .err(),
+        Some(FriProverError::IncompatibleArity)
+    );
+}
+
+/// A clock which advances by a millisecond every time it's read, so that grinding within a time
+/// budget takes the same steps however fast the machine is.
+#[derive(Debug, Default)]
+struct StepClock(AtomicU64);
+
+impl GrindClock for StepClock {
+    fn now(&self) -> Option<Duration> {
+        Some(Duration::from_millis(
+            self.0.fetch_add(1, Ordering::Relaxed),
+        ))
+    }
+}
+
+#[test]
+fn test_grind_time_budget() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let mut fc = fc.with_grinding(ChallengerGrinding {
+        clock: StepClock::default(),
+    });
+    fc.proof_of_work_bits = 1;
+    fc.grind_time_budget = Some(Duration::from_millis(10));
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let (proof, metrics) =
+        prover::prove_with_metrics(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+            open_input(&input, idx)
+        })
+        .unwrap();
+    // Each difficulty takes one tick of the clock, so the prover grinds through 8 of them above
+    // the floor before the next one would overrun the budget.
+    assert!(proof.pow_bits >= 9);
+    // The metrics report the grinding done within the budget too.
+    assert_eq!(metrics.achieved_pow_bits, Some(proof.pow_bits));
+    assert!(metrics.grind_attempts.unwrap() > 0);
+
+    let verify = |proof: &MyProof| {
+        let mut v_chal = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_chal.sample_ext_element();
+        verifier::verify(
+            &generic_config(),
+            &fc,
+            proof,
+            &mut v_chal,
+            |_index, proof| Ok::<_, ()>(proof.clone()),
+        )
+    };
+    verify(&proof).unwrap();
+
+    // The difficulty claimed by the proof must meet the floor.
+    let mut too_easy = proof.clone();
+    too_easy.pow_bits = 0;
+    assert!(matches!(
+        verify(&too_easy),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn test_grind_time_budget_unsupported() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, mut fc) = get_ldt_for_testing(&mut rng, 0);
+    fc.grind_time_budget = Some(Duration::from_millis(10));
+    let ldes = get_ldes(&mut rng, 3..10);
+    let input = reduce_ldes(&ldes, rng.gen());
+
+    // The challenger's own grinding can't measure time, so the budget is refused rather than
+    // silently ignored.
+    assert_eq!(
+        prover::prove(
+            &generic_config(),
+            &fc,
+            input,
+            &mut Challenger::new(perm),
+            |_| unreachable!("no queries should be answered"),
+        )
+        .err(),
+        Some(FriProverError::GrindTimeBudgetUnsupported)
+    );
+}
+
+#[test]
+fn test_pow_bits_must_match_without_budget() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let (mut proof, _) = prove_ldes(&perm, &fc, &ldes);
+    assert_eq!(proof.pow_bits, fc.proof_of_work_bits);
+
+    proof.pow_bits += 1;
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
+/// Mixes each input into the folded codeword with its own random coefficient.
+struct RandomMixConfig(MyGenericConfig);
+
+impl FriGenericConfig<Challenge> for RandomMixConfig {
+    type InputProof = Vec<(usize, Challenge)>;
+    type InputError = ();
+
+    fn extra_query_index_bits(&self) -> usize {
+        self.0.extra_query_index_bits()
+    }
+
+    fn fold_row(
+        &self,
+        index: usize,
+        log_height: usize,
+        beta: Challenge,
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        self.0.fold_row(index, log_height, beta, evals)
+    }
+
+    fn fold_matrix<M: Matrix<Challenge>>(&self, beta: Challenge, m: M) -> Vec<Challenge> {
+        self.0.fold_matrix(beta, m)
+    }
+
+    fn sample_mix_challenge<V, C>(&self, challenger: &mut C) -> Option<Challenge>
+    where
+        V: Field,
+        Challenge: ExtensionField<V>,
+        C: FieldChallenger<V>,
+    {
+        Some(challenger.sample_ext_element())
+    }
+
+    fn mix_input(
+        &self,
+        folded: Challenge,
+        input: Challenge,
+        mix_challenge: Option<Challenge>,
+    ) -> Challenge {
+        folded + mix_challenge.unwrap() * input
+    }
+
+    fn max_final_poly_len(&self) -> usize {
+        self.0.max_final_poly_len()
+    }
+
+    fn interpolate_final_poly(&self, evals: Vec<Challenge>) -> Vec<Challenge> {
+        self.0.interpolate_final_poly(evals)
+    }
+
+    fn eval_final_poly(
+        &self,
+        index: usize,
+        log_height: usize,
+        final_poly: &[Challenge],
+    ) -> Challenge {
+        self.0.eval_final_poly(index, log_height, final_poly)
+    }
+}
+
+#[test]
+fn test_random_input_mixing() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let g = RandomMixConfig(generic_config());
+
+    let mut chal = TranscriptRecorder::new(Challenger::new(perm.clone()));
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&g, &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+
+    // `alpha`, then a folding challenge and a mixing challenge per round.
+    let num_rounds = proof.commit_phase_commits.len();
+    let num_samples = chal
+        .events()
+        .iter()
+        .filter(|e| matches!(e, TranscriptEvent::Sample(_)))
+        .count();
+    assert_eq!(num_samples, 4 + num_rounds * 2 * 4);
+
+    let mut v_chal = TranscriptRecorder::new(Challenger::new(perm.clone()));
+    let _alpha: Challenge = v_chal.sample_ext_element();
+    verifier::verify(&g, &fc, &proof, &mut v_chal, |_index, proof| {
+        Ok(proof.clone())
+    })
+    .unwrap();
+    assert_eq!(chal.events(), v_chal.events());
+
+    // Mixing with plain additions instead is rejected.
+    assert!(verify_ldes(&perm, &fc, &proof).is_err());
+}
+
+/// Commits to the even and odd parts of each pair of sibling evaluations, rather than the
+/// evaluations themselves.
+struct EvenOddCommitConfig(MyGenericConfig);
+
+impl EvenOddCommitConfig {
+    fn encode(row: &mut [Challenge]) {
+        let (a, b) = (row[0], row[1]);
+        row[0] = (a + b).halve();
+        row[1] = (a - b).halve();
+    }
+}
+
+impl FriGenericConfig<Challenge> for EvenOddCommitConfig {
+    type InputProof = Vec<(usize, Challenge)>;
+    type InputError = ();
+
+    fn extra_query_index_bits(&self) -> usize {
+        self.0.extra_query_index_bits()
+    }
+
+    fn fold_row(
+        &self,
+        index: usize,
+        log_height: usize,
+        beta: Challenge,
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        self.0.fold_row(index, log_height, beta, evals)
+    }
+
+    fn fold_matrix<M: Matrix<Challenge>>(&self, beta: Challenge, m: M) -> Vec<Challenge> {
+        self.0.fold_matrix(beta, m)
+    }
+
+    fn commit_phase_matrix(
+        &self,
+        evals: &RowMajorMatrix<Challenge>,
+    ) -> Option<RowMajorMatrix<Challenge>> {
+        let mut committed = evals.clone();
+        committed.rows_mut().for_each(Self::encode);
+        Some(committed)
+    }
+
+    fn encode_commit_phase_row(
+        &self,
+        _index: usize,
+        _log_height: usize,
+        mut row: Vec<Challenge>,
+    ) -> Vec<Challenge> {
+        Self::encode(&mut row);
+        row
+    }
+
+    fn decode_commit_phase_row(&self, _index: usize, _log_height: usize, row: &mut [Challenge]) {
+        let (s, d) = (row[0], row[1]);
+        row[0] = s + d;
+        row[1] = s - d;
+    }
+
+    fn max_final_poly_len(&self) -> usize {
+        self.0.max_final_poly_len()
+    }
+
+    fn interpolate_final_poly(&self, evals: Vec<Challenge>) -> Vec<Challenge> {
+        self.0.interpolate_final_poly(evals)
+    }
+
+    fn eval_final_poly(
+        &self,
+        index: usize,
+        log_height: usize,
+        final_poly: &[Challenge],
+    ) -> Challenge {
+        self.0.eval_final_poly(index, log_height, final_poly)
+    }
+}
+
+#[test]
+fn test_commit_even_odd_parts() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let ldes = get_ldes(&mut rng, 3..10);
+    let g = EvenOddCommitConfig(generic_config());
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&g, &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+
+    // The sibling values sent are still evaluations, so folding is unchanged.
+    let mut v_chal = Challenger::new(perm.clone());
+    let _alpha: Challenge = v_chal.sample_ext_element();
+    verifier::verify(&g, &fc, &proof, &mut v_chal, |_index, proof| {
+        Ok(proof.clone())
+    })
+    .unwrap();
+
+    // A verifier expecting committed evaluations rejects the openings.
+    assert!(matches!(
+        verify_ldes(&perm, &fc, &proof),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+}
+
+#[test]
+fn test_fri_ldt() {
+    // FRI is kind of flaky depending on indexing luck
+    for i in 0..4 {
+        let mut rng = ChaCha20Rng::seed_from_u64(i);
+        do_test_fri_ldt(&mut rng, 0);
+    }
+}
+
+#[test]
+fn test_fri_ldt_final_poly() {
+    for i in 0..4 {
+        let mut rng = ChaCha20Rng::seed_from_u64(i);
+        do_test_fri_ldt(&mut rng, 2);
+    }
+}
+
+#[test]
+fn test_keccak_challenger() {
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type KeccakCompress = CompressionFunctionFromHasher<u8, ByteHash, 2, 32>;
+    type KeccakValMmcs = FieldMerkleTreeMmcs<Val, u8, FieldHash, KeccakCompress, 32>;
+    type KeccakChallengeMmcs = ExtensionMmcs<Val, Challenge, KeccakValMmcs>;
+    type KeccakChallenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, fc) = get_ldt_for_testing(&mut rng, 0);
+    let byte_hash = ByteHash {};
+    let val_mmcs = KeccakValMmcs::new(FieldHash::new(byte_hash), KeccakCompress::new(byte_hash));
+    let fc = FriConfig::new(
+        fc.log_blowup,
+        fc.log_final_poly_len,
+        fc.num_queries,
+        fc.proof_of_work_bits,
+        KeccakChallengeMmcs::new(val_mmcs),
+    );
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    // The folding challenges and query indices are all derived from Keccak output bytes.
+    let mut p_challenger = KeccakChallenger::from_hasher(vec![], byte_hash);
+    let alpha: Challenge = p_challenger.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(
+        &generic_config(),
+        &fc,
+        input.clone(),
+        &mut p_challenger,
+        |idx| open_input(&input, idx),
+    )
+    .unwrap();
+
+    let mut v_challenger = KeccakChallenger::from_hasher(vec![], byte_hash);
+    let _alpha: Challenge = v_challenger.sample_ext_element();
+    verifier::verify(
+        &generic_config(),
+        &fc,
+        &proof,
+        &mut v_challenger,
+        |_index, proof| Ok(proof.clone()),
+    )
+    .unwrap();
+    assert_eq!(
+        p_challenger.sample_bits(8),
+        v_challenger.sample_bits(8),
+        "prover and verifier transcript have same state after FRI"
+    );
+}
+
+/// An MMCS which opens many indices at once, with a proof made of the inner scheme's proof of each
+/// index. This saves nothing, but lets FRI's support for such schemes be tested.
+#[derive(Clone)]
+struct MultiOpenMmcs {
+    inner: ChallengeMmcs,
+    /// Whether `verify_multi_batch` is supported, as a verifier may be built without it.
+    verify_multi: bool,
+}
+
+impl Mmcs<Challenge> for MultiOpenMmcs {
+    type ProverData<M> = <ChallengeMmcs as Mmcs<Challenge>>::ProverData<M>;
+    type Commitment = <ChallengeMmcs as Mmcs<Challenge>>::Commitment;
+    type Proof = Vec<<ChallengeMmcs as Mmcs<Challenge>>::Proof>;
+    type Error = FieldMerkleTreeError;
+    type OpenScratch = ();
+
+    fn commit<M: Matrix<Challenge>>(
+        &self,
+        inputs: Vec<M>,
+    ) -> (Self::Commitment, Self::ProverData<M>) {
+        self.inner.commit(inputs)
+    }
+
+    fn open_batch<M: Matrix<Challenge>>(
+        &self,
+        index: usize,
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Challenge>>, Self::Proof) {
+        let (opened_values, proof) = self.inner.open_batch(index, prover_data);
+        (opened_values, vec![proof])
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn open_multi_batch<M: Matrix<Challenge>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> Option<(Vec<Vec<Vec<Challenge>>>, Self::Proof)> {
+        Some(
+            indices
+                .iter()
+                .map(|&index| self.inner.open_batch(index, prover_data))
+                .unzip(),
+        )
+    }
+
+    fn get_matrices<'a, M: Matrix<Challenge>>(
+        &self,
+        prover_data: &'a Self::ProverData<M>,
+    ) -> Vec<&'a M> {
+        self.inner.get_matrices(prover_data)
+    }
+
+    fn verify_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        index: usize,
+        opened_values: &[Vec<Challenge>],
+        proof: &Self::Proof,
+    ) -> Result<(), Self::Error> {
+        let [proof] = proof.as_slice() else {
+            return Err(FieldMerkleTreeError::WrongBatchSize);
+        };
+        self.inner
+            .verify_batch(commit, dimensions, index, opened_values, proof)
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<Challenge>>],
+        proof: &Self::Proof,
+    ) -> Option<Result<(), Self::Error>> {
+        if !self.verify_multi {
+            return None;
+        }
+        if opened_values.len() != indices.len() || proof.len() != indices.len() {
+            return Some(Err(FieldMerkleTreeError::WrongBatchSize));
+        }
+        Some(indices.iter().zip(opened_values).zip(proof).try_for_each(
+            |((&index, opened_values), proof)| {
+                self.inner
+                    .verify_batch(commit, dimensions, index, opened_values, proof)
+            },
+        ))
+    }
+}
+
+#[test]
+fn test_multi_open_mmcs() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let fc = FriConfig::new(
+        fc.log_blowup,
+        fc.log_final_poly_len,
+        fc.num_queries,
+        fc.proof_of_work_bits,
+        MultiOpenMmcs {
+            inner: fc.mmcs,
+            verify_multi: true,
+        },
+    );
+    let ldes = get_ldes(&mut rng, 3..10);
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&generic_config(), &fc, input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+    let p_sample: usize = chal.sample_bits(8);
+
+    // Every round is opened for all queries at once, so no query has an opening proof of its own.
+    assert_eq!(
+        proof.commit_phase_batch_proofs.len(),
+        proof.commit_phase_commits.len()
+    );
+    assert!(proof.commit_phase_batch_proofs.iter().all(Option::is_some));
+    assert!(proof
+        .query_proofs
+        .iter()
+        .flat_map(|qp| &qp.commit_phase_openings)
+        .all(|step| step.opening_proof.is_none()));
+
+    let verify = |proof: &FriProof<Challenge, MultiOpenMmcs, Val, Vec<(usize, Challenge)>>| {
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        verifier::verify(
+            &generic_config(),
+            &fc,
+            proof,
+            &mut v_challenger,
+            |_index, proof| Ok(proof.clone()),
+        )
+        .map(|()| v_challenger.sample_bits(8))
+    };
+    assert_eq!(verify(&proof).unwrap(), p_sample);
+
+    // A bad path in a shared opening proof is caught.
+    let mut corrupted = proof.clone();
+    corrupted.commit_phase_batch_proofs[2].as_mut().unwrap()[5][0][0] += Val::one();
+    assert!(matches!(
+        verify(&corrupted),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+
+    // A round with neither a shared opening proof nor per-query ones is rejected.
+    let mut missing = proof;
+    missing.commit_phase_batch_proofs[0] = None;
+    assert!(matches!(verify(&missing), Err(FriError::InvalidProofShape)));
+}
+
+/// Commits to each pair of sibling evaluations followed by their sum, so the committed rows are
+/// wider than the folding arity.
+struct SumColumnCommitConfig(MyGenericConfig);
+
+impl FriGenericConfig<Challenge> for SumColumnCommitConfig {
+    type InputProof = Vec<(usize, Challenge)>;
+    type InputError = ();
+
+    fn extra_query_index_bits(&self) -> usize {
+        self.0.extra_query_index_bits()
+    }
+
+    fn fold_row(
+        &self,
+        index: usize,
+        log_height: usize,
+        beta: Challenge,
+        evals: impl Iterator<Item = Challenge>,
+    ) -> Challenge {
+        self.0.fold_row(index, log_height, beta, evals)
+    }
+
+    fn fold_matrix<M: Matrix<Challenge>>(&self, beta: Challenge, m: M) -> Vec<Challenge> {
+        self.0.fold_matrix(beta, m)
+    }
+
+    fn commit_phase_matrix(
+        &self,
+        evals: &RowMajorMatrix<Challenge>,
+    ) -> Option<RowMajorMatrix<Challenge>> {
+        let committed = evals
+            .values
+            .chunks_exact(2)
+            .flat_map(|row| [row[0], row[1], row[0] + row[1]])
+            .collect();
+        Some(RowMajorMatrix::new(committed, 3))
+    }
+
+    fn encode_commit_phase_row(
+        &self,
+        _index: usize,
+        _log_height: usize,
+        mut row: Vec<Challenge>,
+    ) -> Vec<Challenge> {
+        row.push(row[0] + row[1]);
+        row
+    }
+
+    fn max_final_poly_len(&self) -> usize {
+        self.0.max_final_poly_len()
+    }
+
+    fn interpolate_final_poly(&self, evals: Vec<Challenge>) -> Vec<Challenge> {
+        self.0.interpolate_final_poly(evals)
+    }
+
+    fn eval_final_poly(
+        &self,
+        index: usize,
+        log_height: usize,
+        final_poly: &[Challenge],
+    ) -> Challenge {
+        self.0.eval_final_poly(index, log_height, final_poly)
+    }
+}
+
+#[test]
+fn test_multi_open_wide_rows() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0);
+    let fri_config = |verify_multi: bool| {
+        let mut config = FriConfig::new(
+            fc.log_blowup,
+            fc.log_final_poly_len,
+            fc.num_queries,
+            fc.proof_of_work_bits,
+            MultiOpenMmcs {
+                inner: fc.mmcs.clone(),
+                verify_multi,
+            },
+        );
+        config.commit_mixed_inputs = true;
+        config
+    };
+    let ldes = get_ldes(&mut rng, 3..10);
+    let g = SumColumnCommitConfig(generic_config());
+
+    let mut chal = Challenger::new(perm.clone());
+    let alpha: Challenge = chal.sample_ext_element();
+    let input = reduce_ldes(&ldes, alpha);
+    let proof = prover::prove(&g, &fri_config(true), input.clone(), &mut chal, |idx| {
+        open_input(&input, idx)
+    })
+    .unwrap();
+    let p_sample: usize = chal.sample_bits(8);
+    assert!(proof.commit_phase_batch_proofs.iter().all(Option::is_some));
+
+    let verify = |verify_multi: bool, wide_rows: bool| {
+        let fc = fri_config(verify_multi);
+        let mut v_challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = v_challenger.sample_ext_element();
+        let open_input = |_index: usize, proof: &Vec<(usize, Challenge)>| Ok(proof.clone());
+        if wide_rows {
+            verifier::verify(&g, &fc, &proof, &mut v_challenger, open_input)
+        } else {
+            verifier::verify(
+                &generic_config(),
+                &fc,
+                &proof,
+                &mut v_challenger,
+                open_input,
+            )
+        }
+        .map(|()| v_challenger.sample_bits(8))
+    };
+    // The batched openings are checked against rows of the encoded width, along with the
+    // committed inputs' rows of the folding arity.
+    assert_eq!(verify(true, true).unwrap(), p_sample);
+
+    // A verifier expecting committed evaluations rejects the openings.
+    assert!(matches!(
+        verify(true, false),
+        Err(FriError::CommitPhaseMmcsError(_))
+    ));
+
+    // A verifier whose MMCS can't check openings of several indices at once says so, rather than
+    // blaming the proof's shape.
+    assert!(matches!(
+        verify(false, true),
+        Err(FriError::MultiOpeningUnsupported)
+    ));
 }
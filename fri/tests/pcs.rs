@@ -186,12 +186,7 @@ mod babybear_fri_pcs {
         let val_mmcs = ValMmcs::new(hash, compress);
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
-        let fri_config = FriConfig {
-            log_blowup,
-            num_queries: 10,
-            proof_of_work_bits: 8,
-            mmcs: challenge_mmcs,
-        };
+        let fri_config = FriConfig::new(log_blowup, 0, 10, 8, challenge_mmcs);
 
         let pcs = MyPcs::new(Dft {}, val_mmcs, fri_config);
         (pcs, Challenger::new(perm.clone()))
@@ -238,12 +233,7 @@ mod m31_fri_pcs {
         let compress = MyCompress::new(byte_hash);
         let val_mmcs = ValMmcs::new(field_hash, compress);
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-        let fri_config = FriConfig {
-            log_blowup,
-            num_queries: 10,
-            proof_of_work_bits: 8,
-            mmcs: challenge_mmcs,
-        };
+        let fri_config = FriConfig::new(log_blowup, 0, 10, 8, challenge_mmcs);
         let pcs = Pcs {
             mmcs: val_mmcs,
             fri_config,
@@ -8,7 +8,7 @@
 
 use p3_dft::Radix2Bowers;
 use p3_mds::karatsuba_convolution::Convolve;
-use p3_mds::util::{apply_circulant, apply_circulant_fft, first_row_to_first_col};
+use p3_mds::util::{apply_circulant, apply_circulant_fft, dot_product_acc, first_row_to_first_col};
 use p3_mds::MdsPermutation;
 use p3_symmetric::Permutation;
 
@@ -40,9 +40,7 @@ impl Convolve<Goldilocks, i128, i64, i128> for SmallConvolveGoldilocks {
     #[inline(always)]
     fn parity_dot<const N: usize>(u: [i128; N], v: [i64; N]) -> i128 {
         let mut s = 0i128;
-        for i in 0..N {
-            s += u[i] * v[i] as i128;
-        }
+        dot_product_acc(&mut s, &u, &v);
         s
     }
 
@@ -1,13 +1,20 @@
 use std::any::type_name;
 
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use p3_baby_bear::{BabyBear, MdsMatrixBabyBear};
 use p3_field::{AbstractField, Field};
-use p3_goldilocks::{Goldilocks, MdsMatrixGoldilocks};
+use p3_goldilocks::{Goldilocks, MdsMatrixGoldilocks, SmallConvolveGoldilocks};
 use p3_mds::coset_mds::CosetMds;
 use p3_mds::integrated_coset_mds::IntegratedCosetMds;
+use p3_mds::karatsuba_convolution::{
+    negacyclic_conv_karat, negacyclic_conv_karat_scratch_len, negacyclic_conv_karat_with_scratch,
+    Convolve,
+};
 use p3_mds::MdsPermutation;
-use p3_mersenne_31::{MdsMatrixMersenne31, Mersenne31};
+use p3_mersenne_31::{
+    apply_circulant_16_karat, apply_circulant_16_karat_with_col, Circulant16Col,
+    MdsMatrixMersenne31, Mersenne31, SmallConvolveMersenne31,
+};
 use rand::distributions::{Distribution, Standard};
 use rand::{thread_rng, Rng};
 
@@ -51,5 +58,183 @@ where
     c.bench_with_input(id, &input, |b, input| b.iter(|| mds.permute(input.clone())));
 }
 
-criterion_group!(benches, bench_all_mds);
+/// Compare the two formulations of the size 4 negacyclic convolution
+/// at the bottom of the Karatsuba recursion.
+fn bench_negacyclic_conv4(c: &mut Criterion) {
+    type Conv = SmallConvolveGoldilocks;
+
+    let mut rng = thread_rng();
+    let lhs = rng
+        .gen::<[Goldilocks; 4]>()
+        .map(<Conv as Convolve<Goldilocks, i128, i64, i128>>::read);
+    let rhs = [7, 1, 3, 8];
+
+    let mut group = c.benchmark_group("negacyclic_conv4");
+    group.bench_function("matrix_dot", |b| {
+        b.iter(|| {
+            let mut output = [0; 4];
+            <Conv as Convolve<Goldilocks, i128, i64, i128>>::negacyclic_conv4(
+                lhs,
+                rhs,
+                &mut output,
+            );
+            output
+        })
+    });
+    group.bench_function("karatsuba", |b| {
+        b.iter(|| {
+            let mut output = [0; 4];
+            <Conv as Convolve<Goldilocks, i128, i64, i128>>::negacyclic_conv4_karatsuba(
+                lhs,
+                rhs,
+                &mut output,
+            );
+            output
+        })
+    });
+    group.finish();
+}
+
+/// Compare applying a size 64 circulant matrix with a separate
+/// reduction pass against the fused variant, over a batch of inputs.
+fn bench_apply_conv64_fused(c: &mut Criterion) {
+    type Conv = SmallConvolveGoldilocks;
+    const BATCH_SIZE: usize = 100;
+
+    let mut rng = thread_rng();
+    let inputs: Vec<[Goldilocks; 64]> = (0..BATCH_SIZE).map(|_| rng.gen()).collect();
+    let rhs: [i64; 64] = core::array::from_fn(|_| rng.gen_range(0..16));
+
+    let mut group = c.benchmark_group("apply_conv64");
+    group.bench_function("separate_reduce", |b| {
+        b.iter(|| {
+            inputs
+                .iter()
+                .map(|&input| {
+                    <Conv as Convolve<Goldilocks, i128, i64, i128>>::apply(
+                        input,
+                        rhs,
+                        <Conv as Convolve<Goldilocks, i128, i64, i128>>::conv64,
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("fused", |b| {
+        b.iter(|| {
+            inputs
+                .iter()
+                .map(|&input| {
+                    <Conv as Convolve<Goldilocks, i128, i64, i128>>::apply_conv64_fused(input, rhs)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+const CIRCULANT_16_ROW: [i64; 16] = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9, 3];
+
+/// Compare applying a size 16 circulant matrix given by its first row,
+/// which is converted to a column and checked on every call, against
+/// passing a `Circulant16Col` built once from the same row, over a batch
+/// of inputs.
+fn bench_apply_circulant_16_with_col(c: &mut Criterion) {
+    const BATCH_SIZE: usize = 100;
+
+    let mut rng = thread_rng();
+    let inputs: Vec<[Mersenne31; 16]> = (0..BATCH_SIZE).map(|_| rng.gen()).collect();
+
+    let mut group = c.benchmark_group("apply_circulant_16");
+    group.bench_function("first_row", |b| {
+        // As for a row only known at runtime.
+        let row = black_box(CIRCULANT_16_ROW);
+        b.iter(|| {
+            inputs
+                .iter()
+                .map(|&input| apply_circulant_16_karat(input, &row))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("first_col", |b| {
+        let col = Circulant16Col::from_first_row(&black_box(CIRCULANT_16_ROW)).unwrap();
+        b.iter(|| {
+            inputs
+                .iter()
+                .map(|&input| apply_circulant_16_karat_with_col(input, &col))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+/// `SmallConvolveMersenne31` with a base case length of 2.
+struct SmallConvolveMersenne31Base2;
+
+impl Convolve<Mersenne31, i64, i64, i64> for SmallConvolveMersenne31Base2 {
+    const BASE_CASE_LEN: usize = 2;
+
+    fn read(input: Mersenne31) -> i64 {
+        SmallConvolveMersenne31::read(input)
+    }
+
+    fn parity_dot<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> i64 {
+        SmallConvolveMersenne31::parity_dot(lhs, rhs)
+    }
+
+    fn reduce(z: i64) -> Mersenne31 {
+        SmallConvolveMersenne31::reduce(z)
+    }
+}
+
+/// Compare negacyclic convolutions of size 2 and of size 4 at the
+/// bottom of the Karatsuba recursion (see `Convolve::BASE_CASE_LEN`),
+/// for a size 16 circulant matrix over Mersenne31.
+fn bench_base_case_len(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let input = rng.gen::<[Mersenne31; 16]>();
+    let col: [i64; 16] = core::array::from_fn(|_| rng.gen_range(0..1 << 9));
+
+    let mut group = c.benchmark_group("base_case_len");
+    group.bench_function("2", |b| {
+        b.iter(|| {
+            SmallConvolveMersenne31Base2::apply(input, col, SmallConvolveMersenne31Base2::conv16)
+        })
+    });
+    group.bench_function("4", |b| {
+        b.iter(|| SmallConvolveMersenne31::apply(input, col, SmallConvolveMersenne31::conv16))
+    });
+    group.finish();
+}
+
+/// Compare the slice-based negacyclic convolution, which allocates at
+/// each level of the recursion, against the variant taking a scratch
+/// buffer, which is allocated once outside the loop.
+fn bench_negacyclic_conv_karat_scratch(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let mut group = c.benchmark_group("negacyclic_conv_karat");
+    for n in [32, 64] {
+        let lhs: Vec<i64> = (0..n).map(|_| rng.gen_range(-(1 << 20)..1 << 20)).collect();
+        let rhs: Vec<i64> = (0..n).map(|_| rng.gen_range(-(1 << 20)..1 << 20)).collect();
+        group.bench_with_input(BenchmarkId::new("allocating", n), &n, |b, _| {
+            b.iter(|| negacyclic_conv_karat(&lhs, &rhs))
+        });
+        let mut output = vec![0; n];
+        let mut scratch = vec![0; negacyclic_conv_karat_scratch_len(n)];
+        group.bench_with_input(BenchmarkId::new("scratch", n), &n, |b, _| {
+            b.iter(|| negacyclic_conv_karat_with_scratch(&lhs, &rhs, &mut output, &mut scratch))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_all_mds,
+    bench_negacyclic_conv4,
+    bench_apply_conv64_fused,
+    bench_apply_circulant_16_with_col,
+    bench_base_case_len,
+    bench_negacyclic_conv_karat_scratch
+);
 criterion_main!(benches);
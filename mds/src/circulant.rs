@@ -0,0 +1,92 @@
+//! A common interface to the algorithms for multiplying a vector by a
+//! circulant matrix, so that they can be swapped for one another (e.g.
+//! to benchmark an NTT-based algorithm against Karatsuba).
+
+use core::array;
+
+use p3_field::PrimeField64;
+
+use crate::karatsuba_convolution::apply_circulant_karat_i128;
+use crate::util::signed_to_field;
+
+/// An algorithm for multiplying a vector of length `N` by a circulant
+/// matrix.
+pub trait CirculantApply<F, const N: usize> {
+    /// Return the product of the circulant matrix with first row
+    /// `first_row` and `input`.
+    fn apply(input: [F; N], first_row: [i64; N]) -> [F; N];
+}
+
+/// The Karatsuba convolutions of `karatsuba_convolution`, over `i128`
+/// with `Convolve::conv_n`. They are used for the widths and bounds
+/// given in `apply_circulant_karat_slice`, and the naive algorithm
+/// otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KaratsubaCirculant;
+
+impl<F: PrimeField64, const N: usize> CirculantApply<F, N> for KaratsubaCirculant {
+    fn apply(input: [F; N], first_row: [i64; N]) -> [F; N] {
+        apply_circulant_karat_i128(input, first_row).unwrap_or_else(|| {
+            let row = first_row.map(signed_to_field::<F>);
+            array::from_fn(|i| (0..N).map(|j| row[(N + j - i) % N] * input[j]).sum())
+        })
+    }
+}
+
+/// Multiply `input` by the circulant matrix with first row
+/// `first_row`, using the algorithm `S`.
+///
+/// Unlike `util::apply_circulant`, which always uses the naive
+/// algorithm, this lets callers choose the algorithm with a type
+/// parameter, e.g. `apply_circulant::<KaratsubaCirculant, _, 16>`.
+#[inline]
+pub fn apply_circulant<S, F, const N: usize>(input: [F; N], first_row: [i64; N]) -> [F; N]
+where
+    S: CirculantApply<F, N>,
+{
+    S::apply(input, first_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_goldilocks::Goldilocks;
+    use rand::distributions::{Distribution, Standard};
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+
+    /// The quadratic algorithm, standing in for some other strategy.
+    struct NaiveCirculant;
+
+    impl<F: PrimeField64, const N: usize> CirculantApply<F, N> for NaiveCirculant {
+        fn apply(input: [F; N], first_row: [i64; N]) -> [F; N] {
+            let row = first_row.map(signed_to_field::<F>);
+            array::from_fn(|i| (0..N).map(|j| row[(N + j - i) % N] * input[j]).sum())
+        }
+    }
+
+    fn check_strategies_agree<F: PrimeField64, const N: usize>()
+    where
+        Standard: Distribution<F>,
+    {
+        let mut rng = thread_rng();
+        let input: [F; N] = rng.gen();
+        let first_row: [i64; N] = array::from_fn(|_| rng.gen_range(-(1 << 20)..1 << 20));
+        assert_eq!(
+            apply_circulant::<NaiveCirculant, _, N>(input, first_row),
+            apply_circulant::<KaratsubaCirculant, _, N>(input, first_row)
+        );
+    }
+
+    #[test]
+    fn strategies_agree() {
+        check_strategies_agree::<BabyBear, 8>();
+        check_strategies_agree::<BabyBear, 16>();
+        check_strategies_agree::<BabyBear, 24>();
+        check_strategies_agree::<Goldilocks, 12>();
+        check_strategies_agree::<Goldilocks, 64>();
+        // A width without a Karatsuba convolution.
+        check_strategies_agree::<Goldilocks, 9>();
+    }
+}
@@ -44,7 +44,14 @@
 //! Of course, for small sizes we just explicitly write out the O(n^2)
 //! approach.
 
-use core::ops::{Add, AddAssign, Neg, ShrAssign, Sub, SubAssign};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, AddAssign, Mul, Neg, ShrAssign, Sub, SubAssign};
+
+use p3_field::{PrimeField32, PrimeField64};
+use p3_maybe_rayon::prelude::*;
+
+use crate::util::{dot_product, first_row_to_first_col, interleave_slices, signed_to_field};
 
 /// This trait collects the operations needed by `Convolve` below.
 ///
@@ -55,6 +62,7 @@ pub trait RngElt:
     + Copy
     + Default
     + Neg<Output = Self>
+    + PartialEq
     + ShrAssign<u32>
     + Sub<Output = Self>
     + SubAssign
@@ -64,6 +72,46 @@ pub trait RngElt:
 impl RngElt for i64 {}
 impl RngElt for i128 {}
 
+/// The operations needed by the slice-based convolutions `conv_karat`
+/// and `negacyclic_conv_karat`, which multiply their inputs directly
+/// rather than going through `Convolve::parity_dot`.
+pub trait SimpleInteger: RngElt + Mul<Output = Self> {}
+
+impl<T: RngElt + Mul<Output = T>> SimpleInteger for T {}
+
+/// Multiplication of two `Self`s into the accumulator type `V`.
+///
+/// This lets `conv_karat_widening` take inputs of one type and sum
+/// their products in a wider one, e.g. `i64` inputs with an `i128`
+/// accumulator, so that reduction can be deferred until after
+/// several convolutions.
+pub trait WideningMul<V: SimpleInteger>: RngElt {
+    fn widening_mul(self, rhs: Self) -> V;
+}
+
+impl<T: SimpleInteger> WideningMul<T> for T {
+    #[inline(always)]
+    fn widening_mul(self, rhs: T) -> T {
+        self * rhs
+    }
+}
+
+impl WideningMul<i128> for i64 {
+    #[inline(always)]
+    fn widening_mul(self, rhs: i64) -> i128 {
+        self as i128 * rhs as i128
+    }
+}
+
+/// A constant vector of length `N`, carried by a type so that it can
+/// be supplied as a generic parameter (stable Rust does not allow
+/// arrays as const generic parameters).
+///
+/// For example usage, see `Convolve::conv16_const`.
+pub trait ConstVector<U, const N: usize> {
+    const VALUE: [U; N];
+}
+
 /// Template function to perform convolution of vectors.
 ///
 /// Roughly speaking, for a convolution of size `N`, it should be
@@ -73,6 +121,13 @@ impl RngElt for i128 {}
 /// `N` additions (this is an over-estimate).
 ///
 /// For example usage, see `{mersenne-31,baby-bear,goldilocks}/src/mds.rs`.
+/// The implementations there are public, so that circulant matrices
+/// other than the crates' own can reuse the tuned convolutions of this
+/// trait. Each documents the bounds on `rhs` it relies on, which are
+/// not checked at runtime: the "small" implementations over 31-bit
+/// fields need non-negative entries passing `small_conv_is_safe`, while
+/// the "large" ones, like `LargeConvolvePrimeField32`, accept any
+/// entries less than the field's order in absolute value.
 ///
 /// NB: In practice, one of the parameters to the convolution will be
 /// constant (the MDS matrix). After inspecting Godbolt output, it
@@ -107,6 +162,58 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
     /// element.
     fn reduce(z: V) -> F;
 
+    /// The size at which the negacyclic convolutions stop recursing
+    /// and use `parity_dot` directly: either 4, the default, or 2, in
+    /// which case `negacyclic_conv4` is `negacyclic_conv4_karatsuba`.
+    /// The cyclic convolutions always bottom out in `conv4`, which is
+    /// already split down to size 2.
+    ///
+    /// Size 2 does 12 rather than 16 multiplications per negacyclic
+    /// convolution of size 4, at the cost of more additions, so which
+    /// is faster depends on how expensive `parity_dot` is compared to
+    /// the additions around it (see `bench_base_case_len` in
+    /// `mds/benches/mds.rs`). The outputs are identical either way,
+    /// but the bounds required are not: the size 2 convolutions
+    /// multiply sums of two entries of each input, so a bound on
+    /// `parity_dot` stated in terms of the largest entry of `rhs`
+    /// roughly doubles (see `negacyclic_conv4_karatsuba`). One stated
+    /// in terms of the sum of the absolute values of `rhs`, as
+    /// `small_conv_is_safe` is, still holds, since each of those sums
+    /// is of distinct entries. Strategies choosing 2 must have room
+    /// for this in their bounds.
+    const BASE_CASE_LEN: usize = 4;
+
+    /// Whether `lhs` and `rhs`, e.g. outputs of `apply_unreduced`,
+    /// represent the same element of `F`. Internal elements aren't
+    /// canonical, so two outputs which agree in `F` (say `P` and `0`)
+    /// can differ as integers; this compares them after `reduce`.
+    ///
+    /// Callers needing exact equality of the internal representations
+    /// should canonicalize both sides fully first.
+    #[inline(always)]
+    fn eq_canonical(lhs: V, rhs: V) -> bool
+    where
+        F: PartialEq,
+    {
+        Self::reduce(lhs) == Self::reduce(rhs)
+    }
+
+    /// Partially reduce `z`, an output of one level of a negacyclic
+    /// convolution, before it is used by the level above. Each
+    /// negacyclic recombination step can triple the size of the
+    /// values (see e.g. `mersenne-31/src/mds.rs`), so reducing after
+    /// every level keeps the intermediates close to the size of the
+    /// `parity_dot` outputs, at the cost of more reductions. As for
+    /// `parity_dot`, the result must agree with `z` modulo the order
+    /// of `F` and in its lower 10 bits.
+    ///
+    /// The default does nothing, which is right whenever the
+    /// intermediates can't overflow `V` anyway.
+    #[inline(always)]
+    fn reduce_intermediate(z: V) -> V {
+        z
+    }
+
     /// Convolve `lhs` and `rhs`.
     ///
     /// The parameter `conv` should be the function in this trait that
@@ -117,10 +224,158 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
         rhs: [U; N],
         conv: C,
     ) -> [F; N] {
+        Self::apply_unreduced(lhs, rhs, conv).map(Self::reduce)
+    }
+
+    /// Same as `apply`, but returns the output of `conv` without
+    /// applying `reduce`, so that a caller which feeds the result into
+    /// further arithmetic can defer the reduction.
+    #[inline(always)]
+    fn apply_unreduced<const N: usize, C: Fn([T; N], [U; N], &mut [V])>(
+        lhs: [F; N],
+        rhs: [U; N],
+        conv: C,
+    ) -> [V; N] {
         let lhs = lhs.map(Self::read);
         let mut output = [V::default(); N];
         conv(lhs, rhs, &mut output);
-        output.map(Self::reduce)
+        output
+    }
+
+    /// Convolve `lhs` and `rhs` for any supported size `N`, namely
+    /// 3, 4, 5, 6, 7, 8, 10, 12, 16, 20, 24, 32, 40 or 64, by dispatching to the
+    /// corresponding function of this trait. The dispatch is resolved
+    /// at compile time.
+    ///
+    /// Panics if `N` is not a supported size.
+    #[inline(always)]
+    fn conv_n<const N: usize>(lhs: [T; N], rhs: [U; N], output: &mut [V]) {
+        let (lhs, rhs) = (&lhs[..], &rhs[..]);
+        match N {
+            3 => Self::conv3(to_array(lhs), to_array(rhs), output),
+            4 => Self::conv4(to_array(lhs), to_array(rhs), output),
+            5 => Self::conv5(to_array(lhs), to_array(rhs), output),
+            6 => Self::conv6(to_array(lhs), to_array(rhs), output),
+            7 => Self::conv7(to_array(lhs), to_array(rhs), output),
+            8 => Self::conv8(to_array(lhs), to_array(rhs), output),
+            10 => Self::conv10(to_array(lhs), to_array(rhs), output),
+            12 => Self::conv12(to_array(lhs), to_array(rhs), output),
+            16 => Self::conv16(to_array(lhs), to_array(rhs), output),
+            20 => Self::conv20(to_array(lhs), to_array(rhs), output),
+            24 => Self::conv24(to_array(lhs), to_array(rhs), output),
+            32 => Self::conv32(to_array(lhs), to_array(rhs), output),
+            40 => Self::conv40(to_array(lhs), to_array(rhs), output),
+            64 => Self::conv64(to_array(lhs), to_array(rhs), output),
+            _ => panic!("unsupported convolution size {}", N),
+        }
+    }
+
+    /// Multiply `lhs` by the circulant matrix with first column `rhs`,
+    /// for any size supported by `conv_n`. This is the same as `apply`
+    /// with the size `N` convolution, but lets callers handle every
+    /// width uniformly.
+    #[inline(always)]
+    fn apply_circulant_karat<const N: usize>(lhs: [F; N], rhs: [U; N]) -> [F; N] {
+        Self::apply(lhs, rhs, Self::conv_n::<N>)
+    }
+
+    /// Multiply `lhs` by the circulant matrix with first row
+    /// `first_row`, for width 20; see `conv20` for the bounds required.
+    #[inline(always)]
+    fn apply_circulant_20_karat(lhs: [F; 20], first_row: [U; 20]) -> [F; 20] {
+        Self::apply(lhs, first_row_to_first_col(&first_row), Self::conv20)
+    }
+
+    /// Multiply `lhs` by the negacyclic matrix with first column `rhs`,
+    /// i.e. compute the coefficients of lhs(x)rhs(x) mod x^8 + 1, for
+    /// multiplication by a constant in that ring. The bounds required
+    /// are the same as for the cyclic convolution of size 8.
+    #[inline(always)]
+    fn apply_negacyclic_8_karat(lhs: [F; 8], rhs: [U; 8]) -> [F; 8] {
+        Self::apply(lhs, rhs, Self::negacyclic_conv8)
+    }
+
+    /// Same as `apply_negacyclic_8_karat`, for size 16.
+    #[inline(always)]
+    fn apply_negacyclic_16_karat(lhs: [F; 16], rhs: [U; 16]) -> [F; 16] {
+        Self::apply(lhs, rhs, Self::negacyclic_conv16)
+    }
+
+    /// Same as `apply_negacyclic_8_karat`, for size 32.
+    #[inline(always)]
+    fn apply_negacyclic_32_karat(lhs: [F; 32], rhs: [U; 32]) -> [F; 32] {
+        Self::apply(lhs, rhs, Self::negacyclic_conv32)
+    }
+
+    /// Multiply `lhs` by the circulant matrix with the symmetric first
+    /// row `first_row`, i.e. `first_row[i] == first_row[8 - i]` for
+    /// `0 < i < 8`. Such a row is also the first column of the
+    /// matrix, so no conversion is needed, and the symmetry halves the
+    /// work done splitting it; see `conv8_symmetric`.
+    ///
+    /// The result is wrong if `first_row` is not symmetric; this is
+    /// checked in debug builds only.
+    #[inline(always)]
+    fn apply_circulant_symmetric_8_karat(lhs: [F; 8], first_row: [U; 8]) -> [F; 8]
+    where
+        U: PartialEq,
+    {
+        debug_assert!(is_symmetric(&first_row), "first_row is not symmetric");
+        Self::apply(lhs, first_row, Self::conv8_symmetric)
+    }
+
+    /// Same as `apply_circulant_symmetric_8_karat`, for size 16.
+    #[inline(always)]
+    fn apply_circulant_symmetric_16_karat(lhs: [F; 16], first_row: [U; 16]) -> [F; 16]
+    where
+        U: PartialEq,
+    {
+        debug_assert!(is_symmetric(&first_row), "first_row is not symmetric");
+        Self::apply(lhs, first_row, Self::conv16_symmetric)
+    }
+
+    /// Equivalent to `apply` with `conv32`, but the final CRT step
+    /// of the convolution is fused with `reduce`: each pair of output
+    /// coefficients is recombined and reduced in the same loop, which
+    /// writes straight into the returned array, so the unreduced
+    /// output is never written out in full.
+    #[inline(always)]
+    fn apply_conv32_fused(lhs: [F; 32], rhs: [U; 32]) -> [F; 32]
+    where
+        F: Copy + Default,
+    {
+        apply_conv_n_recursive_fused::<32, 16, F, T, U, V, _, _, _>(
+            lhs.map(Self::read),
+            rhs,
+            Self::conv16,
+            Self::negacyclic_conv16,
+            Self::reduce,
+        )
+    }
+
+    /// Equivalent to `apply` with `conv64`, but the final CRT step
+    /// of the convolution is fused with `reduce`: each pair of output
+    /// coefficients is recombined and reduced in the same loop, which
+    /// writes straight into the returned array, so the unreduced
+    /// output is never written out in full.
+    #[inline(always)]
+    fn apply_conv64_fused(lhs: [F; 64], rhs: [U; 64]) -> [F; 64]
+    where
+        F: Copy + Default,
+    {
+        apply_conv_n_recursive_fused::<64, 32, F, T, U, V, _, _, _>(
+            lhs.map(Self::read),
+            rhs,
+            Self::conv32,
+            Self::negacyclic_conv32,
+            Self::reduce,
+        )
+    }
+
+    #[inline(always)]
+    fn negacyclic_conv2(lhs: [T; 2], rhs: [U; 2], output: &mut [V]) {
+        output[0] = Self::parity_dot(lhs, [rhs[0], -rhs[1]]);
+        output[1] = Self::parity_dot(lhs, [rhs[1], rhs[0]]);
     }
 
     #[inline(always)]
@@ -151,24 +406,128 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
         output[2] = Self::parity_dot(u_p, v_p);
         output[3] = Self::parity_dot(u_p, [v_p[1], v_p[0]]);
 
-        output[0] += output[2];
-        output[1] += output[3];
-
-        output[0] >>= 1;
-        output[1] >>= 1;
-
-        output[2] -= output[0];
-        output[3] -= output[1];
+        let (left, right) = output[..4].split_at_mut(2);
+        crt_recombine(left, right);
     }
 
     #[inline(always)]
     fn negacyclic_conv4(lhs: [T; 4], rhs: [U; 4], output: &mut [V]) {
+        const {
+            assert!(
+                Self::BASE_CASE_LEN == 2 || Self::BASE_CASE_LEN == 4,
+                "unsupported base case length"
+            )
+        };
+        if Self::BASE_CASE_LEN == 2 {
+            return Self::negacyclic_conv4_karatsuba(lhs, rhs, output);
+        }
         output[0] = Self::parity_dot(lhs, [rhs[0], -rhs[3], -rhs[2], -rhs[1]]);
         output[1] = Self::parity_dot(lhs, [rhs[1], rhs[0], -rhs[3], -rhs[2]]);
         output[2] = Self::parity_dot(lhs, [rhs[2], rhs[1], rhs[0], -rhs[3]]);
         output[3] = Self::parity_dot(lhs, [rhs[3], rhs[2], rhs[1], rhs[0]]);
     }
 
+    /// Alternative to `negacyclic_conv4` which uses the even/odd
+    /// Karatsuba decomposition down to size 2, rather than dot products
+    /// with the rows of the signed circulant matrix. This does 12
+    /// multiplications instead of 16, at the cost of more additions and
+    /// data movement; the outputs are identical.
+    ///
+    /// NB: The inputs to the size 2 products are sums of two elements,
+    /// so the bounds required of `parity_dot` are roughly doubled.
+    #[inline(always)]
+    fn negacyclic_conv4_karatsuba(lhs: [T; 4], rhs: [U; 4], output: &mut [V]) {
+        negacyclic_conv_n_recursive::<4, 2, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv2,
+            Self::reduce_intermediate,
+        )
+    }
+
+    #[inline(always)]
+    fn conv5(lhs: [T; 5], rhs: [U; 5], output: &mut [V]) {
+        output[0] = Self::parity_dot(lhs, [rhs[0], rhs[4], rhs[3], rhs[2], rhs[1]]);
+        output[1] = Self::parity_dot(lhs, [rhs[1], rhs[0], rhs[4], rhs[3], rhs[2]]);
+        output[2] = Self::parity_dot(lhs, [rhs[2], rhs[1], rhs[0], rhs[4], rhs[3]]);
+        output[3] = Self::parity_dot(lhs, [rhs[3], rhs[2], rhs[1], rhs[0], rhs[4]]);
+        output[4] = Self::parity_dot(lhs, [rhs[4], rhs[3], rhs[2], rhs[1], rhs[0]]);
+    }
+
+    #[inline(always)]
+    fn negacyclic_conv5(lhs: [T; 5], rhs: [U; 5], output: &mut [V]) {
+        output[0] = Self::parity_dot(lhs, [rhs[0], -rhs[4], -rhs[3], -rhs[2], -rhs[1]]);
+        output[1] = Self::parity_dot(lhs, [rhs[1], rhs[0], -rhs[4], -rhs[3], -rhs[2]]);
+        output[2] = Self::parity_dot(lhs, [rhs[2], rhs[1], rhs[0], -rhs[4], -rhs[3]]);
+        output[3] = Self::parity_dot(lhs, [rhs[3], rhs[2], rhs[1], rhs[0], -rhs[4]]);
+        output[4] = Self::parity_dot(lhs, [rhs[4], rhs[3], rhs[2], rhs[1], rhs[0]]);
+    }
+
+    #[inline(always)]
+    fn conv7(lhs: [T; 7], rhs: [U; 7], output: &mut [V]) {
+        output[0] = Self::parity_dot(
+            lhs,
+            [rhs[0], rhs[6], rhs[5], rhs[4], rhs[3], rhs[2], rhs[1]],
+        );
+        output[1] = Self::parity_dot(
+            lhs,
+            [rhs[1], rhs[0], rhs[6], rhs[5], rhs[4], rhs[3], rhs[2]],
+        );
+        output[2] = Self::parity_dot(
+            lhs,
+            [rhs[2], rhs[1], rhs[0], rhs[6], rhs[5], rhs[4], rhs[3]],
+        );
+        output[3] = Self::parity_dot(
+            lhs,
+            [rhs[3], rhs[2], rhs[1], rhs[0], rhs[6], rhs[5], rhs[4]],
+        );
+        output[4] = Self::parity_dot(
+            lhs,
+            [rhs[4], rhs[3], rhs[2], rhs[1], rhs[0], rhs[6], rhs[5]],
+        );
+        output[5] = Self::parity_dot(
+            lhs,
+            [rhs[5], rhs[4], rhs[3], rhs[2], rhs[1], rhs[0], rhs[6]],
+        );
+        output[6] = Self::parity_dot(
+            lhs,
+            [rhs[6], rhs[5], rhs[4], rhs[3], rhs[2], rhs[1], rhs[0]],
+        );
+    }
+
+    #[inline(always)]
+    fn negacyclic_conv7(lhs: [T; 7], rhs: [U; 7], output: &mut [V]) {
+        output[0] = Self::parity_dot(
+            lhs,
+            [rhs[0], -rhs[6], -rhs[5], -rhs[4], -rhs[3], -rhs[2], -rhs[1]],
+        );
+        output[1] = Self::parity_dot(
+            lhs,
+            [rhs[1], rhs[0], -rhs[6], -rhs[5], -rhs[4], -rhs[3], -rhs[2]],
+        );
+        output[2] = Self::parity_dot(
+            lhs,
+            [rhs[2], rhs[1], rhs[0], -rhs[6], -rhs[5], -rhs[4], -rhs[3]],
+        );
+        output[3] = Self::parity_dot(
+            lhs,
+            [rhs[3], rhs[2], rhs[1], rhs[0], -rhs[6], -rhs[5], -rhs[4]],
+        );
+        output[4] = Self::parity_dot(
+            lhs,
+            [rhs[4], rhs[3], rhs[2], rhs[1], rhs[0], -rhs[6], -rhs[5]],
+        );
+        output[5] = Self::parity_dot(
+            lhs,
+            [rhs[5], rhs[4], rhs[3], rhs[2], rhs[1], rhs[0], -rhs[6]],
+        );
+        output[6] = Self::parity_dot(
+            lhs,
+            [rhs[6], rhs[5], rhs[4], rhs[3], rhs[2], rhs[1], rhs[0]],
+        );
+    }
+
     #[inline(always)]
     fn conv6(lhs: [T; 6], rhs: [U; 6], output: &mut [V]) {
         conv_n_recursive::<6, 3, T, U, V, _, _>(
@@ -182,7 +541,13 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
 
     #[inline(always)]
     fn negacyclic_conv6(lhs: [T; 6], rhs: [U; 6], output: &mut [V]) {
-        negacyclic_conv_n_recursive::<6, 3, T, U, V, _>(lhs, rhs, output, Self::negacyclic_conv3)
+        negacyclic_conv_n_recursive::<6, 3, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv3,
+            Self::reduce_intermediate,
+        )
     }
 
     #[inline(always)]
@@ -196,9 +561,67 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
         )
     }
 
+    /// The two halves which `conv8` computes before recombining them:
+    /// `w_0 = lhs(x)rhs(x) mod x^4 - 1` and `w_1 = lhs(x)rhs(x) mod x^4 + 1`,
+    /// returned as `(w_0, w_1)`. By the CRT these determine the product
+    /// modulo x^8 - 1, whose coefficients `conv8` outputs as
+    /// `(w_0 + w_1)/2` followed by `(w_0 - w_1)/2`. Exposing them lets a
+    /// caller weight the cyclic and negacyclic parts differently.
+    #[inline(always)]
+    fn conv8_halves(lhs: [T; 8], rhs: [U; 8]) -> ([V; 4], [V; 4]) {
+        let mut w0 = [V::default(); 4];
+        let mut w1 = [V::default(); 4];
+        conv_n_halves::<8, 4, T, U, V, _, _>(
+            lhs,
+            rhs,
+            &mut w0,
+            &mut w1,
+            Self::conv4,
+            Self::negacyclic_conv4,
+        );
+        (w0, w1)
+    }
+
     #[inline(always)]
     fn negacyclic_conv8(lhs: [T; 8], rhs: [U; 8], output: &mut [V]) {
-        negacyclic_conv_n_recursive::<8, 4, T, U, V, _>(lhs, rhs, output, Self::negacyclic_conv4)
+        negacyclic_conv_n_recursive::<8, 4, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv4,
+            Self::reduce_intermediate,
+        )
+    }
+
+    #[inline(always)]
+    fn conv10(lhs: [T; 10], rhs: [U; 10], output: &mut [V]) {
+        conv_n_recursive::<10, 5, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::conv5,
+            Self::negacyclic_conv5,
+        )
+    }
+
+    #[inline(always)]
+    fn negacyclic_conv10(lhs: [T; 10], rhs: [U; 10], output: &mut [V]) {
+        negacyclic_conv_n_recursive::<10, 5, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv5,
+            Self::reduce_intermediate,
+        )
+    }
+
+    /// Same as `conv8` with `rhs = R::VALUE`, for a vector carried by
+    /// a type rather than passed as an argument. This just forwards to
+    /// `conv8`: the splits of `rhs` at each level of the recursion are
+    /// still computed on every call, unless the optimiser folds them.
+    #[inline(always)]
+    fn conv8_const<R: ConstVector<U, 8>>(lhs: [T; 8], output: &mut [V]) {
+        Self::conv8(lhs, R::VALUE, output)
     }
 
     #[inline(always)]
@@ -214,7 +637,13 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
 
     #[inline(always)]
     fn negacyclic_conv12(lhs: [T; 12], rhs: [U; 12], output: &mut [V]) {
-        negacyclic_conv_n_recursive::<12, 6, T, U, V, _>(lhs, rhs, output, Self::negacyclic_conv6)
+        negacyclic_conv_n_recursive::<12, 6, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv6,
+            Self::reduce_intermediate,
+        )
     }
 
     #[inline(always)]
@@ -228,9 +657,112 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
         )
     }
 
+    /// Same as `conv8`, for `rhs` which is symmetric (`rhs[i] ==
+    /// rhs[8 - i]` for `0 < i < 8`). Only half of the reductions of
+    /// `rhs` need computing, the rest follow by symmetry.
+    #[inline(always)]
+    fn conv8_symmetric(lhs: [T; 8], rhs: [U; 8], output: &mut [V]) {
+        conv_n_recursive_symmetric::<8, 4, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::conv4,
+            Self::negacyclic_conv4,
+        )
+    }
+
+    /// Same as `conv16`, for symmetric `rhs`; see `conv8_symmetric`.
+    #[inline(always)]
+    fn conv16_symmetric(lhs: [T; 16], rhs: [U; 16], output: &mut [V]) {
+        conv_n_recursive_symmetric::<16, 8, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::conv8_symmetric,
+            Self::negacyclic_conv8,
+        )
+    }
+
+    /// Same as `conv16` with `rhs = R::VALUE`; see `conv8_const`.
+    #[inline(always)]
+    fn conv16_const<R: ConstVector<U, 16>>(lhs: [T; 16], output: &mut [V]) {
+        Self::conv16(lhs, R::VALUE, output)
+    }
+
     #[inline(always)]
     fn negacyclic_conv16(lhs: [T; 16], rhs: [U; 16], output: &mut [V]) {
-        negacyclic_conv_n_recursive::<16, 8, T, U, V, _>(lhs, rhs, output, Self::negacyclic_conv8)
+        negacyclic_conv_n_recursive::<16, 8, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv8,
+            Self::reduce_intermediate,
+        )
+    }
+
+    /// Same as `conv8`, but taking slices, for code working with
+    /// slices whose length is only known at runtime.
+    ///
+    /// Panics unless `lhs`, `rhs` and `output` all have length 8.
+    #[inline(always)]
+    fn conv8_slice(lhs: &[T], rhs: &[U], output: &mut [V]) {
+        check_slice_lens(8, lhs.len(), rhs.len(), output.len());
+        Self::conv8(to_array(lhs), to_array(rhs), output)
+    }
+
+    /// Same as `conv8_slice`, for size 16.
+    #[inline(always)]
+    fn conv16_slice(lhs: &[T], rhs: &[U], output: &mut [V]) {
+        check_slice_lens(16, lhs.len(), rhs.len(), output.len());
+        Self::conv16(to_array(lhs), to_array(rhs), output)
+    }
+
+    /// Same as `negacyclic_conv8`, but taking slices; see
+    /// `conv8_slice`.
+    #[inline(always)]
+    fn negacyclic_conv8_slice(lhs: &[T], rhs: &[U], output: &mut [V]) {
+        check_slice_lens(8, lhs.len(), rhs.len(), output.len());
+        Self::negacyclic_conv8(to_array(lhs), to_array(rhs), output)
+    }
+
+    /// Same as `negacyclic_conv8_slice`, for size 16.
+    #[inline(always)]
+    fn negacyclic_conv16_slice(lhs: &[T], rhs: &[U], output: &mut [V]) {
+        check_slice_lens(16, lhs.len(), rhs.len(), output.len());
+        Self::negacyclic_conv16(to_array(lhs), to_array(rhs), output)
+    }
+
+    /// Convolution of size 20, e.g. for width 20 Poseidon over
+    /// BabyBear.
+    ///
+    /// There, the entries of the MDS matrix are too large for the
+    /// `i64` products of the small convolution, so the large
+    /// convolution of `monty-31/src/mds.rs` must be used. Its bounds
+    /// hold as for width 24: the inputs to the size 5 convolutions
+    /// are sums of at most 4 elements, so the products fed to the
+    /// Barrett reduction are below 20^2 * 2^62 < 2^80, and there is a
+    /// single negacyclic recombination step (in `negacyclic_conv10`),
+    /// so the outputs are below 3 * 2^50 < 2^55.
+    #[inline(always)]
+    fn conv20(lhs: [T; 20], rhs: [U; 20], output: &mut [V]) {
+        conv_n_recursive::<20, 10, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::conv10,
+            Self::negacyclic_conv10,
+        )
+    }
+
+    #[inline(always)]
+    fn negacyclic_conv20(lhs: [T; 20], rhs: [U; 20], output: &mut [V]) {
+        negacyclic_conv_n_recursive::<20, 10, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv10,
+            Self::reduce_intermediate,
+        )
     }
 
     #[inline(always)]
@@ -257,7 +789,24 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
 
     #[inline(always)]
     fn negacyclic_conv32(lhs: [T; 32], rhs: [U; 32], output: &mut [V]) {
-        negacyclic_conv_n_recursive::<32, 16, T, U, V, _>(lhs, rhs, output, Self::negacyclic_conv16)
+        negacyclic_conv_n_recursive::<32, 16, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::negacyclic_conv16,
+            Self::reduce_intermediate,
+        )
+    }
+
+    #[inline(always)]
+    fn conv40(lhs: [T; 40], rhs: [U; 40], output: &mut [V]) {
+        conv_n_recursive::<40, 20, T, U, V, _, _>(
+            lhs,
+            rhs,
+            output,
+            Self::conv20,
+            Self::negacyclic_conv20,
+        )
     }
 
     #[inline(always)]
@@ -270,6 +819,167 @@ pub trait Convolve<F, T: RngElt, U: RngElt, V: RngElt> {
             Self::negacyclic_conv32,
         )
     }
+
+    /// Same as `conv64`, but computes the two size 32 halves it
+    /// recombines, `conv32` and `negacyclic_conv32`, on two threads
+    /// with `join`, which pays off when the halves are large enough to
+    /// outweigh the cost of spawning a task. Without the `parallel`
+    /// feature of `p3-maybe-rayon` they're computed one after the
+    /// other. Either way the output is identical to that of `conv64`.
+    #[inline]
+    fn conv64_par(lhs: [T; 64], rhs: [U; 64], output: &mut [V])
+    where
+        T: Send,
+        U: Send,
+        V: Send,
+    {
+        let (lhs_pos, lhs_neg) = split_pos_neg::<64, 32, T>(lhs);
+        let (rhs_pos, rhs_neg) = split_pos_neg::<64, 32, U>(rhs);
+        let (left, right) = output.split_at_mut(32);
+        join(
+            || Self::negacyclic_conv32(lhs_neg, rhs_neg, left),
+            || Self::conv32(lhs_pos, rhs_pos, right),
+        );
+        crt_recombine(left, right);
+    }
+}
+
+/// The integer types used for a Karatsuba convolution over a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvStrategy {
+    /// Products and sums are accumulated in `i64`, reducing only at
+    /// the end, as done by the small convolutions of the 31-bit fields.
+    SmallI64,
+    /// Products are widened to `i128` and reduced in each dot
+    /// product, as done by the large convolutions of the 31-bit
+    /// fields, and by every convolution over a 64-bit field.
+    LargeI128,
+}
+
+/// The strategy for applying the circulant matrix with first column
+/// `first_col` over `F`: `SmallI64` if `F` has at most 31 bits and the
+/// entries of `first_col` are non-negative and pass
+/// `small_conv_is_safe`, and `LargeI128` otherwise. Over a larger field
+/// a single product of two elements already needs `i128`.
+///
+/// This is the predicate the 31-bit fields branch on when the matrix
+/// is only known at runtime, e.g. `apply_circulant_24_karat` and
+/// `Circulant16Col` in `p3-mersenne-31`. Their fixed MDS matrices of
+/// widths 8, 12 and 16 come out as `SmallI64`, and those of widths 32
+/// and 64, whose entries are as large as the field, as `LargeI128`.
+/// `apply_circulant_karat_slice`, which is generic over 64-bit fields,
+/// always uses `i128`.
+pub const fn convolution_strategy<F: PrimeField64>(first_col: &[i64]) -> ConvStrategy {
+    let mut sum = 0u64;
+    let mut i = 0;
+    while i < first_col.len() {
+        if first_col[i] < 0 {
+            return ConvStrategy::LargeI128;
+        }
+        sum = sum.saturating_add(first_col[i] as u64);
+        i += 1;
+    }
+    if F::ORDER_U64 < 1 << 32 && small_conv_is_safe(first_col.len(), sum) {
+        ConvStrategy::SmallI64
+    } else {
+        ConvStrategy::LargeI128
+    }
+}
+
+/// Whether a `ConvStrategy::SmallI64` convolution of width `n`, over
+/// a field of at most 31 bits, is free of overflow when the entries of
+/// the circulant matrix's first row (or column) sum to at most
+/// `max_row_sum` in absolute value.
+///
+/// The intermediate values of the convolution are bounded by
+/// `Sum(|lhs|) * Sum(|rhs|)`, where `Sum(|lhs|) < n * 2^31`, and this
+/// must be less than 2^63 for the `i64` accumulators.
+pub const fn small_conv_is_safe(n: usize, max_row_sum: u64) -> bool {
+    (n as u128) * (1 << 31) * (max_row_sum as u128) < 1 << 63
+}
+
+/// The sum of the absolute values of the entries of `row`, as needed
+/// by `small_conv_is_safe`.
+pub const fn row_abs_sum<const N: usize>(row: &[i64; N]) -> u64 {
+    let mut sum = 0;
+    let mut i = 0;
+    while i < N {
+        sum += row[i].unsigned_abs();
+        i += 1;
+    }
+    sum
+}
+
+/// The number of multiplications done by `Convolve::conv_n::<N>` for a
+/// strategy whose `BASE_CASE_LEN` is `base_case_len`, counting each
+/// term of each `parity_dot` as one multiplication.
+///
+/// This follows the recursion used by `Convolve`: a cyclic
+/// convolution of size `n` is a cyclic and a negacyclic convolution
+/// of size `n/2`, a negacyclic convolution of size `n` is three
+/// negacyclic convolutions of size `n/2`, and sizes 3, 4, 5 and 7 are
+/// written out explicitly, except that the negacyclic convolution of
+/// size 4 is split into three of size 2 when `base_case_len` is 2. It
+/// is useful for comparing the cost of different widths without
+/// benchmarking.
+///
+/// Panics if `n` is not a size supported by `conv_n`, or if
+/// `base_case_len` is neither 2 nor 4.
+pub const fn karat_mul_count(n: usize, base_case_len: usize) -> usize {
+    assert!(is_conv_n_size(n), "unsupported convolution size");
+    assert!(
+        base_case_len == 2 || base_case_len == 4,
+        "unsupported base case length"
+    );
+    cyclic_karat_mul_count(n, base_case_len)
+}
+
+/// `karat_mul_count` without the checks, for the sizes met in the
+/// recursion of a supported size.
+const fn cyclic_karat_mul_count(n: usize, base_case_len: usize) -> usize {
+    match n {
+        3 => 9,
+        4 => 8,
+        5 => 25,
+        7 => 49,
+        _ => {
+            cyclic_karat_mul_count(n / 2, base_case_len)
+                + negacyclic_karat_mul_count(n / 2, base_case_len)
+        }
+    }
+}
+
+/// The number of multiplications done by the negacyclic convolution
+/// of size `n`; see `karat_mul_count`.
+const fn negacyclic_karat_mul_count(n: usize, base_case_len: usize) -> usize {
+    match n {
+        2 => 4,
+        3 => 9,
+        4 if base_case_len == 4 => 16,
+        5 => 25,
+        7 => 49,
+        _ => 3 * negacyclic_karat_mul_count(n / 2, base_case_len),
+    }
+}
+
+/// Convert a slice whose length is known to be `N` to an array.
+#[inline(always)]
+fn to_array<T: Copy, const N: usize>(v: &[T]) -> [T; N] {
+    v.try_into().unwrap()
+}
+
+/// Panic unless the inputs and output of a slice convolution all have
+/// length `n`.
+#[inline(always)]
+fn check_slice_lens(n: usize, lhs_len: usize, rhs_len: usize, output_len: usize) {
+    assert!(
+        lhs_len == n && rhs_len == n && output_len == n,
+        "expected slices of length {}, got {}, {} and {}",
+        n,
+        lhs_len,
+        rhs_len,
+        output_len
+    );
 }
 
 /// Compute output(x) = lhs(x)rhs(x) mod x^N - 1.
@@ -288,12 +998,122 @@ fn conv_n_recursive<const N: usize, const HALF_N: usize, T, U, V, C, NC>(
     C: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
     NC: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
 {
+    let (left, right) = output.split_at_mut(HALF_N);
+    conv_n_halves::<N, HALF_N, T, U, V, C, NC>(
+        lhs,
+        rhs,
+        right,
+        left,
+        inner_conv,
+        inner_negacyclic_conv,
+    );
+    crt_recombine(left, right);
+}
+
+/// Split `v(x)` into `v(x) mod x^{N/2} - 1` and `v(x) mod x^{N/2} + 1`.
+#[inline(always)]
+fn split_pos_neg<const N: usize, const HALF_N: usize, T: RngElt>(
+    v: [T; N],
+) -> ([T; HALF_N], [T; HALF_N]) {
     debug_assert_eq!(2 * HALF_N, N);
     // NB: The compiler is smart enough not to initialise these arrays.
-    let mut lhs_pos = [T::default(); HALF_N]; // lhs_pos = lhs(x) mod x^{N/2} - 1
-    let mut lhs_neg = [T::default(); HALF_N]; // lhs_neg = lhs(x) mod x^{N/2} + 1
-    let mut rhs_pos = [U::default(); HALF_N]; // rhs_pos = rhs(x) mod x^{N/2} - 1
-    let mut rhs_neg = [U::default(); HALF_N]; // rhs_neg = rhs(x) mod x^{N/2} + 1
+    let mut pos = [T::default(); HALF_N];
+    let mut neg = [T::default(); HALF_N];
+    for i in 0..HALF_N {
+        let s = v[i];
+        let t = v[i + HALF_N];
+        pos[i] = s + t;
+        neg[i] = s - t;
+    }
+    (pos, neg)
+}
+
+/// Compute the two halves from which `conv_n_recursive` recombines
+/// lhs(x)rhs(x) mod x^N - 1, namely w0 = lhs(x)rhs(x) mod x^{N/2} - 1
+/// and w1 = lhs(x)rhs(x) mod x^{N/2} + 1.
+#[inline(always)]
+fn conv_n_halves<const N: usize, const HALF_N: usize, T, U, V, C, NC>(
+    lhs: [T; N],
+    rhs: [U; N],
+    w0: &mut [V],
+    w1: &mut [V],
+    inner_conv: C,
+    inner_negacyclic_conv: NC,
+) where
+    T: RngElt,
+    U: RngElt,
+    V: RngElt,
+    C: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+    NC: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+{
+    let (lhs_pos, lhs_neg) = split_pos_neg::<N, HALF_N, T>(lhs);
+    let (rhs_pos, rhs_neg) = split_pos_neg::<N, HALF_N, U>(rhs);
+
+    // w1 = lhs(x)rhs(x) mod x^{N/2} + 1
+    inner_negacyclic_conv(lhs_neg, rhs_neg, w1);
+
+    // w0 = lhs(x)rhs(x) mod x^{N/2} - 1
+    inner_conv(lhs_pos, rhs_pos, w0);
+}
+
+/// Recombine the two halves of a convolution computed via the CRT.
+///
+/// On entry `low` holds w_1 = lhs(x)rhs(x) mod x^{N/2} + 1 and `high`
+/// holds w_0 = lhs(x)rhs(x) mod x^{N/2} - 1. On exit they hold the
+/// low and high halves of lhs(x)rhs(x) mod x^N - 1, namely
+/// (w_0 + w_1)/2 and (w_0 - w_1)/2. The halving is an arithmetic
+/// shift, which is exact since w_0 + w_1 is always even; this is
+/// checked in debug builds, as an odd sum means the inputs weren't
+/// valid halves (e.g. a `parity_dot` which doesn't preserve the low
+/// bits), and the shift would silently truncate it.
+#[inline(always)]
+fn crt_recombine<T: RngElt>(low: &mut [T], high: &mut [T]) {
+    debug_assert_eq!(low.len(), high.len());
+    for (l, h) in low.iter_mut().zip(high) {
+        *l += *h; // w_0 + w_1
+        debug_assert!(is_even(*l), "w_0 + w_1 must be even");
+        *l >>= 1; // (w_0 + w_1)/2
+        *h -= *l; // (w_0 - w_1)/2
+    }
+}
+
+/// Whether `x` is even, i.e. halving it with `>>= 1` is exact.
+#[inline(always)]
+fn is_even<T: RngElt>(x: T) -> bool {
+    let mut half = x;
+    half >>= 1;
+    half + half == x
+}
+
+/// Whether `v[i] == v[N - i]` for all `0 < i < N`.
+fn is_symmetric<U: PartialEq, const N: usize>(v: &[U; N]) -> bool {
+    (1..N).all(|i| v[i] == v[N - i])
+}
+
+/// Same as `conv_n_recursive`, but for symmetric `rhs`.
+///
+/// If rhs[i] = rhs[N - i] then rhs(x) mod x^{N/2} - 1 is again
+/// symmetric, and rhs(x) mod x^{N/2} + 1 is antisymmetric, so only
+/// their first N/4 + 1 coefficients are computed directly.
+#[inline(always)]
+fn conv_n_recursive_symmetric<const N: usize, const HALF_N: usize, T, U, V, C, NC>(
+    lhs: [T; N],
+    rhs: [U; N],
+    output: &mut [V],
+    inner_conv: C,
+    inner_negacyclic_conv: NC,
+) where
+    T: RngElt,
+    U: RngElt,
+    V: RngElt,
+    C: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+    NC: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+{
+    debug_assert_eq!(2 * HALF_N, N);
+    let mut lhs_pos = [T::default(); HALF_N];
+    let mut lhs_neg = [T::default(); HALF_N];
+    let mut rhs_pos = [U::default(); HALF_N];
+    let mut rhs_neg = [U::default(); HALF_N];
 
     for i in 0..HALF_N {
         let s = lhs[i];
@@ -301,42 +1121,101 @@ fn conv_n_recursive<const N: usize, const HALF_N: usize, T, U, V, C, NC>(
 
         lhs_pos[i] = s + t;
         lhs_neg[i] = s - t;
+    }
 
+    for i in 0..=HALF_N / 2 {
         let s = rhs[i];
         let t = rhs[i + HALF_N];
 
         rhs_pos[i] = s + t;
         rhs_neg[i] = s - t;
     }
+    for i in HALF_N / 2 + 1..HALF_N {
+        rhs_pos[i] = rhs_pos[HALF_N - i];
+        rhs_neg[i] = -rhs_neg[HALF_N - i];
+    }
 
     let (left, right) = output.split_at_mut(HALF_N);
-
-    // left = w1 = lhs(x)rhs(x) mod x^{N/2} + 1
     inner_negacyclic_conv(lhs_neg, rhs_neg, left);
-
-    // right = w0 = lhs(x)rhs(x) mod x^{N/2} - 1
     inner_conv(lhs_pos, rhs_pos, right);
 
+    crt_recombine(left, right);
+}
+
+/// Compute reduce(lhs(x)rhs(x) mod x^N - 1) in the same way as
+/// `conv_n_recursive`, but reducing each coefficient as soon as the
+/// CRT combination produces it, in a single loop over the halves.
+#[inline(always)]
+fn apply_conv_n_recursive_fused<const N: usize, const HALF_N: usize, F, T, U, V, C, NC, R>(
+    lhs: [T; N],
+    rhs: [U; N],
+    inner_conv: C,
+    inner_negacyclic_conv: NC,
+    reduce: R,
+) -> [F; N]
+where
+    T: RngElt,
+    U: RngElt,
+    V: RngElt,
+    C: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+    NC: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+    F: Copy + Default,
+    R: Fn(V) -> F,
+{
+    debug_assert_eq!(2 * HALF_N, N);
+    let mut lhs_pos = [T::default(); HALF_N]; // lhs_pos = lhs(x) mod x^{N/2} - 1
+    let mut lhs_neg = [T::default(); HALF_N]; // lhs_neg = lhs(x) mod x^{N/2} + 1
+    let mut rhs_pos = [U::default(); HALF_N]; // rhs_pos = rhs(x) mod x^{N/2} - 1
+    let mut rhs_neg = [U::default(); HALF_N]; // rhs_neg = rhs(x) mod x^{N/2} + 1
+
+    for i in 0..HALF_N {
+        let s = lhs[i];
+        let t = lhs[i + HALF_N];
+
+        lhs_pos[i] = s + t;
+        lhs_neg[i] = s - t;
+
+        let s = rhs[i];
+        let t = rhs[i + HALF_N];
+
+        rhs_pos[i] = s + t;
+        rhs_neg[i] = s - t;
+    }
+
+    let mut w1 = [V::default(); HALF_N];
+    let mut w0 = [V::default(); HALF_N];
+    inner_negacyclic_conv(lhs_neg, rhs_neg, &mut w1);
+    inner_conv(lhs_pos, rhs_pos, &mut w0);
+
+    // The same steps as `crt_recombine`, but reducing each pair of
+    // coefficients as soon as they're computed.
+    let mut output = [F::default(); N];
+    let (low, high) = output.split_at_mut(HALF_N);
     for i in 0..HALF_N {
-        left[i] += right[i]; // w_0 + w_1
-        left[i] >>= 1; // (w_0 + w_1)/2
-        right[i] -= left[i]; // (w_0 - w_1)/2
+        let mut sum = w1[i] + w0[i]; // w_0 + w_1
+        debug_assert!(is_even(sum), "w_0 + w_1 must be even");
+        sum >>= 1; // (w_0 + w_1)/2
+        low[i] = reduce(sum);
+        high[i] = reduce(w0[i] - sum); // (w_0 - w_1)/2
     }
+    output
 }
 
 /// Compute output(x) = lhs(x)rhs(x) mod x^N + 1.
 /// Do this recursively using three negacyclic convolutions of size HALF_N = N/2.
 #[inline(always)]
-fn negacyclic_conv_n_recursive<const N: usize, const HALF_N: usize, T, U, V, NC>(
+fn negacyclic_conv_n_recursive<const N: usize, const HALF_N: usize, T, U, V, NC, R>(
     lhs: [T; N],
     rhs: [U; N],
     output: &mut [V],
     inner_negacyclic_conv: NC,
+    reduce_intermediate: R,
 ) where
     T: RngElt,
     U: RngElt,
     V: RngElt,
     NC: Fn([T; HALF_N], [U; HALF_N], &mut [V]),
+    R: Fn(V) -> V,
 {
     debug_assert_eq!(2 * HALF_N, N);
     // NB: The compiler is smart enough not to initialise these arrays.
@@ -381,8 +1260,1458 @@ fn negacyclic_conv_n_recursive<const N: usize, const HALF_N: usize, T, U, V, NC>
     }
 
     // Interleave even_s_conv and right in the output:
-    for i in 0..HALF_N {
-        output[2 * i] = even_s_conv[i];
-        output[2 * i + 1] = output[i + HALF_N];
+    let even_s_conv = even_s_conv.map(&reduce_intermediate);
+    let odd_s_conv: [V; HALF_N] = core::array::from_fn(|i| reduce_intermediate(right[i]));
+    interleave_slices(&even_s_conv, &odd_s_conv, &mut output[..N]);
+}
+
+/// Compute the cyclic convolution of `lhs` and `rhs`, that is the
+/// coefficients of lhs(x)rhs(x) mod x^N - 1, where `N` is the common
+/// length of the inputs.
+///
+/// This works for any `N`, using the same recursion as the fixed-size
+/// functions of `Convolve` while `N` can be halved, and convolving the
+/// remaining odd part (e.g. 3 for `N` = 12 or 24) directly, so it's
+/// fastest when `N` is a power of two times a small odd number. It
+/// allocates at each level and is not tuned for any particular size.
+/// No reduction is performed, so the caller must ensure that the
+/// result (roughly `N` products of an `lhs` and an `rhs` element) fits
+/// in `T`.
+///
+/// Panics if the lengths differ.
+pub fn conv_karat<T: SimpleInteger>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    assert_eq!(lhs.len(), rhs.len());
+    let mut output = vec![T::default(); lhs.len()];
+    conv_karat_generic(lhs, rhs, &mut output);
+    output
+}
+
+/// Compute the negacyclic convolution of `lhs` and `rhs`, that is the
+/// coefficients of lhs(x)rhs(x) mod x^N + 1, where `N` is the common
+/// length of the inputs. Unlike the fixed-size negacyclic convolutions
+/// of `Convolve`, this handles any `N`, e.g. for multiplication in the
+/// rings used by lattice schemes.
+///
+/// The same caveats as for `conv_karat` apply: it's fastest when `N`
+/// is a power of two times a small odd number, and the result must fit
+/// in `T`.
+///
+/// Panics if the lengths differ.
+pub fn negacyclic_conv_karat<T: SimpleInteger>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    assert_eq!(lhs.len(), rhs.len());
+    let mut output = vec![T::default(); lhs.len()];
+    signed_conv_karat_generic(lhs, rhs, &mut output);
+    output
+}
+
+/// Same as `negacyclic_conv_karat`, but writing the result to `output`
+/// and taking the temporary space the recursion needs from `scratch`,
+/// rather than allocating at each level, so that repeated calls, e.g.
+/// on a hot path, needn't allocate at all. `scratch` must hold at least
+/// `negacyclic_conv_karat_scratch_len(N)` elements. Its contents on
+/// entry don't matter, and are unspecified on return.
+///
+/// Panics if the lengths of `lhs`, `rhs` and `output` differ, or if
+/// `scratch` is too short.
+pub fn negacyclic_conv_karat_with_scratch<T: SimpleInteger>(
+    lhs: &[T],
+    rhs: &[T],
+    output: &mut [T],
+    scratch: &mut [T],
+) {
+    let n = lhs.len();
+    assert_eq!(rhs.len(), n);
+    assert_eq!(output.len(), n);
+    assert!(
+        scratch.len() >= negacyclic_conv_karat_scratch_len(n),
+        "scratch too short"
+    );
+    let (scratch_t, scratch_v) = scratch.split_at_mut(signed_conv_scratch_len_t(n));
+    signed_conv_karat_generic_with_scratch(lhs, rhs, output, scratch_t, scratch_v);
+}
+
+/// The length of the scratch space `negacyclic_conv_karat_with_scratch`
+/// needs for inputs of length `n`, which is less than `7 * n`.
+pub const fn negacyclic_conv_karat_scratch_len(n: usize) -> usize {
+    signed_conv_scratch_len_t(n) + signed_conv_scratch_len_v(n)
+}
+
+/// Compute the cyclic convolution of `lhs` and `rhs`, as for
+/// `conv_karat`, but accumulating the products in the wider type `V`.
+///
+/// The inputs are still added and subtracted in `T` as the recursion
+/// splits them, so at depth `d` they have been summed `2^d`-fold:
+/// `N * max|lhs[i]|` and `N * max|rhs[i]|` must fit in `T`, where `N`
+/// is the common length of the inputs. The result must fit in `V`.
+///
+/// Panics if the lengths differ.
+pub fn conv_karat_widening<T: WideningMul<V>, V: SimpleInteger>(lhs: &[T], rhs: &[T]) -> Vec<V> {
+    assert_eq!(lhs.len(), rhs.len());
+    let mut output = vec![V::default(); lhs.len()];
+    conv_karat_generic(lhs, rhs, &mut output);
+    output
+}
+
+/// Compute the cyclic convolution of `lhs` and `rhs` over `i64`,
+/// returning the exact integer result without any reduction.
+///
+/// With `lhs` as the input vector and `rhs` as the first column of a
+/// circulant matrix, this is the product of the matrix and the vector.
+///
+/// No intermediate value overflows provided that
+///
+/// N^2 * max|lhs[i]| * max|rhs[i]| < 2^63,
+///
+/// where `N` is the common length of the inputs: at recursion depth
+/// `d` the inputs have been summed `2^d`-fold while the length has
+/// shrunk to `N / 2^d`, so no intermediate value exceeds `N^2` times
+/// the product of the input bounds. Returns `None` if the inputs break
+/// this bound or if their lengths differ.
+pub fn conv_karat_i64(lhs: &[i64], rhs: &[i64]) -> Option<Vec<i64>> {
+    let n = lhs.len();
+    if rhs.len() != n {
+        return None;
+    }
+    let max_abs = |v: &[i64]| v.iter().map(|x| x.unsigned_abs()).max().unwrap_or(0) as u128;
+    // This can exceed even a u128 for large inputs, hence the checked multiplications.
+    let bound = (n as u128 * n as u128)
+        .checked_mul(max_abs(lhs))
+        .and_then(|b| b.checked_mul(max_abs(rhs)))?;
+    if bound >= 1 << 63 {
+        return None;
+    }
+    let mut output = vec![0; n];
+    conv_karat_generic(lhs, rhs, &mut output);
+    Some(output)
+}
+
+/// Compute the cyclic convolution of two vectors of length 8 over
+/// `i64`, with the same Karatsuba decomposition as `Convolve::conv8`,
+/// but as a `const fn`.
+///
+/// This makes it possible to check a circulant matrix against a known
+/// output at compile time, e.g.
+/// `const _: () = assert!(conv8_i64(input, first_col)[0] == expected);`,
+/// so that a bad constant is caught before the binary runs. No
+/// reduction is performed; as for `conv_karat_i64`, nothing overflows
+/// provided that 64 * max|lhs[i]| * max|rhs[i]| < 2^63.
+pub const fn conv8_i64(lhs: [i64; 8], rhs: [i64; 8]) -> [i64; 8] {
+    let mut lhs_pos = [0; 4]; // lhs_pos = lhs(x) mod x^4 - 1
+    let mut lhs_neg = [0; 4]; // lhs_neg = lhs(x) mod x^4 + 1
+    let mut rhs_pos = [0; 4]; // rhs_pos = rhs(x) mod x^4 - 1
+    let mut rhs_neg = [0; 4]; // rhs_neg = rhs(x) mod x^4 + 1
+    let mut i = 0;
+    while i < 4 {
+        lhs_pos[i] = lhs[i] + lhs[i + 4];
+        lhs_neg[i] = lhs[i] - lhs[i + 4];
+        rhs_pos[i] = rhs[i] + rhs[i + 4];
+        rhs_neg[i] = rhs[i] - rhs[i + 4];
+        i += 1;
+    }
+
+    let w0 = conv4_i64(lhs_pos, rhs_pos);
+    let w1 = negacyclic_conv4_i64(lhs_neg, rhs_neg);
+
+    let mut output = [0; 8];
+    let mut i = 0;
+    while i < 4 {
+        debug_assert!((w0[i] + w1[i]) & 1 == 0);
+        output[i] = (w0[i] + w1[i]) >> 1; // (w_0 + w_1)/2
+        output[i + 4] = w0[i] - output[i]; // (w_0 - w_1)/2
+        i += 1;
+    }
+    output
+}
+
+/// `Convolve::conv4` over `i64`, for `conv8_i64`.
+const fn conv4_i64(lhs: [i64; 4], rhs: [i64; 4]) -> [i64; 4] {
+    let u_p = [lhs[0] + lhs[2], lhs[1] + lhs[3]];
+    let u_m = [lhs[0] - lhs[2], lhs[1] - lhs[3]];
+    let v_p = [rhs[0] + rhs[2], rhs[1] + rhs[3]];
+    let v_m = [rhs[0] - rhs[2], rhs[1] - rhs[3]];
+
+    let w0 = [
+        u_p[0] * v_p[0] + u_p[1] * v_p[1],
+        u_p[0] * v_p[1] + u_p[1] * v_p[0],
+    ];
+    let w1 = [
+        u_m[0] * v_m[0] - u_m[1] * v_m[1],
+        u_m[0] * v_m[1] + u_m[1] * v_m[0],
+    ];
+
+    debug_assert!((w0[0] + w1[0]) & 1 == 0 && (w0[1] + w1[1]) & 1 == 0);
+    let lo = [(w0[0] + w1[0]) >> 1, (w0[1] + w1[1]) >> 1];
+    [lo[0], lo[1], w0[0] - lo[0], w0[1] - lo[1]]
+}
+
+/// `Convolve::negacyclic_conv4` over `i64`, for `conv8_i64`.
+const fn negacyclic_conv4_i64(lhs: [i64; 4], rhs: [i64; 4]) -> [i64; 4] {
+    let mut output = [0; 4];
+    let mut i = 0;
+    while i < 4 {
+        let mut j = 0;
+        while j < 4 {
+            if j <= i {
+                output[i] += lhs[j] * rhs[i - j];
+            } else {
+                output[i] -= lhs[j] * rhs[4 + i - j];
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    output
+}
+
+/// Compute the cyclic convolution of `lhs` and `rhs` directly from the
+/// definition, with the output coefficients computed in parallel.
+///
+/// This does `N^2` multiplications, so for large `N` it is far slower
+/// than `conv_karat` in total, but it neither recurses nor allocates
+/// anything besides its output, and is simple enough to serve as a
+/// reference. It works for any length `N`. As for `conv_karat`, the
+/// result must fit in `T`.
+///
+/// Panics if the lengths differ.
+pub fn conv_naive_par<T: SimpleInteger + Send + Sync>(lhs: &[T], rhs: &[T]) -> Vec<T> {
+    let n = lhs.len();
+    assert_eq!(rhs.len(), n);
+    (0..n)
+        .into_par_iter()
+        .map(|i| {
+            (0..n).fold(T::default(), |acc, j| {
+                acc + lhs[j] * rhs[if j <= i { i - j } else { n + i - j }]
+            })
+        })
+        .collect()
+}
+
+/// Multiply `input` by the circulant matrix whose first row is
+/// `first_row`, for a width only known at runtime.
+///
+/// Widths supported by `Convolve::conv_n` (3, 4, 5, 6, 7, 8, 10, 12,
+/// 16, 20, 24, 32, 40 and 64) use the corresponding Karatsuba convolution over
+/// `i128`, provided that every entry of `first_row` is less than
+/// 2^50 in absolute value, which keeps all intermediate values below
+/// 2^127. Any other width or larger entries fall back to the naive
+/// quadratic algorithm in `F`, so the result is always correct.
+///
+/// Panics if `input` and `first_row` have different lengths.
+pub fn apply_circulant_karat_slice<F: PrimeField64>(input: &[F], first_row: &[i64]) -> Vec<F> {
+    let n = input.len();
+    assert_eq!(first_row.len(), n);
+
+    let conv: Option<fn(&[i128], &[i128], &mut [i128])> = match n {
+        3 => Some(conv_slice::<3>),
+        4 => Some(conv_slice::<4>),
+        5 => Some(conv_slice::<5>),
+        6 => Some(conv_slice::<6>),
+        7 => Some(conv_slice::<7>),
+        8 => Some(conv_slice::<8>),
+        10 => Some(conv_slice::<10>),
+        12 => Some(conv_slice::<12>),
+        16 => Some(conv_slice::<16>),
+        20 => Some(conv_slice::<20>),
+        24 => Some(conv_slice::<24>),
+        32 => Some(conv_slice::<32>),
+        40 => Some(conv_slice::<40>),
+        64 => Some(conv_slice::<64>),
+        _ => None,
+    };
+    let small_row = first_row
+        .iter()
+        .all(|x| x.unsigned_abs() < I128_CONV_MAX_ENTRY);
+
+    match conv {
+        Some(conv) if small_row => {
+            let lhs: Vec<i128> = input.iter().map(|x| x.as_canonical_u64() as i128).collect();
+            // The convolutions take the first column of the matrix.
+            let rhs: Vec<i128> = (0..n).map(|i| first_row[(n - i) % n] as i128).collect();
+            let mut output = vec![0; n];
+            conv(&lhs, &rhs, &mut output);
+            output
+                .into_iter()
+                .map(|z| F::from_canonical_u64(z.rem_euclid(F::ORDER_U64 as i128) as u64))
+                .collect()
+        }
+        _ => {
+            let row: Vec<F> = first_row.iter().map(|&x| signed_to_field(x)).collect();
+            (0..n)
+                .map(|i| (0..n).map(|j| row[(n + j - i) % n] * input[j]).sum())
+                .collect()
+        }
+    }
+}
+
+/// The first column of the size 16 circulant matrix with first row
+/// `row`, with each entry reduced to less than P in absolute value so
+/// that it can be used with `LargeConvolvePrimeField32`.
+fn circulant_16_large_col<F: PrimeField32>(row: &[i64; 16]) -> [i64; 16] {
+    first_row_to_first_col(row).map(|x| x % F::ORDER_U32 as i64)
+}
+
+/// Entries of a first row below this in absolute value keep the `i128`
+/// convolutions of `apply_circulant_karat_slice` from overflowing.
+const I128_CONV_MAX_ENTRY: u64 = 1 << 50;
+
+/// Whether `conv_n` supports convolutions of size `n`.
+pub(crate) const fn is_conv_n_size(n: usize) -> bool {
+    matches!(n, 3..=8 | 10 | 12 | 16 | 20 | 24 | 32 | 40 | 64)
+}
+
+/// Same as `apply_circulant_karat_slice` for a fixed width `N`, using
+/// `conv_n` on arrays rather than allocating, under the same bounds.
+/// Returns `None` when `N` or the entries of `first_row` are outside
+/// them, in which case the caller falls back to another algorithm.
+pub(crate) fn apply_circulant_karat_i128<F: PrimeField64, const N: usize>(
+    input: [F; N],
+    first_row: [i64; N],
+) -> Option<[F; N]> {
+    if !is_conv_n_size(N)
+        || first_row
+            .iter()
+            .any(|x| x.unsigned_abs() >= I128_CONV_MAX_ENTRY)
+    {
+        return None;
+    }
+    let lhs = input.map(|x| x.as_canonical_u64() as i128);
+    let rhs = first_row_to_first_col(&first_row).map(|x| x as i128);
+    let mut output = [0; N];
+    I128Convolve::conv_n::<N>(lhs, rhs, &mut output);
+    Some(output.map(|z| F::from_canonical_u64(z.rem_euclid(F::ORDER_U64 as i128) as u64)))
+}
+
+/// Multiply `input`, a vector of pairs such as the (real, imaginary)
+/// coordinates of elements of a quadratic extension, by the real
+/// circulant matrix whose first row is `row`.
+///
+/// This is two independent size 16 convolutions, one per coordinate,
+/// done with `LargeConvolvePrimeField32`. The entries of `row` are
+/// reduced modulo P once beforehand, so they can be any `i64`.
+pub fn apply_circulant_16_karat_pairs<F: PrimeField32>(
+    input: [[F; 2]; 16],
+    row: [i64; 16],
+) -> [[F; 2]; 16] {
+    let col = circulant_16_large_col::<F>(&row);
+    let re = <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::apply(
+        input.map(|x| x[0]),
+        col,
+        <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::conv16,
+    );
+    let im = <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::apply(
+        input.map(|x| x[1]),
+        col,
+        <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::conv16,
+    );
+    core::array::from_fn(|i| [re[i], im[i]])
+}
+
+/// Multiply each row of a 16-column matrix stored column-major, i.e. as
+/// its 16 columns, by the circulant matrix whose first row is `row`, in
+/// place and without transposing the matrix.
+///
+/// The first column of the matrix is computed once, with its entries
+/// reduced modulo P so that `row` can be any `i64`. Each row is then
+/// gathered from the columns, multiplied by a size 16 convolution with
+/// `LargeConvolvePrimeField32`, and scattered back.
+///
+/// Panics if the columns have different lengths.
+pub fn apply_circulant_16_karat_colmajor<F: PrimeField32>(cols: &mut [Vec<F>; 16], row: [i64; 16]) {
+    let height = cols[0].len();
+    assert!(
+        cols.iter().all(|col| col.len() == height),
+        "columns have different lengths"
+    );
+    let col = circulant_16_large_col::<F>(&row);
+    for r in 0..height {
+        let input: [F; 16] = core::array::from_fn(|c| cols[c][r]);
+        let output = <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::apply(
+            input,
+            col,
+            <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::conv16,
+        );
+        for (col, y) in cols.iter_mut().zip(output) {
+            col[r] = y;
+        }
+    }
+}
+
+/// Instantiate convolution for "large" RHS vectors over any prime
+/// field of at most 31 bits, such as BabyBear or Mersenne31.
+///
+/// Here "large" means the elements of the RHS can be as big as the
+/// field characteristic P in absolute value, and the size N is <= 64.
+///
+/// This only relies on `PrimeField32`, reading the canonical
+/// representative of each input and reducing each dot product modulo
+/// P * 2^10, which preserves its value both modulo P and modulo 2^10,
+/// the latter being what the divisions by 2 in the recombination steps
+/// need. The field-specific convolutions, such as Barrett reduction in
+/// Monty form for BabyBear, avoid the conversions and the `i128`
+/// remainder, so they are faster where they exist.
+///
+/// Bounds: the inputs read are below P < 2^31, so the dot products are
+/// below N^2 * 2^62 <= 2^74 and fit an `i128`. After reduction they are
+/// below P * 2^10 < 2^41, and the at most three negacyclic
+/// recombinations for N = 64 grow them by at most 3^3 < 2^5, so every
+/// value handled by `reduce` is below 2^46 in absolute value.
+#[derive(Clone, Debug, Default)]
+pub struct LargeConvolvePrimeField32;
+
+impl<F: PrimeField32> Convolve<F, i64, i64, i64> for LargeConvolvePrimeField32 {
+    #[inline(always)]
+    fn read(input: F) -> i64 {
+        input.as_canonical_u32() as i64
+    }
+
+    #[inline]
+    fn parity_dot<const N: usize>(u: [i64; N], v: [i64; N]) -> i64 {
+        let dp: i128 = u.iter().zip(v).map(|(&x, y)| x as i128 * y as i128).sum();
+        dp.rem_euclid((F::ORDER_U32 as i128) << 10) as i64
+    }
+
+    #[inline]
+    fn reduce(z: i64) -> F {
+        debug_assert!(z.unsigned_abs() < 1 << 46);
+        F::from_canonical_u32(z.rem_euclid(F::ORDER_U32 as i64) as u32)
+    }
+}
+
+/// Multiply `input` by the size 32 circulant matrix with first row
+/// `first_row`, over any `PrimeField32`, using
+/// `LargeConvolvePrimeField32`. The entries of `first_row` must be less
+/// than P in absolute value.
+pub fn apply_circulant_32_karat<F: PrimeField32>(input: [F; 32], first_row: [i64; 32]) -> [F; 32] {
+    <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::apply(
+        input,
+        first_row_to_first_col(&first_row),
+        <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::conv32,
+    )
+}
+
+/// Same as `apply_circulant_32_karat`, for size 64.
+pub fn apply_circulant_64_karat<F: PrimeField32>(input: [F; 64], first_row: [i64; 64]) -> [F; 64] {
+    <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::apply(
+        input,
+        first_row_to_first_col(&first_row),
+        <LargeConvolvePrimeField32 as Convolve<F, i64, i64, i64>>::conv64,
+    )
+}
+
+/// Convolution over `i128`, without any reduction, used by
+/// `apply_circulant_karat_slice` and `apply_circulant_karat_i128`.
+struct I128Convolve;
+
+impl Convolve<i128, i128, i128, i128> for I128Convolve {
+    #[inline(always)]
+    fn read(input: i128) -> i128 {
+        input
+    }
+
+    #[inline(always)]
+    fn parity_dot<const N: usize>(lhs: [i128; N], rhs: [i128; N]) -> i128 {
+        dot_product(lhs, rhs)
+    }
+
+    #[inline(always)]
+    fn reduce(z: i128) -> i128 {
+        z
+    }
+}
+
+fn conv_slice<const N: usize>(lhs: &[i128], rhs: &[i128], output: &mut [i128]) {
+    I128Convolve::conv_n::<N>(to_array(lhs), to_array(rhs), output)
+}
+
+/// Compute output(x) = lhs(x)rhs(x) mod x^n - 1 for any length n,
+/// accumulating in `V`. The recursion mirrors `conv_n_recursive`,
+/// halving n while it's even and larger than 6; the remaining lengths,
+/// e.g. 3 and 6 for widths 12 and 24, are convolved directly.
+fn conv_karat_generic<T: WideningMul<V>, V: SimpleInteger>(lhs: &[T], rhs: &[T], output: &mut [V]) {
+    let n = lhs.len();
+    debug_assert_eq!(rhs.len(), n);
+    debug_assert_eq!(output.len(), n);
+    match n {
+        1 => output[0] = lhs[0].widening_mul(rhs[0]),
+        2 => {
+            output[0] = lhs[0].widening_mul(rhs[0]) + lhs[1].widening_mul(rhs[1]);
+            output[1] = lhs[0].widening_mul(rhs[1]) + lhs[1].widening_mul(rhs[0]);
+        }
+        _ if is_karat_base_case(n) => conv_naive_generic(lhs, rhs, output, false),
+        _ => {
+            let half = n / 2;
+            let (lhs_lo, lhs_hi) = lhs.split_at(half);
+            let (rhs_lo, rhs_hi) = rhs.split_at(half);
+
+            // lhs(x) mod x^{n/2} - 1 and lhs(x) mod x^{n/2} + 1, similarly for rhs.
+            let lhs_pos: Vec<T> = lhs_lo.iter().zip(lhs_hi).map(|(&s, &t)| s + t).collect();
+            let lhs_neg: Vec<T> = lhs_lo.iter().zip(lhs_hi).map(|(&s, &t)| s - t).collect();
+            let rhs_pos: Vec<T> = rhs_lo.iter().zip(rhs_hi).map(|(&s, &t)| s + t).collect();
+            let rhs_neg: Vec<T> = rhs_lo.iter().zip(rhs_hi).map(|(&s, &t)| s - t).collect();
+
+            let (left, right) = output.split_at_mut(half);
+            signed_conv_karat_generic(&lhs_neg, &rhs_neg, left);
+            conv_karat_generic(&lhs_pos, &rhs_pos, right);
+
+            crt_recombine(left, right);
+        }
+    }
+}
+
+/// Compute output(x) = lhs(x)rhs(x) mod x^n + 1 for any length n,
+/// accumulating in `V`. The recursion mirrors
+/// `negacyclic_conv_n_recursive`, with the same base cases as
+/// `conv_karat_generic`.
+fn signed_conv_karat_generic<T: WideningMul<V>, V: SimpleInteger>(
+    lhs: &[T],
+    rhs: &[T],
+    output: &mut [V],
+) {
+    let n = lhs.len();
+    let mut scratch_t = vec![T::default(); signed_conv_scratch_len_t(n)];
+    let mut scratch_v = vec![V::default(); signed_conv_scratch_len_v(n)];
+    signed_conv_karat_generic_with_scratch(lhs, rhs, output, &mut scratch_t, &mut scratch_v);
+}
+
+/// Same as `signed_conv_karat_generic`, but taking the space for the
+/// split inputs from `scratch_t`, and for the partial results from
+/// `scratch_v`, which must hold at least `signed_conv_scratch_len_t(n)`
+/// and `signed_conv_scratch_len_v(n)` elements respectively.
+fn signed_conv_karat_generic_with_scratch<T: WideningMul<V>, V: SimpleInteger>(
+    lhs: &[T],
+    rhs: &[T],
+    output: &mut [V],
+    scratch_t: &mut [T],
+    scratch_v: &mut [V],
+) {
+    let n = lhs.len();
+    debug_assert_eq!(rhs.len(), n);
+    debug_assert_eq!(output.len(), n);
+    match n {
+        1 => output[0] = lhs[0].widening_mul(rhs[0]),
+        2 => {
+            output[0] = lhs[0].widening_mul(rhs[0]) - lhs[1].widening_mul(rhs[1]);
+            output[1] = lhs[0].widening_mul(rhs[1]) + lhs[1].widening_mul(rhs[0]);
+        }
+        _ if is_karat_base_case(n) => conv_naive_generic(lhs, rhs, output, true),
+        _ => {
+            let half = n / 2;
+            let (lhs_split, scratch_t) = scratch_t.split_at_mut(3 * half);
+            let (rhs_split, scratch_t) = scratch_t.split_at_mut(3 * half);
+            split_eom(lhs, lhs_split);
+            split_eom(rhs, rhs_split);
+            let (lhs_even, lhs_rest) = lhs_split.split_at(half);
+            let (lhs_odd, lhs_sum) = lhs_rest.split_at(half);
+            let (rhs_even, rhs_rest) = rhs_split.split_at(half);
+            let (rhs_odd, rhs_sum) = rhs_rest.split_at(half);
+
+            let (even_s_conv, scratch_v) = scratch_v.split_at_mut(half);
+            let (left, right) = output.split_at_mut(half);
+
+            signed_conv_karat_generic_with_scratch(
+                lhs_even,
+                rhs_even,
+                even_s_conv,
+                scratch_t,
+                scratch_v,
+            );
+            signed_conv_karat_generic_with_scratch(lhs_odd, rhs_odd, left, scratch_t, scratch_v);
+            signed_conv_karat_generic_with_scratch(lhs_sum, rhs_sum, right, scratch_t, scratch_v);
+
+            right[0] -= even_s_conv[0] + left[0];
+            even_s_conv[0] -= left[half - 1];
+
+            for i in 1..half {
+                right[i] -= even_s_conv[i] + left[i];
+                even_s_conv[i] += left[i - 1];
+            }
+
+            // The recursive calls are done with the rest of `scratch_v`, so it can hold a copy of
+            // `right` while the two halves are interleaved into `output`.
+            let odd_s_conv = &mut scratch_v[..half];
+            odd_s_conv.copy_from_slice(right);
+            interleave_slices(even_s_conv, odd_s_conv, output);
+        }
+    }
+}
+
+/// Whether `conv_karat_generic` and `signed_conv_karat_generic` convolve
+/// inputs of length `n` directly, rather than recursing.
+const fn is_karat_base_case(n: usize) -> bool {
+    n <= 6 || n % 2 == 1
+}
+
+/// The number of `T`s of scratch space `signed_conv_karat_generic_with_scratch`
+/// needs for inputs of length `n`: each level holds the three splits of
+/// both inputs while it recurses.
+const fn signed_conv_scratch_len_t(n: usize) -> usize {
+    if is_karat_base_case(n) {
+        0
+    } else {
+        3 * n + signed_conv_scratch_len_t(n / 2)
+    }
+}
+
+/// The number of `V`s of scratch space `signed_conv_karat_generic_with_scratch`
+/// needs for inputs of length `n`: each level holds one partial result
+/// while it recurses, and a copy of another afterwards.
+const fn signed_conv_scratch_len_v(n: usize) -> usize {
+    if is_karat_base_case(n) {
+        0
+    } else {
+        let half = n / 2;
+        let rest = signed_conv_scratch_len_v(half);
+        half + if rest > half { rest } else { half }
+    }
+}
+
+/// Compute output(x) = lhs(x)rhs(x) mod x^n - 1, or mod x^n + 1 if
+/// `negacyclic`, directly from the definition, as the base case of
+/// `conv_karat_generic` and `signed_conv_karat_generic`.
+fn conv_naive_generic<T: WideningMul<V>, V: SimpleInteger>(
+    lhs: &[T],
+    rhs: &[T],
+    output: &mut [V],
+    negacyclic: bool,
+) {
+    let n = lhs.len();
+    for (i, out) in output.iter_mut().enumerate() {
+        *out = (0..n).fold(V::default(), |acc, j| {
+            if j <= i {
+                acc + lhs[j].widening_mul(rhs[i - j])
+            } else if negacyclic {
+                acc - lhs[j].widening_mul(rhs[n + i - j])
+            } else {
+                acc + lhs[j].widening_mul(rhs[n + i - j])
+            }
+        });
+    }
+}
+
+/// Write the even-indexed elements of `v`, its odd-indexed elements,
+/// and the sums of each consecutive (even, odd) pair, one after the
+/// other, to `out`, which must be 3/2 times as long as `v`.
+fn split_eom<T: RngElt>(v: &[T], out: &mut [T]) {
+    let half = v.len() / 2;
+    debug_assert_eq!(out.len(), 3 * half);
+    let (even, rest) = out.split_at_mut(half);
+    let (odd, mix) = rest.split_at_mut(half);
+    for (i, pair) in v.chunks_exact(2).enumerate() {
+        even[i] = pair[0];
+        odd[i] = pair[1];
+        mix[i] = pair[0] + pair[1];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::array;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use p3_baby_bear::BabyBear;
+    use p3_goldilocks::Goldilocks;
+    use p3_mersenne_31::Mersenne31;
+    use rand::distributions::{Distribution, Standard};
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+    use crate::util::{apply_circulant, dot_product, first_row_to_first_col};
+
+    /// Convolution over plain integers, so that the convolutions can be
+    /// compared directly with their naive definitions.
+    struct IntegerConvolve;
+
+    impl Convolve<i64, i64, i64, i64> for IntegerConvolve {
+        fn read(input: i64) -> i64 {
+            input
+        }
+
+        fn parity_dot<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> i64 {
+            dot_product(lhs, rhs)
+        }
+
+        fn reduce(z: i64) -> i64 {
+            z
+        }
+    }
+
+    /// Same as `IntegerConvolve`, but with negacyclic convolutions of
+    /// size 2 at the bottom of the recursion.
+    struct IntegerConvolveBase2;
+
+    impl Convolve<i64, i64, i64, i64> for IntegerConvolveBase2 {
+        const BASE_CASE_LEN: usize = 2;
+
+        fn read(input: i64) -> i64 {
+            input
+        }
+
+        fn parity_dot<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> i64 {
+            dot_product(lhs, rhs)
+        }
+
+        fn reduce(z: i64) -> i64 {
+            z
+        }
+    }
+
+    static MUL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Same as `IntegerConvolve`, but counts the multiplications done
+    /// by `parity_dot`.
+    struct CountingConvolve<const BASE_CASE_LEN: usize>;
+
+    impl<const BASE_CASE_LEN: usize> Convolve<i64, i64, i64, i64> for CountingConvolve<BASE_CASE_LEN> {
+        const BASE_CASE_LEN: usize = BASE_CASE_LEN;
+
+        fn read(input: i64) -> i64 {
+            input
+        }
+
+        fn parity_dot<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> i64 {
+            MUL_COUNT.fetch_add(N, Ordering::Relaxed);
+            dot_product(lhs, rhs)
+        }
+
+        fn reduce(z: i64) -> i64 {
+            z
+        }
+    }
+
+    fn naive_conv<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> [i64; N] {
+        let mut output = [0; N];
+        for i in 0..N {
+            for j in 0..N {
+                output[(i + j) % N] += lhs[i] * rhs[j];
+            }
+        }
+        output
+    }
+
+    fn naive_negacyclic_conv<const N: usize>(lhs: [i64; N], rhs: [i64; N]) -> [i64; N] {
+        let mut output = [0; N];
+        for (i, &l) in lhs.iter().enumerate() {
+            for (j, &r) in rhs.iter().enumerate() {
+                let prod = l * r;
+                if i + j < N {
+                    output[i + j] += prod;
+                } else {
+                    output[i + j - N] -= prod;
+                }
+            }
+        }
+        output
+    }
+
+    fn rand_vec<const N: usize>() -> [i64; N] {
+        let mut rng = thread_rng();
+        array::from_fn(|_| rng.gen_range(-(1 << 20)..1 << 20))
+    }
+
+    fn check_conv_n<const N: usize>() {
+        let lhs = rand_vec::<N>();
+        let rhs = rand_vec::<N>();
+        let mut output = [0; N];
+        IntegerConvolve::conv_n(lhs, rhs, &mut output);
+        assert_eq!(output, naive_conv(lhs, rhs));
+    }
+
+    #[test]
+    fn conv_n_matches_naive() {
+        check_conv_n::<3>();
+        check_conv_n::<4>();
+        check_conv_n::<5>();
+        check_conv_n::<7>();
+        check_conv_n::<6>();
+        check_conv_n::<8>();
+        check_conv_n::<10>();
+        check_conv_n::<12>();
+        check_conv_n::<16>();
+        check_conv_n::<20>();
+        check_conv_n::<24>();
+        check_conv_n::<32>();
+        check_conv_n::<40>();
+        check_conv_n::<64>();
+    }
+
+    #[test]
+    fn crt_recombine_matches_definition() {
+        let mut rng = thread_rng();
+        for half in [2, 4, 8, 16, 32] {
+            let w1: Vec<i64> = (0..half)
+                .map(|_| rng.gen_range(-(1 << 40)..1 << 40))
+                .collect();
+            // w_0 and w_1 always have the same parity.
+            let w0: Vec<i64> = w1
+                .iter()
+                .map(|&x| x + 2 * rng.gen_range(-(1 << 40)..1 << 40))
+                .collect();
+            let (mut low, mut high) = (w1.clone(), w0.clone());
+            crt_recombine(&mut low, &mut high);
+            for i in 0..half {
+                assert_eq!(low[i], (w0[i] + w1[i]) / 2);
+                assert_eq!(high[i], (w0[i] - w1[i]) / 2);
+            }
+        }
+    }
+
+    fn check_karat_mul_count<const N: usize>() {
+        MUL_COUNT.store(0, Ordering::Relaxed);
+        let mut output = [0; N];
+        CountingConvolve::<4>::conv_n(rand_vec::<N>(), rand_vec::<N>(), &mut output);
+        assert_eq!(MUL_COUNT.load(Ordering::Relaxed), karat_mul_count(N, 4));
+
+        MUL_COUNT.store(0, Ordering::Relaxed);
+        CountingConvolve::<2>::conv_n(rand_vec::<N>(), rand_vec::<N>(), &mut output);
+        assert_eq!(MUL_COUNT.load(Ordering::Relaxed), karat_mul_count(N, 2));
+    }
+
+    #[test]
+    fn karat_mul_count_matches_conv_n() {
+        check_karat_mul_count::<3>();
+        check_karat_mul_count::<4>();
+        check_karat_mul_count::<5>();
+        check_karat_mul_count::<7>();
+        check_karat_mul_count::<6>();
+        check_karat_mul_count::<8>();
+        check_karat_mul_count::<10>();
+        check_karat_mul_count::<12>();
+        check_karat_mul_count::<16>();
+        check_karat_mul_count::<20>();
+        check_karat_mul_count::<24>();
+        check_karat_mul_count::<32>();
+        check_karat_mul_count::<40>();
+        check_karat_mul_count::<64>();
+
+        assert_eq!(karat_mul_count(16, 4), 72);
+        assert_eq!(karat_mul_count(32, 4), 216);
+        assert_eq!(karat_mul_count(64, 4), 648);
+        // The size 2 base case saves a quarter of each size 4 negacyclic
+        // convolution's multiplications.
+        assert_eq!(karat_mul_count(16, 2), 8 + 12 + 3 * 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported convolution size")]
+    fn karat_mul_count_rejects_size_14() {
+        karat_mul_count(14, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported convolution size")]
+    fn karat_mul_count_rejects_size_48() {
+        karat_mul_count(48, 4);
+    }
+
+    #[test]
+    fn convolution_strategy_by_column() {
+        for n in [8, 12, 16, 24, 32, 64] {
+            let small_col = vec![9; n];
+            assert_eq!(
+                convolution_strategy::<BabyBear>(&small_col),
+                ConvStrategy::SmallI64
+            );
+            assert_eq!(
+                convolution_strategy::<Mersenne31>(&small_col),
+                ConvStrategy::SmallI64
+            );
+            // A 64-bit field always needs `i128`.
+            assert_eq!(
+                convolution_strategy::<Goldilocks>(&small_col),
+                ConvStrategy::LargeI128
+            );
+
+            // Entries as large as the field are too large for `i64`.
+            let large_col = vec![(1 << 31) - 2; n];
+            assert_eq!(
+                convolution_strategy::<Mersenne31>(&large_col),
+                ConvStrategy::LargeI128
+            );
+            // As are negative entries, however small.
+            let mut negative_col = small_col;
+            negative_col[1] = -1;
+            assert_eq!(
+                convolution_strategy::<Mersenne31>(&negative_col),
+                ConvStrategy::LargeI128
+            );
+        }
+
+        // The boundary is that of `small_conv_is_safe`.
+        let mut col = [0; 16];
+        col[0] = 1 << 27;
+        col[1] = (1 << 27) - 1;
+        assert!(small_conv_is_safe(16, row_abs_sum(&col)));
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&col),
+            ConvStrategy::SmallI64
+        );
+        col[1] += 1;
+        assert!(!small_conv_is_safe(16, row_abs_sum(&col)));
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&col),
+            ConvStrategy::LargeI128
+        );
+    }
+
+    #[test]
+    fn conv_n_matches_conv12() {
+        let lhs = rand_vec::<12>();
+        let rhs = rand_vec::<12>();
+
+        let mut generic = [0; 12];
+        IntegerConvolve::conv_n(lhs, rhs, &mut generic);
+        let mut specific = [0; 12];
+        IntegerConvolve::conv12(lhs, rhs, &mut specific);
+
+        assert_eq!(generic, specific);
+        assert_eq!(IntegerConvolve::apply_circulant_karat(lhs, rhs), specific);
+    }
+
+    fn check_conv_karat<const N: usize>() {
+        let lhs = rand_vec::<N>();
+        let rhs = rand_vec::<N>();
+        assert_eq!(conv_karat(&lhs, &rhs), naive_conv(lhs, rhs));
+        assert_eq!(
+            negacyclic_conv_karat(&lhs, &rhs),
+            naive_negacyclic_conv(lhs, rhs)
+        );
+    }
+
+    #[test]
+    fn conv_karat_matches_naive() {
+        check_conv_karat::<1>();
+        check_conv_karat::<2>();
+        check_conv_karat::<4>();
+        check_conv_karat::<8>();
+        check_conv_karat::<16>();
+        check_conv_karat::<32>();
+        check_conv_karat::<64>();
+        check_conv_karat::<128>();
+    }
+
+    #[test]
+    fn conv_karat_matches_naive_non_power_of_two() {
+        check_conv_karat::<3>();
+        check_conv_karat::<5>();
+        check_conv_karat::<6>();
+        check_conv_karat::<12>();
+        check_conv_karat::<20>();
+        check_conv_karat::<24>();
+        check_conv_karat::<48>();
+    }
+
+    #[test]
+    fn small_conv_bounds() {
+        // The size 16 matrix of the 31-bit fields is far from the bound.
+        let row = [1, 1, 51, 1, 11, 17, 2, 1, 101, 63, 15, 2, 67, 22, 13, 3];
+        assert_eq!(row_abs_sum(&row), 371);
+        assert!(small_conv_is_safe(16, row_abs_sum(&row)));
+        assert_eq!(row_abs_sum(&[-3, 4]), 7);
+
+        // Doubling the width of a convolution which is just safe makes it unsafe.
+        assert!(small_conv_is_safe(64, (1 << 26) - 1));
+        assert!(!small_conv_is_safe(64, 1 << 26));
+        assert!(!small_conv_is_safe(128, (1 << 26) - 1));
+    }
+
+    #[test]
+    fn conv8_halves_recombine_to_conv8() {
+        let lhs = rand_vec::<8>();
+        let rhs = rand_vec::<8>();
+
+        let (mut w0, mut w1) = IntegerConvolve::conv8_halves(lhs, rhs);
+        let mut expected_w0 = [0; 4];
+        IntegerConvolve::conv4(
+            array::from_fn(|i| lhs[i] + lhs[i + 4]),
+            array::from_fn(|i| rhs[i] + rhs[i + 4]),
+            &mut expected_w0,
+        );
+        assert_eq!(w0, expected_w0);
+
+        crt_recombine(&mut w1, &mut w0);
+        let mut expected = [0; 8];
+        IntegerConvolve::conv8(lhs, rhs, &mut expected);
+        assert_eq!([w1, w0].concat(), expected);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "w_0 + w_1 must be even")]
+    fn crt_recombine_rejects_odd_sum() {
+        // Not the halves of any product, as w_0 and w_1 differ in parity.
+        let mut low = [1i64, 2];
+        let mut high = [2i64, 2];
+        crt_recombine(&mut low, &mut high);
+    }
+
+    #[test]
+    fn conv64_par_matches_conv64() {
+        let lhs = rand_vec::<64>();
+        let rhs = rand_vec::<64>();
+        let mut expected = [0; 64];
+        IntegerConvolve::conv64(lhs, rhs, &mut expected);
+        let mut output = [0; 64];
+        IntegerConvolve::conv64_par(lhs, rhs, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    fn check_negacyclic_conv_karat<const N: usize>() {
+        // Schoolbook multiplication, then reduction modulo x^N + 1.
+        let lhs = rand_vec::<N>();
+        let rhs = rand_vec::<N>();
+        let mut expected = [0; N];
+        for (i, &l) in lhs.iter().enumerate() {
+            for (j, &r) in rhs.iter().enumerate() {
+                let prod = l * r;
+                if i + j < N {
+                    expected[i + j] += prod;
+                } else {
+                    expected[i + j - N] -= prod;
+                }
+            }
+        }
+        assert_eq!(negacyclic_conv_karat(&lhs, &rhs), expected);
+    }
+
+    #[test]
+    fn negacyclic_conv_karat_matches_schoolbook() {
+        check_negacyclic_conv_karat::<8>();
+        check_negacyclic_conv_karat::<16>();
+        check_negacyclic_conv_karat::<32>();
+        check_negacyclic_conv_karat::<64>();
+    }
+
+    #[test]
+    fn negacyclic_conv_karat_matches_schoolbook_non_power_of_two() {
+        check_negacyclic_conv_karat::<3>();
+        check_negacyclic_conv_karat::<6>();
+        check_negacyclic_conv_karat::<12>();
+        check_negacyclic_conv_karat::<24>();
+    }
+
+    fn check_negacyclic_conv_karat_with_scratch<const N: usize>() {
+        let lhs = rand_vec::<N>();
+        let rhs = rand_vec::<N>();
+        let scratch_len = negacyclic_conv_karat_scratch_len(N);
+        assert!(scratch_len < 7 * N);
+        // The scratch space's initial contents don't matter, so reuse it dirty.
+        let mut scratch = vec![i64::MAX; scratch_len];
+        for _ in 0..2 {
+            let mut output = [0; N];
+            negacyclic_conv_karat_with_scratch(&lhs, &rhs, &mut output, &mut scratch);
+            assert_eq!(output, naive_negacyclic_conv(lhs, rhs));
+        }
+    }
+
+    #[test]
+    fn negacyclic_conv_karat_with_scratch_matches_naive() {
+        check_negacyclic_conv_karat_with_scratch::<1>();
+        check_negacyclic_conv_karat_with_scratch::<4>();
+        check_negacyclic_conv_karat_with_scratch::<12>();
+        check_negacyclic_conv_karat_with_scratch::<16>();
+        check_negacyclic_conv_karat_with_scratch::<24>();
+        check_negacyclic_conv_karat_with_scratch::<64>();
+        check_negacyclic_conv_karat_with_scratch::<128>();
+    }
+
+    #[test]
+    #[should_panic(expected = "scratch too short")]
+    fn negacyclic_conv_karat_with_scratch_rejects_short_scratch() {
+        let mut scratch = vec![0; negacyclic_conv_karat_scratch_len(16) - 1];
+        negacyclic_conv_karat_with_scratch(&[1i64; 16], &[1; 16], &mut [0; 16], &mut scratch);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negacyclic_conv_karat_rejects_mismatched_lengths() {
+        negacyclic_conv_karat(&[1i64; 8], &[1i64; 4]);
+    }
+
+    #[test]
+    fn conv_karat_widening_matches_conv_karat() {
+        // Where the i64 path is valid, both must agree.
+        let lhs = rand_vec::<64>();
+        let rhs = rand_vec::<64>();
+        let narrow: Vec<i128> = conv_karat(&lhs, &rhs).into_iter().map(i128::from).collect();
+        assert_eq!(conv_karat_widening::<i64, i128>(&lhs, &rhs), narrow);
+
+        // With inputs near 2^50, the products alone overflow an i64.
+        let mut rng = thread_rng();
+        let lhs: [i64; 32] = array::from_fn(|_| rng.gen_range(-(1 << 50)..1 << 50));
+        let rhs: [i64; 32] = array::from_fn(|_| rng.gen_range(-(1 << 50)..1 << 50));
+        let wide_lhs = lhs.map(i128::from);
+        let wide_rhs = rhs.map(i128::from);
+        assert_eq!(
+            conv_karat_widening::<i64, i128>(&lhs, &rhs),
+            conv_karat(&wide_lhs, &wide_rhs)
+        );
+    }
+
+    #[test]
+    fn conv_naive_par_matches_conv_karat() {
+        let lhs = rand_vec::<64>();
+        let rhs = rand_vec::<64>();
+        assert_eq!(conv_naive_par(&lhs, &rhs), conv_karat(&lhs, &rhs));
+
+        let lhs = rand_vec::<12>();
+        let rhs = rand_vec::<12>();
+        assert_eq!(conv_naive_par(&lhs, &rhs), naive_conv(lhs, rhs));
+    }
+
+    #[test]
+    #[should_panic]
+    fn conv_karat_rejects_mismatched_lengths() {
+        conv_karat(&[1i64; 8], &[1i64; 4]);
+    }
+
+    #[test]
+    fn conv_karat_i64_at_bound() {
+        // With N = 16, the bound is 2^8 * A * B < 2^63.
+        const A: i64 = 1 << 27;
+        const B: i64 = (1 << 28) - 1;
+        let mut rng = thread_rng();
+        let mut rand_signs =
+            |x: i64| -> Vec<i64> { (0..16).map(|_| if rng.gen() { x } else { -x }).collect() };
+        let lhs = rand_signs(A);
+        let rhs = rand_signs(B);
+
+        let wide = |v: &[i64]| -> Vec<i128> { v.iter().map(|&x| x as i128).collect() };
+        let expected = conv_naive_par(&wide(&lhs), &wide(&rhs));
+        let output = conv_karat_i64(&lhs, &rhs).unwrap();
+        assert_eq!(wide(&output), expected);
+
+        // One more in a single entry of `rhs` reaches the bound.
+        let mut over = rhs.clone();
+        over[3] = if over[3] < 0 { -B - 1 } else { B + 1 };
+        assert_eq!(conv_karat_i64(&lhs, &over), None);
+    }
+
+    #[test]
+    fn conv_karat_i64_non_power_of_two() {
+        let lhs = rand_vec::<24>();
+        let rhs = rand_vec::<24>();
+        assert_eq!(
+            conv_karat_i64(&lhs, &rhs).unwrap(),
+            naive_conv(lhs, rhs).to_vec()
+        );
+        assert_eq!(conv_karat_i64(&[1; 8], &[1; 4]), None);
+    }
+
+    // Checked when the tests are compiled, not when they run.
+    const CONV8_I64_OUTPUT: [i64; 8] =
+        conv8_i64([1, 2, 3, 4, 5, 6, 7, 8], [7, 1, 3, 8, 8, 3, 4, 9]);
+    const _: () = {
+        let expected = [166, 201, 220, 199, 178, 197, 208, 179];
+        let mut i = 0;
+        while i < 8 {
+            assert!(CONV8_I64_OUTPUT[i] == expected[i]);
+            i += 1;
+        }
+    };
+
+    #[test]
+    fn conv8_i64_matches_conv8() {
+        for _ in 0..100 {
+            let lhs = rand_vec::<8>();
+            let rhs = rand_vec::<8>();
+            let mut output = [0; 8];
+            IntegerConvolve::conv8(lhs, rhs, &mut output);
+            assert_eq!(conv8_i64(lhs, rhs), output);
+            assert_eq!(conv8_i64(lhs, rhs), naive_conv(lhs, rhs));
+        }
+    }
+
+    struct Row8;
+    impl ConstVector<i64, 8> for Row8 {
+        const VALUE: [i64; 8] = [7, 1, 3, 8, 8, 3, 4, 9];
+    }
+
+    struct Row16;
+    impl ConstVector<i64, 16> for Row16 {
+        const VALUE: [i64; 16] = [1, 1, 51, 1, 11, 17, 2, 1, 101, 63, 15, 2, 67, 22, 13, 3];
+    }
+
+    #[test]
+    fn const_conv_matches_conv() {
+        let lhs = rand_vec::<8>();
+        let mut expected = [0; 8];
+        IntegerConvolve::conv8(lhs, Row8::VALUE, &mut expected);
+        let mut output = [0; 8];
+        IntegerConvolve::conv8_const::<Row8>(lhs, &mut output);
+        assert_eq!(output, expected);
+
+        let lhs = rand_vec::<16>();
+        let mut expected = [0; 16];
+        IntegerConvolve::conv16(lhs, Row16::VALUE, &mut expected);
+        let mut output = [0; 16];
+        IntegerConvolve::conv16_const::<Row16>(lhs, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    fn rand_symmetric_vec<const N: usize>() -> [i64; N] {
+        let mut v = rand_vec::<N>();
+        for i in N / 2 + 1..N {
+            v[i] = v[N - i];
+        }
+        v
+    }
+
+    #[test]
+    fn apply_circulant_20_karat_matches_naive() {
+        let lhs = rand_vec::<20>();
+        let row = rand_vec::<20>();
+        assert_eq!(
+            IntegerConvolve::apply_circulant_20_karat(lhs, row),
+            naive_conv(lhs, first_row_to_first_col(&row))
+        );
+
+        // Width 20 over BabyBear, with entries as large as the field.
+        check_apply_circulant_karat_slice::<BabyBear, 20>(BabyBear::ORDER_U64);
+    }
+
+    #[test]
+    fn apply_negacyclic_karat_matches_naive() {
+        let (lhs, rhs) = (rand_vec::<8>(), rand_vec::<8>());
+        assert_eq!(
+            IntegerConvolve::apply_negacyclic_8_karat(lhs, rhs),
+            naive_negacyclic_conv(lhs, rhs)
+        );
+        let (lhs, rhs) = (rand_vec::<16>(), rand_vec::<16>());
+        assert_eq!(
+            IntegerConvolve::apply_negacyclic_16_karat(lhs, rhs),
+            naive_negacyclic_conv(lhs, rhs)
+        );
+        let (lhs, rhs) = (rand_vec::<32>(), rand_vec::<32>());
+        assert_eq!(
+            IntegerConvolve::apply_negacyclic_32_karat(lhs, rhs),
+            naive_negacyclic_conv(lhs, rhs)
+        );
+    }
+
+    #[test]
+    fn symmetric_apply_matches_apply() {
+        let lhs = rand_vec::<8>();
+        let row = rand_symmetric_vec::<8>();
+        assert_eq!(
+            IntegerConvolve::apply_circulant_symmetric_8_karat(lhs, row),
+            IntegerConvolve::apply(lhs, first_row_to_first_col(&row), IntegerConvolve::conv8)
+        );
+
+        let lhs = rand_vec::<16>();
+        let row = rand_symmetric_vec::<16>();
+        assert_eq!(
+            IntegerConvolve::apply_circulant_symmetric_16_karat(lhs, row),
+            IntegerConvolve::apply(lhs, first_row_to_first_col(&row), IntegerConvolve::conv16)
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn symmetric_apply_rejects_asymmetric_row() {
+        let row = [1, 2, 3, 4, 5, 6, 7, 8];
+        IntegerConvolve::apply_circulant_symmetric_8_karat(rand_vec::<8>(), row);
+    }
+
+    #[test]
+    fn fused_apply_matches_apply() {
+        let lhs = rand_vec::<32>();
+        let rhs = rand_vec::<32>();
+        assert_eq!(
+            IntegerConvolve::apply_conv32_fused(lhs, rhs),
+            IntegerConvolve::apply(lhs, rhs, IntegerConvolve::conv32)
+        );
+
+        let lhs = rand_vec::<64>();
+        let rhs = rand_vec::<64>();
+        assert_eq!(
+            IntegerConvolve::apply_conv64_fused(lhs, rhs),
+            IntegerConvolve::apply(lhs, rhs, IntegerConvolve::conv64)
+        );
+    }
+
+    fn check_apply_circulant_karat_slice<F: PrimeField64, const N: usize>(max_entry: u64)
+    where
+        Standard: Distribution<F>,
+    {
+        let mut rng = thread_rng();
+        let input: [F; N] = rng.gen();
+        let row: [u64; N] = array::from_fn(|_| rng.gen_range(0..max_entry));
+        let expected = apply_circulant(&row, input);
+        let row = row.map(|x| x as i64);
+        assert_eq!(apply_circulant_karat_slice(&input, &row), expected);
+    }
+
+    #[test]
+    fn apply_circulant_karat_slice_matches_naive() {
+        check_apply_circulant_karat_slice::<Goldilocks, 8>(1 << 20);
+        check_apply_circulant_karat_slice::<Goldilocks, 12>(1 << 20);
+        check_apply_circulant_karat_slice::<Goldilocks, 16>(1 << 20);
+        check_apply_circulant_karat_slice::<Goldilocks, 24>(1 << 20);
+        check_apply_circulant_karat_slice::<Goldilocks, 64>(1 << 49);
+        check_apply_circulant_karat_slice::<BabyBear, 32>(BabyBear::ORDER_U64);
+        // Unsupported width, or entries too large for the Karatsuba path.
+        check_apply_circulant_karat_slice::<Goldilocks, 5>(1 << 20);
+        check_apply_circulant_karat_slice::<Goldilocks, 16>(1 << 62);
+    }
+
+    #[test]
+    fn apply_circulant_16_karat_pairs_matches_coordinates() {
+        let mut rng = thread_rng();
+        let input: [[BabyBear; 2]; 16] = rng.gen();
+        // Entries beyond P in absolute value are reduced first.
+        let row: [i64; 16] = array::from_fn(|_| rng.gen_range(-(1 << 40)..1 << 40));
+
+        let output = apply_circulant_16_karat_pairs(input, row);
+        for coord in 0..2 {
+            let expected = apply_circulant_karat_slice(&input.map(|x| x[coord]), &row);
+            assert_eq!(output.map(|x| x[coord]).to_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn apply_circulant_16_karat_colmajor_matches_rowmajor() {
+        let mut rng = thread_rng();
+        let height = 5;
+        let rows: Vec<[BabyBear; 16]> = (0..height).map(|_| rng.gen()).collect();
+        // Entries beyond P in absolute value are reduced first.
+        let row: [i64; 16] = array::from_fn(|_| rng.gen_range(-(1 << 40)..1 << 40));
+
+        let mut cols: [Vec<BabyBear>; 16] = array::from_fn(|c| rows.iter().map(|r| r[c]).collect());
+        apply_circulant_16_karat_colmajor(&mut cols, row);
+
+        for (r, input) in rows.iter().enumerate() {
+            let expected = apply_circulant_karat_slice(input, &row);
+            let actual: Vec<BabyBear> = cols.iter().map(|col| col[r]).collect();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "columns have different lengths")]
+    fn apply_circulant_16_karat_colmajor_rejects_ragged_columns() {
+        let mut rng = thread_rng();
+        let mut cols: [Vec<BabyBear>; 16] = array::from_fn(|_| (0..3).map(|_| rng.gen()).collect());
+        cols[7].pop();
+        apply_circulant_16_karat_colmajor(&mut cols, [1; 16]);
+    }
+
+    fn check_large_convolve_prime_field_32<F: PrimeField32, const N: usize>(
+        apply: fn([F; N], [i64; N]) -> [F; N],
+    ) where
+        Standard: Distribution<F>,
+    {
+        let mut rng = thread_rng();
+        let input: [F; N] = array::from_fn(|_| rng.gen());
+        let row: [u64; N] = array::from_fn(|_| rng.gen_range(0..F::ORDER_U64));
+        let expected = apply_circulant(&row, input);
+        assert_eq!(apply(input, row.map(|x| x as i64)), expected);
+        // Negative entries are allowed too.
+        let neg_expected = apply_circulant(&row, input.map(|x| -x));
+        assert_eq!(apply(input, row.map(|x| -(x as i64))), neg_expected);
+    }
+
+    #[test]
+    fn large_convolve_prime_field_32_matches_naive() {
+        check_large_convolve_prime_field_32::<BabyBear, 32>(apply_circulant_32_karat);
+        check_large_convolve_prime_field_32::<BabyBear, 64>(apply_circulant_64_karat);
+        check_large_convolve_prime_field_32::<Mersenne31, 32>(apply_circulant_32_karat);
+        check_large_convolve_prime_field_32::<Mersenne31, 64>(apply_circulant_64_karat);
+    }
+
+    fn check_small_negacyclic_conv<const N: usize>(
+        negacyclic_conv: fn([i64; N], [i64; N], &mut [i64]),
+    ) {
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let lhs = array::from_fn(|_| rng.gen_range(-8..8));
+            let rhs = array::from_fn(|_| rng.gen_range(-8..8));
+            let mut output = [0; N];
+            negacyclic_conv(lhs, rhs, &mut output);
+            assert_eq!(output, naive_negacyclic_conv(lhs, rhs));
+        }
+    }
+
+    #[test]
+    fn small_negacyclic_convs_match_naive() {
+        check_small_negacyclic_conv::<3>(IntegerConvolve::negacyclic_conv3);
+        check_small_negacyclic_conv::<5>(IntegerConvolve::negacyclic_conv5);
+        check_small_negacyclic_conv::<7>(IntegerConvolve::negacyclic_conv7);
+    }
+
+    #[test]
+    fn negacyclic_conv4_karatsuba_matches() {
+        for _ in 0..100 {
+            let lhs = rand_vec::<4>();
+            let rhs = rand_vec::<4>();
+
+            let mut direct = [0; 4];
+            IntegerConvolve::negacyclic_conv4(lhs, rhs, &mut direct);
+            let mut karatsuba = [0; 4];
+            IntegerConvolve::negacyclic_conv4_karatsuba(lhs, rhs, &mut karatsuba);
+
+            assert_eq!(direct, naive_negacyclic_conv(lhs, rhs));
+            assert_eq!(karatsuba, direct);
+        }
+    }
+
+    fn check_base_case_lens_agree<const N: usize>() {
+        let lhs = rand_vec::<N>();
+        let rhs = rand_vec::<N>();
+
+        let mut base4 = [0; N];
+        IntegerConvolve::conv_n::<N>(lhs, rhs, &mut base4);
+        let mut base2 = [0; N];
+        IntegerConvolveBase2::conv_n::<N>(lhs, rhs, &mut base2);
+        assert_eq!(base2, base4);
+        assert_eq!(base4, naive_conv(lhs, rhs));
+    }
+
+    #[test]
+    fn base_case_lens_agree() {
+        check_base_case_lens_agree::<8>();
+        check_base_case_lens_agree::<16>();
+        check_base_case_lens_agree::<32>();
+        check_base_case_lens_agree::<64>();
+
+        let lhs = rand_vec::<16>();
+        let rhs = rand_vec::<16>();
+        let mut base4 = [0; 16];
+        IntegerConvolve::negacyclic_conv16(lhs, rhs, &mut base4);
+        let mut base2 = [0; 16];
+        IntegerConvolveBase2::negacyclic_conv16(lhs, rhs, &mut base2);
+        assert_eq!(base2, base4);
+        assert_eq!(base4, naive_negacyclic_conv(lhs, rhs));
+    }
+
+    #[test]
+    fn slice_convs_match_array_convs() {
+        let lhs = rand_vec::<16>();
+        let rhs = rand_vec::<16>();
+
+        let mut expected = [0; 8];
+        let mut output = [0; 8];
+        IntegerConvolve::conv8(to_array(&lhs[..8]), to_array(&rhs[..8]), &mut expected);
+        IntegerConvolve::conv8_slice(&lhs[..8], &rhs[..8], &mut output);
+        assert_eq!(output, expected);
+        IntegerConvolve::negacyclic_conv8(to_array(&lhs[..8]), to_array(&rhs[..8]), &mut expected);
+        IntegerConvolve::negacyclic_conv8_slice(&lhs[..8], &rhs[..8], &mut output);
+        assert_eq!(output, expected);
+
+        let mut expected = [0; 16];
+        let mut output = [0; 16];
+        IntegerConvolve::conv16(lhs, rhs, &mut expected);
+        IntegerConvolve::conv16_slice(&lhs, &rhs, &mut output);
+        assert_eq!(output, expected);
+        IntegerConvolve::negacyclic_conv16(lhs, rhs, &mut expected);
+        IntegerConvolve::negacyclic_conv16_slice(&lhs, &rhs, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected slices of length 8")]
+    fn slice_conv_rejects_wrong_length() {
+        let mut output = [0; 8];
+        IntegerConvolve::conv8_slice(&[1; 8], &[1; 7], &mut output);
     }
 }
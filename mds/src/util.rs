@@ -3,7 +3,7 @@ use core::array;
 use core::ops::{AddAssign, Mul};
 
 use p3_dft::TwoAdicSubgroupDft;
-use p3_field::{AbstractField, TwoAdicField};
+use p3_field::{AbstractField, PrimeField64, TwoAdicField};
 
 // NB: These are all MDS for M31, BabyBear and Goldilocks
 // const MATRIX_CIRC_MDS_8_2EXP: [u64; 8] = [1, 1, 2, 1, 8, 32, 4, 256];
@@ -35,6 +35,85 @@ where
     dp
 }
 
+/// Integer types with overflow-checked addition and multiplication,
+/// as needed by `dot_product_checked`.
+pub trait CheckedArith: Copy {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_arith {
+    ($($t:ty),*) => {
+        $(
+            impl CheckedArith for $t {
+                #[inline(always)]
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                #[inline(always)]
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_arith!(i32, i64, i128, u32, u64, u128);
+
+/// Same as `dot_product`, but returns `None` if any product or partial
+/// sum overflows `T`, rather than silently wrapping (or panicking in
+/// debug builds).
+///
+/// This is a runtime safety net for the dot products in the
+/// convolutions, whose accumulators are only sized by reasoning about
+/// the bit widths of the inputs.
+#[inline]
+pub fn dot_product_checked<T: CheckedArith, const N: usize>(u: [T; N], v: [T; N]) -> Option<T> {
+    debug_assert_ne!(N, 0);
+    let mut dp = u[0].checked_mul(v[0])?;
+    for i in 1..N {
+        dp = dp.checked_add(u[i].checked_mul(v[i])?)?;
+    }
+    Some(dp)
+}
+
+/// Add the dot product of `u` and `v` to `acc`, widening each entry
+/// to the type of `acc` before multiplying.
+///
+/// Unlike `dot_product`, this lets a single sum be accumulated across
+/// several calls (e.g. one per block of a convolution), so that it
+/// only needs reducing once at the end. It is also how the large
+/// convolutions compute `parity_dot` in a wider type than their
+/// inputs.
+#[inline(always)]
+pub fn dot_product_acc<T, U, V>(acc: &mut V, u: &[T], v: &[U])
+where
+    T: Copy + Into<V>,
+    U: Copy + Into<V>,
+    V: Copy + AddAssign + Mul<Output = V>,
+{
+    debug_assert_eq!(u.len(), v.len());
+    for (&x, &y) in u.iter().zip(v) {
+        *acc += x.into() * y.into();
+    }
+}
+
+/// Write `even` to the even-indexed entries of `out` and `odd` to the
+/// odd-indexed ones, so that `out = [even[0], odd[0], even[1], ...]`.
+///
+/// Panics unless `even` and `odd` each have half the length of `out`.
+#[inline(always)]
+pub fn interleave_slices<T: Copy>(even: &[T], odd: &[T], out: &mut [T]) {
+    assert_eq!(even.len(), odd.len());
+    assert_eq!(out.len(), 2 * even.len());
+    for ((pair, &e), &o) in out.chunks_exact_mut(2).zip(even).zip(odd) {
+        pair[0] = e;
+        pair[1] = o;
+    }
+}
+
 /// Given the first row `circ_matrix` of an NxN circulant matrix, say
 /// C, return the product `C*input`.
 ///
@@ -82,6 +161,33 @@ pub const fn first_row_to_first_col<const N: usize, T: Copy>(v: &[T; N]) -> [T;
     output
 }
 
+/// Map a signed matrix entry to `F`, sending a negative `x` to the
+/// negation of `|x|`.
+#[inline]
+pub fn signed_to_field<F: PrimeField64>(x: i64) -> F {
+    let abs = F::from_wrapped_u64(x.unsigned_abs());
+    if x < 0 {
+        -abs
+    } else {
+        abs
+    }
+}
+
+/// Return the NxN circulant matrix with the given first row, as field
+/// elements. Row `i` is `first_row` rotated right by `i`, so that the
+/// matrix times a vector agrees with `apply_circulant(first_row, _)`.
+/// Negative entries are mapped to their negations in `F`.
+pub fn circulant_matrix<F: PrimeField64, const N: usize>(first_row: [i64; N]) -> [[F; N]; N] {
+    let first_row = first_row.map(signed_to_field);
+    array::from_fn(|i| array::from_fn(|j| first_row[(j + N - i) % N]))
+}
+
+/// Whether `m` is circulant, i.e. every row is the one above it
+/// rotated right by one.
+pub fn is_circulant<F: PartialEq, const N: usize>(m: &[[F; N]; N]) -> bool {
+    (0..N).all(|i| (0..N).all(|j| m[i][j] == m[0][(j + N - i) % N]))
+}
+
 /// Use the convolution theorem to calculate the product of the given
 /// circulant matrix and the given vector. The circulant matrix must
 /// be specified by its first *column*, not its first row. If you have
@@ -109,7 +215,67 @@ pub fn apply_circulant_fft<F: TwoAdicField, const N: usize, FFT: TwoAdicSubgroup
 
 #[cfg(test)]
 mod tests {
-    use super::first_row_to_first_col;
+    use p3_field::AbstractField;
+    use p3_mersenne_31::Mersenne31;
+
+    use super::{
+        apply_circulant, circulant_matrix, dot_product, dot_product_acc, dot_product_checked,
+        first_row_to_first_col, interleave_slices, is_circulant, signed_to_field,
+    };
+
+    #[test]
+    fn dot_product_checked_detects_overflow() {
+        let u: [i64; 4] = [1, -2, 3, 1 << 40];
+        let v: [i64; 4] = [7, 8, -9, 1 << 20];
+        assert_eq!(dot_product_checked(u, v), Some(dot_product(u, v)));
+
+        // The product overflows.
+        assert_eq!(dot_product_checked([1i64 << 32], [1 << 31]), None);
+        // Each product fits, but their sum doesn't.
+        assert_eq!(dot_product_checked([i64::MAX, 1], [1, 1]), None);
+    }
+
+    #[test]
+    fn dot_product_acc_continues_sum() {
+        let u: [i128; 6] = [1, -2, 3, 1 << 70, 5, -6];
+        let v: [i128; 6] = [7, 8, -9, 3, 11, 1 << 40];
+
+        let mut acc = 0i128;
+        dot_product_acc(&mut acc, &u[..4], &v[..4]);
+        dot_product_acc(&mut acc, &u[4..], &v[4..]);
+        assert_eq!(acc, dot_product(u, v));
+    }
+
+    #[test]
+    fn dot_product_acc_widens() {
+        let u: [i64; 3] = [i64::MAX, -3, 1 << 40];
+        let v: [i64; 3] = [i64::MAX, 5, -(1 << 40)];
+
+        let mut acc = 0i128;
+        dot_product_acc(&mut acc, &u, &v);
+        assert_eq!(acc, dot_product(u.map(i128::from), v.map(i128::from)));
+    }
+
+    #[test]
+    fn signed_to_field_negates() {
+        let f = |x| signed_to_field::<Mersenne31>(x);
+        assert_eq!(f(5), Mersenne31::from_canonical_u32(5));
+        assert_eq!(f(-5), -Mersenne31::from_canonical_u32(5));
+        assert_eq!(f(i64::MIN), -Mersenne31::from_wrapped_u64(1 << 63));
+    }
+
+    #[test]
+    fn interleave() {
+        let mut out = [0; 6];
+        interleave_slices(&[0, 2, 4], &[1, 3, 5], &mut out);
+        assert_eq!(out, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn interleave_rejects_unequal_halves() {
+        interleave_slices(&[0, 2, 4], &[1, 3], &mut [0; 5]);
+    }
 
     #[test]
     fn rotation() {
@@ -118,4 +284,24 @@ mod tests {
 
         assert_eq!(first_row_to_first_col(&input), output);
     }
+
+    #[test]
+    fn circulant_matrix_round_trip() {
+        let first_row = [3, -1, 4, 1, -5, 9];
+        let m = circulant_matrix::<Mersenne31, 6>(first_row);
+        assert!(is_circulant(&m));
+        assert_eq!(m[1][0], Mersenne31::from_canonical_u32(9));
+        assert_eq!(m[0][1], -Mersenne31::one());
+
+        let m = circulant_matrix::<Mersenne31, 6>([3, 1, 4, 1, 5, 9]);
+        let input: [Mersenne31; 6] =
+            core::array::from_fn(|i| Mersenne31::from_canonical_usize(i + 7));
+        let product: [Mersenne31; 6] =
+            core::array::from_fn(|i| Mersenne31::dot_product(&m[i], &input));
+        assert_eq!(product, apply_circulant(&[3, 1, 4, 1, 5, 9], input));
+
+        let mut not_circulant = m;
+        not_circulant[2][3] += Mersenne31::one();
+        assert!(!is_circulant(&not_circulant));
+    }
 }
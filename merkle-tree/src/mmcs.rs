@@ -63,6 +63,7 @@ where
 {
     type Commitment = Hash<P::Scalar, PW::Value, DIGEST_ELEMS>;
     type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
+    type OpenScratch = ();
     type Error = FieldMerkleTreeError;
     type ProverData<M> = FieldMerkleTree<P::Scalar, PW::Value, M, DIGEST_ELEMS>;
 
@@ -101,6 +102,27 @@ where
         (openings, proof)
     }
 
+    fn open_batch_into<M: Matrix<P::Scalar>>(
+        &self,
+        index: usize,
+        prover_data: &FieldMerkleTree<P::Scalar, PW::Value, M, DIGEST_ELEMS>,
+        opened_values: &mut Vec<P::Scalar>,
+        _scratch: &mut (),
+    ) -> Vec<[PW::Value; DIGEST_ELEMS]> {
+        let max_height = self.get_max_height(prover_data);
+        let log_max_height = log2_ceil_usize(max_height);
+
+        opened_values.clear();
+        for matrix in &prover_data.leaves {
+            let bits_reduced = log_max_height - log2_ceil_usize(matrix.height());
+            opened_values.extend(matrix.row(index >> bits_reduced));
+        }
+
+        (0..log_max_height)
+            .map(|i| prover_data.digest_layers[i][(index >> i) ^ 1])
+            .collect()
+    }
+
     fn get_matrices<'a, M: Matrix<P::Scalar>>(
         &self,
         prover_data: &'a Self::ProverData<M>,
@@ -540,4 +562,33 @@ mod tests {
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
             .expect("expected verification to succeed");
     }
+
+    #[test]
+    fn open_batch_into_matches_open_batch() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(
+            Poseidon2ExternalMatrixGeneral,
+            DiffusionMatrixBabyBear::default(),
+            &mut rng,
+        );
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 2),
+            RowMajorMatrix::<F>::rand(&mut rng, 4, 5),
+        ];
+        let (_, prover_data) = mmcs.commit(mats);
+
+        // Start with a dirty buffer to check that it's cleared.
+        let mut opened_values = vec![F::one(); 7];
+        for index in [0, 17, 31] {
+            let (opened_rows, proof) = mmcs.open_batch(index, &prover_data);
+            let proof_into = mmcs.open_batch_into(index, &prover_data, &mut opened_values, &mut ());
+            assert_eq!(opened_values, opened_rows.concat());
+            assert_eq!(proof_into, proof);
+        }
+    }
 }
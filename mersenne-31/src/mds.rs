@@ -1,14 +1,17 @@
 //! MDS matrices over the Mersenne31 field, and permutations defined by them.
 //!
 //! NB: Not all sizes have fast implementations of their permutations.
-//! Supported sizes: 8, 12, 16, 32, 64.
+//! Supported sizes: 8, 12, 16, 32, 64. Other circulant matrices of size 24
+//! can be applied with `apply_circulant_24_karat`.
 //! Sizes 8 and 12 are from Plonky2, size 16 was found as part of concurrent
 //! work by Angus Gruen and Hamish Ivey-Law. Other sizes are from Ulrich Haböck's
 //! database.
 
-use p3_field::AbstractField;
-use p3_mds::karatsuba_convolution::Convolve;
-use p3_mds::util::{dot_product, first_row_to_first_col};
+use p3_field::{AbstractField, PrimeField32};
+use p3_mds::karatsuba_convolution::{
+    convolution_strategy, row_abs_sum, small_conv_is_safe, ConvStrategy, Convolve,
+};
+use p3_mds::util::{dot_product, dot_product_acc, dot_product_checked, first_row_to_first_col};
 use p3_mds::MdsPermutation;
 use p3_symmetric::Permutation;
 
@@ -21,27 +24,72 @@ pub struct MdsMatrixMersenne31;
 ///
 /// Here "small" means N = len(rhs) <= 16 and sum(r for r in rhs) <
 /// 2^24 (roughly), though in practice the sum will be less than 2^9.
-struct SmallConvolveMersenne31;
+///
+/// Like the other convolutions of this crate, this is public so that
+/// other arithmetic, e.g. in a custom hash, can reuse its lazy
+/// reduction through the `Convolve` trait: `read` lifts field
+/// elements to `i64`s, which can be accumulated without reduction
+/// while they stay within the bounds above, and `reduce` maps the
+/// result back to the field.
+///
+/// In particular, a circulant matrix of one's own with small entries
+/// can be applied with the same tuned convolutions as
+/// `MdsMatrixMersenne31`:
+///
+/// ```
+/// use p3_field::AbstractField;
+/// use p3_mds::karatsuba_convolution::{row_abs_sum, small_conv_is_safe, Convolve};
+/// use p3_mds::util::{apply_circulant, first_row_to_first_col};
+/// use p3_mersenne_31::{Mersenne31, SmallConvolveMersenne31};
+///
+/// const MY_ROW: [i64; 16] = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9, 3];
+/// const MY_COL: [i64; 16] = first_row_to_first_col(&MY_ROW);
+/// // The entries are non-negative, and their sum is well within the bound.
+/// const _: () = assert!(small_conv_is_safe(16, row_abs_sum(&MY_COL)));
+///
+/// fn apply_my_circulant_16(input: [Mersenne31; 16]) -> [Mersenne31; 16] {
+///     SmallConvolveMersenne31::apply(input, MY_COL, SmallConvolveMersenne31::conv16)
+/// }
+///
+/// let input: [Mersenne31; 16] =
+///     core::array::from_fn(|i| Mersenne31::from_canonical_usize(i * i));
+/// let expected = apply_circulant(&MY_ROW.map(|x| x as u64), input);
+/// assert_eq!(apply_my_circulant_16(input), expected);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SmallConvolveMersenne31;
 impl Convolve<Mersenne31, i64, i64, i64> for SmallConvolveMersenne31 {
     /// Return the lift of an (almost) reduced Mersenne31 element.
     /// The Mersenne31 implementation guarantees that
     /// 0 <= input.value <= P < 2^31.
     #[inline(always)]
     fn read(input: Mersenne31) -> i64 {
+        debug_assert!(input.value <= Mersenne31::ORDER_U32);
         input.value as i64
     }
 
     /// FIXME: Refactor the dot product
-    /// For a convolution of size N, |x| < N * 2^31 and (as per the
-    /// assumption above), |y| < 2^24. So the product is at most N * 2^55
-    /// which will not overflow for N <= 16.
+    /// For a convolution of size N, every vector convolved along the
+    /// way has entries which are signed sums of distinct entries of
+    /// the original inputs. So the sum of |x| over `u` is less than
+    /// N * 2^31 and each |y| is at most the sum of the absolute values
+    /// of the first column, which (as per the assumption above) is less
+    /// than 2^24. This would still hold with a `BASE_CASE_LEN` of 2,
+    /// even though an entry of `v` can then be the sum of two entries
+    /// of the column. So the dot product is at most N * 2^55, which will not
+    /// overflow for N <= 16; more generally it's what
+    /// `small_conv_is_safe` requires to be below 2^63.
     #[inline(always)]
     fn parity_dot<const N: usize>(u: [i64; N], v: [i64; N]) -> i64 {
+        debug_assert!(
+            dot_product_checked(u, v).is_some(),
+            "parity_dot overflowed i64"
+        );
         dot_product(u, v)
     }
 
-    /// The assumptions above mean z < N^2 * 2^55, which is at most
-    /// 2^63 when N <= 16.
+    /// The assumptions above mean z < N * 2^55, as for the dot
+    /// products, which is at most 2^59 when N <= 16.
     ///
     /// NB: Even though intermediate values could be negative, the
     /// output must be non-negative since the inputs were
@@ -53,17 +101,60 @@ impl Convolve<Mersenne31, i64, i64, i64> for SmallConvolveMersenne31 {
     }
 }
 
+impl SmallConvolveMersenne31 {
+    /// Same as `apply`, but first checks, in debug builds, that
+    /// `first_col` is small enough for the `i64` accumulators.
+    #[inline(always)]
+    fn apply_checked<const N: usize, C: Fn([i64; N], [i64; N], &mut [i64])>(
+        input: [Mersenne31; N],
+        first_col: [i64; N],
+        conv: C,
+    ) -> [Mersenne31; N] {
+        debug_assert!(
+            small_conv_is_safe(N, row_abs_sum(&first_col)),
+            "matrix too large for a small convolution of size {}",
+            N
+        );
+        Self::apply(input, first_col, conv)
+    }
+}
+
 /// Instantiate convolution for "large" RHS vectors over Mersenne31.
 ///
 /// Here "large" means the elements can be as big as the field
 /// characteristic, and the size N of the RHS is <= 64.
-struct LargeConvolveMersenne31;
+///
+/// This can also be used for lazily reduced arithmetic outside of
+/// convolutions, e.g. a dot product with large constants, reduced once
+/// at the end:
+///
+/// ```
+/// use p3_field::AbstractField;
+/// use p3_mds::karatsuba_convolution::Convolve;
+/// use p3_mersenne_31::{LargeConvolveMersenne31, Mersenne31};
+///
+/// let xs = [5, 7, 11].map(Mersenne31::from_canonical_u32);
+/// let cs: [i64; 3] = [(1 << 30) + 3, -(1 << 29), 12345];
+///
+/// // `parity_dot` widens each product to i128 and partially reduces the sum, and `reduce`
+/// // finishes the reduction.
+/// let acc = LargeConvolveMersenne31::parity_dot(xs.map(LargeConvolveMersenne31::read), cs);
+/// let dot: Mersenne31 = LargeConvolveMersenne31::reduce(acc);
+///
+/// let expected = xs[0] * Mersenne31::from_canonical_u32((1 << 30) + 3)
+///     - xs[1] * Mersenne31::from_canonical_u32(1 << 29)
+///     + xs[2] * Mersenne31::from_canonical_u32(12345);
+/// assert_eq!(dot, expected);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LargeConvolveMersenne31;
 impl Convolve<Mersenne31, i64, i64, i64> for LargeConvolveMersenne31 {
     /// Return the lift of an (almost) reduced Mersenne31 element.
     /// The Mersenne31 implementation guarantees that
     /// 0 <= input.value <= P < 2^31.
     #[inline(always)]
     fn read(input: Mersenne31) -> i64 {
+        debug_assert!(input.value <= Mersenne31::ORDER_U32);
         input.value as i64
     }
 
@@ -74,9 +165,7 @@ impl Convolve<Mersenne31, i64, i64, i64> for LargeConvolveMersenne31 {
         // we first widen to i128.
 
         let mut dp = 0i128;
-        for i in 0..N {
-            dp += u[i] as i128 * v[i] as i128;
-        }
+        dot_product_acc(&mut dp, &u, &v);
 
         const LOWMASK: i128 = (1 << 42) - 1; // Gets the bits lower than 42.
         const HIGHMASK: i128 = !LOWMASK; // Gets all bits higher than 42.
@@ -135,13 +224,96 @@ impl Convolve<Mersenne31, i64, i64, i64> for LargeConvolveMersenne31 {
     }
 }
 
+/// Same as the "large" convolution over Mersenne31, but partially
+/// reducing after each level of the negacyclic recursion, rather than
+/// letting the values grow by up to a factor of 3 per level.
+///
+/// The "large" convolution is preferable for Mersenne31 itself, as its
+/// bound of 2^49 leaves plenty of room in an i64. This variant keeps
+/// all values below 3 * 2^44 < 2^46 regardless of the number of
+/// levels, which is what a field slightly larger than 31 bits would
+/// need, and serves as a reference for such fields.
+#[derive(Clone, Debug, Default)]
+pub struct VeryLargeConvolveMersenne31;
+impl Convolve<Mersenne31, i64, i64, i64> for VeryLargeConvolveMersenne31 {
+    #[inline(always)]
+    fn read(input: Mersenne31) -> i64 {
+        LargeConvolveMersenne31::read(input)
+    }
+
+    #[inline]
+    fn parity_dot<const N: usize>(u: [i64; N], v: [i64; N]) -> i64 {
+        LargeConvolveMersenne31::parity_dot(u, v)
+    }
+
+    #[inline]
+    fn reduce(z: i64) -> Mersenne31 {
+        LargeConvolveMersenne31::reduce(z)
+    }
+
+    /// The same split as in `LargeConvolveMersenne31::parity_dot`:
+    /// the bits above 2^42 are shifted down by 31, which preserves
+    /// the value modulo P = 2^31 - 1 and modulo 2^11. The input
+    /// satisfies |z| < 3 * 2^44 (one recombination step applied to
+    /// values below 2^44), so the output is below 2^42 + 2^15.
+    #[inline(always)]
+    fn reduce_intermediate(z: i64) -> i64 {
+        const LOWMASK: i64 = (1 << 42) - 1;
+        const HIGHMASK: i64 = !LOWMASK;
+
+        (z & LOWMASK) + ((z & HIGHMASK) >> 31)
+    }
+}
+
+/// Multiply `input` by the size 16 circulant matrix with first column
+/// `first_col`, using the "large" convolution with its i128 dot
+/// products.
+///
+/// `MdsMatrixMersenne31` uses the "small" convolution for size 16,
+/// which only works when `first_col` is non-negative and
+/// `small_conv_is_safe(16, row_abs_sum(&first_col))` holds (see
+/// `Circulant16Col::new`). Other matrices should use this function
+/// instead, which accepts any entries less than P in absolute value,
+/// at the cost of widening every dot product to i128.
+pub fn apply_circulant_16_karat_large(
+    input: [Mersenne31; 16],
+    first_col: [i64; 16],
+) -> [Mersenne31; 16] {
+    LargeConvolveMersenne31::apply(input, first_col, LargeConvolveMersenne31::conv16)
+}
+
+/// Multiply `input` by the size 24 circulant matrix with first column
+/// `first_col`, e.g. for the external layer of a width 24 Poseidon2.
+///
+/// `MdsMatrixMersenne31` has no size 24 matrix of its own, so the
+/// caller supplies one. The size 24 convolution splits into two of
+/// size 12, and those into size 6 and 3 convolutions. Every
+/// intermediate value is bounded by `Sum(|input|) * Sum(|first_col|)`,
+/// where `Sum(|input|) < 24 * 2^31`, so the "small" convolution is
+/// used when this is below 2^63, i.e. when the entries of `first_col`
+/// sum to less than about 2^27 (see `small_conv_is_safe`), provided
+/// they're non-negative, as it can't reduce negative results; this is
+/// what `convolution_strategy` decides. Otherwise this falls back to
+/// the "large" convolution, which accepts any entries less than P in
+/// absolute value.
+pub fn apply_circulant_24_karat(input: [Mersenne31; 24], first_col: [i64; 24]) -> [Mersenne31; 24] {
+    match convolution_strategy::<Mersenne31>(&first_col) {
+        ConvStrategy::SmallI64 => {
+            SmallConvolveMersenne31::apply(input, first_col, SmallConvolveMersenne31::conv24)
+        }
+        ConvStrategy::LargeI128 => {
+            LargeConvolveMersenne31::apply(input, first_col, LargeConvolveMersenne31::conv24)
+        }
+    }
+}
+
 const MATRIX_CIRC_MDS_8_SML_ROW: [i64; 8] = [7, 1, 3, 8, 8, 3, 4, 9];
 
 impl Permutation<[Mersenne31; 8]> for MdsMatrixMersenne31 {
     fn permute(&self, input: [Mersenne31; 8]) -> [Mersenne31; 8] {
         const MATRIX_CIRC_MDS_8_SML_COL: [i64; 8] =
             first_row_to_first_col(&MATRIX_CIRC_MDS_8_SML_ROW);
-        SmallConvolveMersenne31::apply(
+        SmallConvolveMersenne31::apply_checked(
             input,
             MATRIX_CIRC_MDS_8_SML_COL,
             SmallConvolveMersenne31::conv8,
@@ -160,7 +332,7 @@ impl Permutation<[Mersenne31; 12]> for MdsMatrixMersenne31 {
     fn permute(&self, input: [Mersenne31; 12]) -> [Mersenne31; 12] {
         const MATRIX_CIRC_MDS_12_SML_COL: [i64; 12] =
             first_row_to_first_col(&MATRIX_CIRC_MDS_12_SML_ROW);
-        SmallConvolveMersenne31::apply(
+        SmallConvolveMersenne31::apply_checked(
             input,
             MATRIX_CIRC_MDS_12_SML_COL,
             SmallConvolveMersenne31::conv12,
@@ -175,12 +347,31 @@ impl MdsPermutation<Mersenne31, 12> for MdsMatrixMersenne31 {}
 
 const MATRIX_CIRC_MDS_16_SML_ROW: [i64; 16] =
     [1, 1, 51, 1, 11, 17, 2, 1, 101, 63, 15, 2, 67, 22, 13, 3];
+const MATRIX_CIRC_MDS_16_SML_COL: [i64; 16] = first_row_to_first_col(&MATRIX_CIRC_MDS_16_SML_ROW);
+
+/// Same as the size 16 `MdsMatrixMersenne31` permutation, but returning
+/// the exact integer products rather than reducing them modulo P, for
+/// callers which feed the output straight into further arithmetic and
+/// can defer the reduction.
+///
+/// As the inputs are at most P and the entries of the matrix sum to
+/// 371, every output lies in `[0, 371 * P]`, so below 2^40. Reduce
+/// with `Mersenne31::from_wrapped_u64(x as u64)`.
+pub fn apply_circulant_16_karat_noncanonical(input: [Mersenne31; 16]) -> [i64; 16] {
+    debug_assert!(small_conv_is_safe(
+        16,
+        row_abs_sum(&MATRIX_CIRC_MDS_16_SML_COL)
+    ));
+    SmallConvolveMersenne31::apply_unreduced(
+        input,
+        MATRIX_CIRC_MDS_16_SML_COL,
+        SmallConvolveMersenne31::conv16,
+    )
+}
 
 impl Permutation<[Mersenne31; 16]> for MdsMatrixMersenne31 {
     fn permute(&self, input: [Mersenne31; 16]) -> [Mersenne31; 16] {
-        const MATRIX_CIRC_MDS_16_SML_COL: [i64; 16] =
-            first_row_to_first_col(&MATRIX_CIRC_MDS_16_SML_ROW);
-        SmallConvolveMersenne31::apply(
+        SmallConvolveMersenne31::apply_checked(
             input,
             MATRIX_CIRC_MDS_16_SML_COL,
             SmallConvolveMersenne31::conv16,
@@ -191,6 +382,61 @@ impl Permutation<[Mersenne31; 16]> for MdsMatrixMersenne31 {
         *input = self.permute(*input);
     }
 }
+
+/// The first column of a size 16 circulant matrix, checked when it is
+/// built to be small enough for `SmallConvolveMersenne31`, so that
+/// `apply_circulant_16_karat_with_col` can apply the matrix any number
+/// of times without converting or checking it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Circulant16Col([i64; 16]);
+
+impl Circulant16Col {
+    /// Returns `None` if the entries of `first_col` are negative or too
+    /// large for the small convolution, i.e. unless
+    /// `convolution_strategy` chooses `SmallI64` for it.
+    pub const fn new(first_col: [i64; 16]) -> Option<Self> {
+        match convolution_strategy::<Mersenne31>(&first_col) {
+            ConvStrategy::SmallI64 => Some(Self(first_col)),
+            ConvStrategy::LargeI128 => None,
+        }
+    }
+
+    /// Same as `new`, for the matrix given by its first row.
+    pub const fn from_first_row(first_row: &[i64; 16]) -> Option<Self> {
+        Self::new(first_row_to_first_col(first_row))
+    }
+
+    pub const fn first_col(&self) -> &[i64; 16] {
+        &self.0
+    }
+}
+
+/// Multiply `input` by the circulant matrix with first row `first_row`,
+/// using `SmallConvolveMersenne31`. The row is converted to the first
+/// column the convolution takes, and checked, on every call; callers
+/// applying the same matrix many times should build a `Circulant16Col`
+/// once and use `apply_circulant_16_karat_with_col` instead.
+///
+/// Panics if the entries of `first_row` are negative or too large for
+/// the small convolution.
+pub fn apply_circulant_16_karat(
+    input: [Mersenne31; 16],
+    first_row: &[i64; 16],
+) -> [Mersenne31; 16] {
+    let first_col = Circulant16Col::from_first_row(first_row)
+        .expect("matrix too large for a small convolution of size 16");
+    apply_circulant_16_karat_with_col(input, &first_col)
+}
+
+/// Same as `apply_circulant_16_karat`, for the matrix whose first
+/// column is `first_col`, which was checked when it was built.
+pub fn apply_circulant_16_karat_with_col(
+    input: [Mersenne31; 16],
+    first_col: &Circulant16Col,
+) -> [Mersenne31; 16] {
+    SmallConvolveMersenne31::apply(input, first_col.0, SmallConvolveMersenne31::conv16)
+}
+
 impl MdsPermutation<Mersenne31, 16> for MdsMatrixMersenne31 {}
 
 #[rustfmt::skip]
@@ -209,11 +455,7 @@ impl Permutation<[Mersenne31; 32]> for MdsMatrixMersenne31 {
     fn permute(&self, input: [Mersenne31; 32]) -> [Mersenne31; 32] {
         const MATRIX_CIRC_MDS_32_MERSENNE31_COL: [i64; 32] =
             first_row_to_first_col(&MATRIX_CIRC_MDS_32_MERSENNE31_ROW);
-        LargeConvolveMersenne31::apply(
-            input,
-            MATRIX_CIRC_MDS_32_MERSENNE31_COL,
-            LargeConvolveMersenne31::conv32,
-        )
+        LargeConvolveMersenne31::apply_conv32_fused(input, MATRIX_CIRC_MDS_32_MERSENNE31_COL)
     }
 
     fn permute_mut(&self, input: &mut [Mersenne31; 32]) {
@@ -246,11 +488,7 @@ impl Permutation<[Mersenne31; 64]> for MdsMatrixMersenne31 {
     fn permute(&self, input: [Mersenne31; 64]) -> [Mersenne31; 64] {
         const MATRIX_CIRC_MDS_64_MERSENNE31_COL: [i64; 64] =
             first_row_to_first_col(&MATRIX_CIRC_MDS_64_MERSENNE31_ROW);
-        LargeConvolveMersenne31::apply(
-            input,
-            MATRIX_CIRC_MDS_64_MERSENNE31_COL,
-            LargeConvolveMersenne31::conv64,
-        )
+        LargeConvolveMersenne31::apply_conv64_fused(input, MATRIX_CIRC_MDS_64_MERSENNE31_COL)
     }
 
     fn permute_mut(&self, input: &mut [Mersenne31; 64]) {
@@ -259,12 +497,45 @@ impl Permutation<[Mersenne31; 64]> for MdsMatrixMersenne31 {
 }
 impl MdsPermutation<Mersenne31, 64> for MdsMatrixMersenne31 {}
 
+/// The first row of the circulant MDS matrix which `MdsMatrixMersenne31`
+/// uses at the given width, for applying it some other way, e.g. with
+/// `p3_mds::karatsuba_convolution::apply_circulant_karat_slice`.
+///
+/// Panics unless `width` is one of the supported sizes 8, 12, 16, 32
+/// and 64.
+pub const fn recommended_mds_row(width: usize) -> &'static [i64] {
+    match width {
+        8 => &MATRIX_CIRC_MDS_8_SML_ROW,
+        12 => &MATRIX_CIRC_MDS_12_SML_ROW,
+        16 => &MATRIX_CIRC_MDS_16_SML_ROW,
+        32 => &MATRIX_CIRC_MDS_32_MERSENNE31_ROW,
+        64 => &MATRIX_CIRC_MDS_64_MERSENNE31_ROW,
+        _ => panic!("no MDS matrix of this width"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use p3_field::AbstractField;
+    use itertools::Itertools;
+    use p3_field::{AbstractField, Field, PrimeField32};
+    use p3_mds::karatsuba_convolution::{
+        apply_circulant_karat_slice, convolution_strategy, row_abs_sum, small_conv_is_safe,
+        ConvStrategy, Convolve,
+    };
+    use p3_mds::util::{apply_circulant, circulant_matrix, first_row_to_first_col, is_circulant};
     use p3_symmetric::Permutation;
 
-    use super::{MdsMatrixMersenne31, Mersenne31};
+    use rand::{thread_rng, Rng};
+
+    use super::{
+        apply_circulant_16_karat, apply_circulant_16_karat_large,
+        apply_circulant_16_karat_noncanonical, apply_circulant_16_karat_with_col,
+        apply_circulant_24_karat, recommended_mds_row, Circulant16Col, LargeConvolveMersenne31,
+        MdsMatrixMersenne31, Mersenne31, SmallConvolveMersenne31, VeryLargeConvolveMersenne31,
+        MATRIX_CIRC_MDS_12_SML_ROW, MATRIX_CIRC_MDS_16_SML_COL, MATRIX_CIRC_MDS_16_SML_ROW,
+        MATRIX_CIRC_MDS_32_MERSENNE31_ROW, MATRIX_CIRC_MDS_64_MERSENNE31_ROW,
+        MATRIX_CIRC_MDS_8_SML_ROW,
+    };
 
     #[test]
     fn mersenne8() {
@@ -304,6 +575,20 @@ mod tests {
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn mersenne12_apply_circulant_karat() {
+        let input: [Mersenne31; 12] = [
+            1232740094, 661555540, 11024822, 1620264994, 471137070, 276755041, 1316882747,
+            1023679816, 1675266989, 743211887, 44774582, 1990989306,
+        ]
+        .map(Mersenne31::from_canonical_u64);
+
+        let col = first_row_to_first_col(&MATRIX_CIRC_MDS_12_SML_ROW);
+        let output = SmallConvolveMersenne31::apply_circulant_karat(input, col);
+
+        assert_eq!(output, MdsMatrixMersenne31.permute(input));
+    }
+
     #[test]
     fn mersenne16() {
         let input: [Mersenne31; 16] = [
@@ -350,6 +635,19 @@ mod tests {
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn mersenne32_matches_circulant_matrix() {
+        let mut rng = thread_rng();
+        let input: [Mersenne31; 32] = core::array::from_fn(|_| rng.gen());
+
+        let matrix = circulant_matrix::<Mersenne31, 32>(MATRIX_CIRC_MDS_32_MERSENNE31_ROW);
+        assert!(is_circulant(&matrix));
+        let expected: [Mersenne31; 32] =
+            core::array::from_fn(|i| Mersenne31::dot_product(&matrix[i], &input));
+
+        assert_eq!(MdsMatrixMersenne31.permute(input), expected);
+    }
+
     #[test]
     fn mersenne64() {
         let input: [Mersenne31; 64] = [
@@ -384,4 +682,299 @@ mod tests {
 
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn very_large_convolve_matches_large() {
+        let mut rng = thread_rng();
+
+        let input: [Mersenne31; 32] = core::array::from_fn(|_| rng.gen());
+        let col = first_row_to_first_col(&MATRIX_CIRC_MDS_32_MERSENNE31_ROW);
+        assert_eq!(
+            VeryLargeConvolveMersenne31::apply(input, col, VeryLargeConvolveMersenne31::conv32),
+            LargeConvolveMersenne31::apply(input, col, LargeConvolveMersenne31::conv32)
+        );
+
+        let input: [Mersenne31; 64] = core::array::from_fn(|_| rng.gen());
+        let col = first_row_to_first_col(&MATRIX_CIRC_MDS_64_MERSENNE31_ROW);
+        assert_eq!(
+            VeryLargeConvolveMersenne31::apply(input, col, VeryLargeConvolveMersenne31::conv64),
+            LargeConvolveMersenne31::apply(input, col, LargeConvolveMersenne31::conv64)
+        );
+        assert_eq!(
+            VeryLargeConvolveMersenne31::apply(input, col, VeryLargeConvolveMersenne31::conv64),
+            MdsMatrixMersenne31.permute(input)
+        );
+    }
+
+    #[test]
+    fn apply_circulant_16_karat_large_matches_naive() {
+        let mut rng = thread_rng();
+        let input: [Mersenne31; 16] = core::array::from_fn(|_| rng.gen());
+        // Entries of at least 2^29, so the sum is far beyond what the small convolution handles.
+        let row: [u64; 16] = core::array::from_fn(|_| rng.gen_range(1 << 29..(1 << 31) - 1));
+        let col = first_row_to_first_col(&row).map(|c| c as i64);
+        assert_eq!(
+            apply_circulant_16_karat_large(input, col),
+            apply_circulant(&row, input)
+        );
+    }
+
+    #[test]
+    fn large_parity_dot_matches_i128_dot() {
+        const N: usize = 64;
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            // |x|, |y| < N * 2^31, the largest inputs a convolution of size N produces.
+            let bound = (N as i64) << 31;
+            let u: [i64; N] = core::array::from_fn(|_| rng.gen_range(1 - bound..bound));
+            let v: [i64; N] = core::array::from_fn(|_| rng.gen_range(1 - bound..bound));
+            let dp: i128 = u.iter().zip(v).map(|(&x, y)| x as i128 * y as i128).sum();
+
+            let z = LargeConvolveMersenne31::parity_dot(u, v);
+            let p = Mersenne31::ORDER_U32 as i128;
+            assert_eq!((z as i128 - dp).rem_euclid(p), 0);
+            assert_eq!((z as i128 - dp) % (1 << 10), 0);
+        }
+    }
+
+    #[test]
+    fn convolution_strategy_matches_permutations() {
+        // The fixed matrices of widths 8, 12 and 16 use the small convolution, and those of widths
+        // 32 and 64 the large one.
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&first_row_to_first_col(&MATRIX_CIRC_MDS_8_SML_ROW)),
+            ConvStrategy::SmallI64
+        );
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&first_row_to_first_col(
+                &MATRIX_CIRC_MDS_12_SML_ROW
+            )),
+            ConvStrategy::SmallI64
+        );
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&MATRIX_CIRC_MDS_16_SML_COL),
+            ConvStrategy::SmallI64
+        );
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&first_row_to_first_col(
+                &MATRIX_CIRC_MDS_32_MERSENNE31_ROW
+            )),
+            ConvStrategy::LargeI128
+        );
+        assert_eq!(
+            convolution_strategy::<Mersenne31>(&first_row_to_first_col(
+                &MATRIX_CIRC_MDS_64_MERSENNE31_ROW
+            )),
+            ConvStrategy::LargeI128
+        );
+
+        // `Circulant16Col` accepts exactly the columns given the small convolution.
+        let mut rng = thread_rng();
+        for max_entry in [1 << 10, 1 << 24, 1 << 28] {
+            let col: [i64; 16] = core::array::from_fn(|_| rng.gen_range(-1..max_entry));
+            assert_eq!(
+                Circulant16Col::new(col).is_some(),
+                convolution_strategy::<Mersenne31>(&col) == ConvStrategy::SmallI64
+            );
+        }
+    }
+
+    #[test]
+    fn apply_circulant_24_karat_matches_naive() {
+        let mut rng = thread_rng();
+        let input: [Mersenne31; 24] = core::array::from_fn(|_| rng.gen());
+        // Small entries, which take the small convolution, and entries of at least 2^29, which
+        // don't.
+        for entries in [0..1 << 10, 1 << 29..(1 << 31) - 1] {
+            let row: [u64; 24] = core::array::from_fn(|_| rng.gen_range(entries.clone()));
+            let col = first_row_to_first_col(&row).map(|c| c as i64);
+            assert_eq!(
+                apply_circulant_24_karat(input, col),
+                apply_circulant(&row, input)
+            );
+        }
+    }
+
+    #[test]
+    fn noncanonical_16_reduces_to_permute() {
+        let mut rng = thread_rng();
+        let input: [Mersenne31; 16] = core::array::from_fn(|_| rng.gen());
+        let output = apply_circulant_16_karat_noncanonical(input);
+        assert!(output.iter().all(|&x| (0..1 << 40).contains(&x)));
+        let expected = MdsMatrixMersenne31.permute(input);
+        for (x, y) in output.into_iter().zip(expected) {
+            assert!(SmallConvolveMersenne31::eq_canonical(
+                x,
+                y.as_canonical_u32() as i64
+            ));
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "matrix too large for a small convolution")]
+    fn small_convolution_rejects_large_matrix() {
+        let input = [Mersenne31::one(); 16];
+        SmallConvolveMersenne31::apply_checked(
+            input,
+            [1 << 28; 16],
+            SmallConvolveMersenne31::conv16,
+        );
+    }
+
+    #[test]
+    fn apply_circulant_16_karat_with_col_matches_row() {
+        let col = Circulant16Col::new(MATRIX_CIRC_MDS_16_SML_COL).unwrap();
+        assert_eq!(
+            Circulant16Col::from_first_row(&MATRIX_CIRC_MDS_16_SML_ROW),
+            Some(col)
+        );
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let input: [Mersenne31; 16] = rng.gen();
+            let expected = apply_circulant(&MATRIX_CIRC_MDS_16_SML_ROW.map(|x| x as u64), input);
+            assert_eq!(
+                apply_circulant_16_karat(input, &MATRIX_CIRC_MDS_16_SML_ROW),
+                expected
+            );
+            assert_eq!(apply_circulant_16_karat_with_col(input, &col), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "matrix too large for a small convolution")]
+    fn apply_circulant_16_karat_rejects_negative_entries() {
+        let mut row = MATRIX_CIRC_MDS_16_SML_ROW;
+        row[3] = -1;
+        apply_circulant_16_karat([Mersenne31::one(); 16], &row);
+    }
+
+    #[test]
+    fn circulant_16_col_rejects_large_entries() {
+        let mut col = MATRIX_CIRC_MDS_16_SML_COL;
+        col[3] = -1;
+        assert_eq!(Circulant16Col::new(col), None);
+        assert_eq!(Circulant16Col::new([1 << 28; 16]), None);
+    }
+
+    #[test]
+    fn small_convolution_at_safety_bound() {
+        // The largest column sum `small_conv_is_safe` allows, concentrated in
+        // a pair of entries which a size 2 base case would add together.
+        let mut col = [0; 16];
+        col[0] = 1 << 27;
+        col[1] = (1 << 27) - 1;
+        assert!(small_conv_is_safe(16, row_abs_sum(&col)));
+        assert!(!small_conv_is_safe(16, row_abs_sum(&col) + 1));
+
+        // `parity_dot` checks for overflow in debug builds.
+        let input = [Mersenne31::neg_one(); 16];
+        let row = first_row_to_first_col(&col).map(|x| x as u64);
+        assert_eq!(
+            SmallConvolveMersenne31::apply_checked(input, col, SmallConvolveMersenne31::conv16),
+            apply_circulant(&row, input)
+        );
+    }
+
+    #[test]
+    fn eq_canonical_ignores_representative() {
+        let p = Mersenne31::ORDER_U32 as i64;
+        assert!(SmallConvolveMersenne31::eq_canonical(p, 0));
+        assert!(SmallConvolveMersenne31::eq_canonical(3 * p + 5, 5));
+        assert!(!SmallConvolveMersenne31::eq_canonical(p + 1, 0));
+    }
+
+    fn check_recommended_mds_row<const N: usize>()
+    where
+        MdsMatrixMersenne31: Permutation<[Mersenne31; N]>,
+    {
+        let mut rng = thread_rng();
+        let input: [Mersenne31; N] = core::array::from_fn(|_| rng.gen());
+        let row = recommended_mds_row(N);
+        assert_eq!(row.len(), N);
+        assert_eq!(
+            apply_circulant_karat_slice(&input, row),
+            MdsMatrixMersenne31.permute(input).to_vec()
+        );
+    }
+
+    #[test]
+    fn recommended_mds_rows_match_permutations() {
+        check_recommended_mds_row::<8>();
+        check_recommended_mds_row::<12>();
+        check_recommended_mds_row::<16>();
+        check_recommended_mds_row::<32>();
+        check_recommended_mds_row::<64>();
+    }
+
+    /// The determinant of the square matrix `m`, by Gaussian elimination.
+    fn det(mut m: Vec<Vec<Mersenne31>>) -> Mersenne31 {
+        let n = m.len();
+        let mut det = Mersenne31::one();
+        for col in 0..n {
+            let Some(pivot) = (col..n).find(|&r| !m[r][col].is_zero()) else {
+                return Mersenne31::zero();
+            };
+            if pivot != col {
+                m.swap(pivot, col);
+                det = -det;
+            }
+            det *= m[col][col];
+            let inv = m[col][col].inverse();
+            let pivot_row = m[col].clone();
+            for row in m.iter_mut().skip(col + 1) {
+                let factor = row[col] * inv;
+                for (x, &p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                    *x -= factor * p;
+                }
+            }
+        }
+        det
+    }
+
+    /// Check that every square submatrix of `m` of size at most `max_size`, and `m` itself, is
+    /// nonsingular. With `max_size` equal to the width this is the full MDS property; it is
+    /// exponential in the width, so wider matrices only have their small minors checked.
+    fn check_mds_minors(m: &[Vec<Mersenne31>], max_size: usize) {
+        let n = m.len();
+        assert!(!det(m.to_vec()).is_zero());
+        for size in 1..=max_size {
+            for rows in (0..n).combinations(size) {
+                for cols in (0..n).combinations(size) {
+                    let minor = rows
+                        .iter()
+                        .map(|&r| cols.iter().map(|&c| m[r][c]).collect())
+                        .collect();
+                    assert!(!det(minor).is_zero(), "singular minor {rows:?} x {cols:?}");
+                }
+            }
+        }
+    }
+
+    /// The circulant matrix with first row `recommended_mds_row(width)`.
+    fn recommended_mds_matrix(width: usize) -> Vec<Vec<Mersenne31>> {
+        let row = recommended_mds_row(width);
+        (0..width)
+            .map(|i| {
+                (0..width)
+                    .map(|j| Mersenne31::from_canonical_u64(row[(j + width - i) % width] as u64))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recommended_mds_row_8_is_mds() {
+        check_mds_minors(&recommended_mds_matrix(8), 8);
+    }
+
+    /// A partial check only: checking every minor is out of reach beyond width 8 (there are
+    /// binomial(2n, n) of them, about 6 * 10^8 for width 16), so this checks the minors of
+    /// size at most 2, or 1 for width 64, and the full determinant.
+    #[test]
+    fn recommended_mds_rows_have_nonsingular_small_minors() {
+        for (width, max_size) in [(12, 2), (16, 2), (32, 2), (64, 1)] {
+            check_mds_minors(&recommended_mds_matrix(width), max_size);
+        }
+    }
 }
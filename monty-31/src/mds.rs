@@ -1,7 +1,7 @@
 use core::marker::PhantomData;
 
-use p3_mds::karatsuba_convolution::Convolve;
-use p3_mds::util::dot_product;
+use p3_mds::karatsuba_convolution::{row_abs_sum, small_conv_is_safe, Convolve};
+use p3_mds::util::{dot_product, dot_product_acc, dot_product_checked};
 use p3_mds::MdsPermutation;
 use p3_symmetric::Permutation;
 
@@ -26,7 +26,13 @@ pub struct MdsMatrixMontyField31<MU: MDSUtils> {
 ///
 /// Here "small" means N = len(rhs) <= 16 and sum(r for r in rhs) <
 /// 2^24 (roughly), though in practice the sum will be less than 2^9.
-struct SmallConvolveMontyField31;
+///
+/// This is public so that other lazily reduced arithmetic, e.g. in a
+/// custom hash, can reuse it through the `Convolve` trait. Note that
+/// `read` and `reduce` work on the Monty form of the elements, which is
+/// fine for multiplication by plain integers.
+#[derive(Clone, Debug, Default)]
+pub struct SmallConvolveMontyField31;
 
 impl<FP: MontyParameters> Convolve<MontyField31<FP>, i64, i64, i64> for SmallConvolveMontyField31 {
     /// Return the lift of a Monty31 element, satisfying 0 <=
@@ -34,23 +40,35 @@ impl<FP: MontyParameters> Convolve<MontyField31<FP>, i64, i64, i64> for SmallCon
     /// represented in Monty form.
     #[inline(always)]
     fn read(input: MontyField31<FP>) -> i64 {
+        debug_assert!(input.value < FP::PRIME);
         input.value as i64
     }
 
-    /// For a convolution of size N, |x| < N * 2^31 and (as per the
-    /// assumption above), |y| < 2^24. So the product is at most N * 2^55
-    /// which will not overflow for N <= 16.
+    /// For a convolution of size N, every vector convolved along the
+    /// way has entries which are signed sums of distinct entries of
+    /// the original inputs. So the sum of |x| over `u` is less than
+    /// N * 2^31 and each |y| is at most the sum of the absolute values
+    /// of the first column, which (as per the assumption above) is less
+    /// than 2^24. This would still hold with a `BASE_CASE_LEN` of 2,
+    /// even though an entry of `v` can then be the sum of two entries
+    /// of the column. So the dot product is at most N * 2^55, which will not
+    /// overflow for N <= 16; more generally it's what
+    /// `small_conv_is_safe` requires to be below 2^63.
     ///
     /// Note that the LHS element is in Monty form, while the RHS
     /// element is a "plain integer". This informs the implementation
     /// of `reduce()` below.
     #[inline(always)]
     fn parity_dot<const N: usize>(u: [i64; N], v: [i64; N]) -> i64 {
+        debug_assert!(
+            dot_product_checked(u, v).is_some(),
+            "parity_dot overflowed i64"
+        );
         dot_product(u, v)
     }
 
-    /// The assumptions above mean z < N^2 * 2^55, which is at most
-    /// 2^63 when N <= 16.
+    /// The assumptions above mean z < N * 2^55, as for the dot
+    /// products, which is at most 2^59 when N <= 16.
     ///
     /// Because the LHS elements were in Monty form and the RHS
     /// elements were plain integers, reduction is simply the usual
@@ -67,13 +85,35 @@ impl<FP: MontyParameters> Convolve<MontyField31<FP>, i64, i64, i64> for SmallCon
     }
 }
 
+impl SmallConvolveMontyField31 {
+    /// Same as `apply`, but first checks, in debug builds, that
+    /// `first_col` is small enough for the `i64` accumulators.
+    #[inline(always)]
+    fn apply_checked<FP: MontyParameters, const N: usize, C: Fn([i64; N], [i64; N], &mut [i64])>(
+        input: [MontyField31<FP>; N],
+        first_col: [i64; N],
+        conv: C,
+    ) -> [MontyField31<FP>; N] {
+        debug_assert!(
+            small_conv_is_safe(N, row_abs_sum(&first_col)),
+            "matrix too large for a small convolution of size {}",
+            N
+        );
+        <Self as Convolve<MontyField31<FP>, i64, i64, i64>>::apply(input, first_col, conv)
+    }
+}
+
 /// Given |x| < 2^80 compute x' such that:
 /// |x'| < 2**50
 /// x' = x mod p
 /// x' = x mod 2^10
 /// See Thm 1 (Below function) for a proof that this function is correct.
+///
+/// This is the reduction used by `LargeConvolveMontyField31::parity_dot`;
+/// since it preserves the low bits, its output can keep being added to
+/// and halved before the final reduction.
 #[inline(always)]
-fn barrett_red_monty31<BP: BarrettParameters>(input: i128) -> i64 {
+pub fn barrett_red_monty31<BP: BarrettParameters>(input: i128) -> i64 {
     // input = input_low + beta*input_high
     // So input_high < 2**63 and fits in an i64.
     let input_high = (input >> BP::N) as i64; // input_high < input / beta < 2**{80 - N}
@@ -198,8 +238,10 @@ fn barrett_red_monty31<BP: BarrettParameters>(input: i128) -> i64 {
 ///
 /// Here "large" means the elements can be as big as the field
 /// characteristic, and the size N of the RHS is <= 64.
+/// As for `SmallConvolveMontyField31`, this is public for reuse in other
+/// lazily reduced arithmetic.
 #[derive(Debug, Clone, Default)]
-struct LargeConvolveMontyField31;
+pub struct LargeConvolveMontyField31;
 
 impl<FP> Convolve<MontyField31<FP>, i64, i64, i64> for LargeConvolveMontyField31
 where
@@ -210,6 +252,7 @@ where
     /// Note that MontyField31 elements are represented in Monty form.
     #[inline(always)]
     fn read(input: MontyField31<FP>) -> i64 {
+        debug_assert!(input.value < FP::PRIME);
         input.value as i64
     }
 
@@ -221,9 +264,7 @@ where
         // for N <= 64, as required by `barrett_red_monty31()`.
 
         let mut dp = 0i128;
-        for i in 0..N {
-            dp += u[i] as i128 * v[i] as i128;
-        }
+        dot_product_acc(&mut dp, &u, &v);
         barrett_red_monty31::<FP>(dp)
     }
 
@@ -264,11 +305,32 @@ where
     }
 }
 
+/// Multiply `input` by the circulant matrix with first column `first_col`, for any size `N` up
+/// to 64 supported by `Convolve::conv_n`, without taking the input out of Monty form.
+///
+/// As the entries of the matrix are plain integers, the product of the Monty form of `x` and an
+/// entry `c` is the Monty form of `cx`, so the convolution runs directly on the Monty
+/// representatives and the result only needs the usual reduction modulo `P`. This is the same
+/// approach used by `MdsMatrixMontyField31`, for arbitrary matrices. The entries of `first_col`
+/// must be less than `P` in absolute value.
+pub fn apply_circulant_monty_karat<FP: BarrettParameters, const N: usize>(
+    input: [MontyField31<FP>; N],
+    first_col: [i64; N],
+) -> [MontyField31<FP>; N] {
+    assert!(N <= 64, "unsupported convolution size {}", N);
+    debug_assert!(first_col
+        .iter()
+        .all(|c| c.unsigned_abs() < FP::PRIME as u64));
+    <LargeConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::apply_circulant_karat(
+        input, first_col,
+    )
+}
+
 impl<FP: MontyParameters, MU: MDSUtils> Permutation<[MontyField31<FP>; 8]>
     for MdsMatrixMontyField31<MU>
 {
     fn permute(&self, input: [MontyField31<FP>; 8]) -> [MontyField31<FP>; 8] {
-        SmallConvolveMontyField31::apply(
+        SmallConvolveMontyField31::apply_checked(
             input,
             MU::MATRIX_CIRC_MDS_8_COL,
             <SmallConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::conv8,
@@ -288,7 +350,7 @@ impl<FP: MontyParameters, MU: MDSUtils> Permutation<[MontyField31<FP>; 12]>
     for MdsMatrixMontyField31<MU>
 {
     fn permute(&self, input: [MontyField31<FP>; 12]) -> [MontyField31<FP>; 12] {
-        SmallConvolveMontyField31::apply(
+        SmallConvolveMontyField31::apply_checked(
             input,
             MU::MATRIX_CIRC_MDS_12_COL,
             <SmallConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::conv12,
@@ -308,7 +370,7 @@ impl<FP: MontyParameters, MU: MDSUtils> Permutation<[MontyField31<FP>; 16]>
     for MdsMatrixMontyField31<MU>
 {
     fn permute(&self, input: [MontyField31<FP>; 16]) -> [MontyField31<FP>; 16] {
-        SmallConvolveMontyField31::apply(
+        SmallConvolveMontyField31::apply_checked(
             input,
             MU::MATRIX_CIRC_MDS_16_COL,
             <SmallConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::conv16,
@@ -349,11 +411,7 @@ impl<FP: BarrettParameters, MU: MDSUtils> Permutation<[MontyField31<FP>; 32]>
     for MdsMatrixMontyField31<MU>
 {
     fn permute(&self, input: [MontyField31<FP>; 32]) -> [MontyField31<FP>; 32] {
-        LargeConvolveMontyField31::apply(
-            input,
-            MU::MATRIX_CIRC_MDS_32_COL,
-            <LargeConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::conv32,
-        )
+        LargeConvolveMontyField31::apply_conv32_fused(input, MU::MATRIX_CIRC_MDS_32_COL)
     }
 
     fn permute_mut(&self, input: &mut [MontyField31<FP>; 32]) {
@@ -369,11 +427,7 @@ impl<FP: BarrettParameters, MU: MDSUtils> Permutation<[MontyField31<FP>; 64]>
     for MdsMatrixMontyField31<MU>
 {
     fn permute(&self, input: [MontyField31<FP>; 64]) -> [MontyField31<FP>; 64] {
-        LargeConvolveMontyField31::apply(
-            input,
-            MU::MATRIX_CIRC_MDS_64_COL,
-            <LargeConvolveMontyField31 as Convolve<MontyField31<FP>, i64, i64, i64>>::conv64,
-        )
+        LargeConvolveMontyField31::apply_conv64_fused(input, MU::MATRIX_CIRC_MDS_64_COL)
     }
 
     fn permute_mut(&self, input: &mut [MontyField31<FP>; 64]) {